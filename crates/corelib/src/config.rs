@@ -1,2 +1,178 @@
 //! Shared configuration structures and loading helpers.
 
+use crate::error::{Error, Result};
+use crate::partitioner::{
+    Blake3Partitioner, Murmur3Partitioner, RingPartitioner, SipPartitioner, Xxh3Partitioner,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Selects a ring partitioner by name.
+///
+/// Names are case-insensitive and accept either the short form or the
+/// partitioner's `name()`:
+///
+/// | Name | Partitioner | Notes |
+/// |------|-------------|-------|
+/// | `murmur3` | `Murmur3Partitioner` | default |
+/// | `xxh3` | `Xxh3Partitioner` | fastest, trusted keys only |
+/// | `blake3` | `Blake3Partitioner` | cryptographic digest |
+/// | `sip` | `SipPartitioner` | requires `key` (32 hex chars) |
+///
+/// # Example
+/// ```rust
+/// # use corelib::config::PartitionerConfig;
+/// # use corelib::ring::HashRing;
+/// let config = PartitionerConfig::keyed("sip", "000102030405060708090a0b0c0d0e0f");
+/// let ring = HashRing::with_partitioner(config.build().unwrap());
+/// assert_eq!(ring.partitioner_name(), "SipPartitioner");
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionerConfig {
+    /// Partitioner name (see the table above).
+    #[serde(default = "PartitionerConfig::default_name")]
+    pub name: String,
+    /// Secret 128-bit key as 32 hex characters (keyed partitioners only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl PartitionerConfig {
+    fn default_name() -> String {
+        "murmur3".to_string()
+    }
+
+    /// Config for an unkeyed partitioner.
+    pub fn named(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            key: None,
+        }
+    }
+
+    /// Config for a keyed partitioner.
+    pub fn keyed(name: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            key: Some(key.into()),
+        }
+    }
+
+    /// Instantiate the configured partitioner.
+    ///
+    /// # Errors
+    /// `Error::Config` if the name is unknown, a keyed partitioner has no
+    /// (or a malformed) key, or an unkeyed partitioner is given one.
+    pub fn build(&self) -> Result<Arc<RingPartitioner>> {
+        let name = self.name.to_ascii_lowercase();
+        let keyed = matches!(name.as_str(), "sip" | "sippartitioner");
+        if !keyed && self.key.is_some() {
+            return Err(Error::Config(format!(
+                "partitioner '{}' does not take a key",
+                self.name
+            )));
+        }
+
+        let partitioner: Arc<RingPartitioner> = match name.as_str() {
+            "murmur3" | "murmur3partitioner" => Arc::new(Murmur3Partitioner),
+            "xxh3" | "xxh3partitioner" => Arc::new(Xxh3Partitioner),
+            "blake3" | "blake3partitioner" => Arc::new(Blake3Partitioner),
+            "sip" | "sippartitioner" => {
+                let key = self.key.as_deref().ok_or_else(|| {
+                    Error::Config(format!("partitioner '{}' requires a key", self.name))
+                })?;
+                Arc::new(SipPartitioner::new(parse_key(key)?))
+            }
+            _ => {
+                return Err(Error::Config(format!(
+                    "unknown partitioner '{}' (expected murmur3, xxh3, blake3 or sip)",
+                    self.name
+                )))
+            }
+        };
+        Ok(partitioner)
+    }
+}
+
+impl Default for PartitionerConfig {
+    fn default() -> Self {
+        Self::named(Self::default_name())
+    }
+}
+
+// Keys are secrets: never print them
+impl std::fmt::Debug for PartitionerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PartitionerConfig")
+            .field("name", &self.name)
+            .field("key", &self.key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// Parse a 128-bit key from 32 hex characters.
+fn parse_key(hex: &str) -> Result<[u8; 16]> {
+    let invalid = || Error::Config("partitioner key must be 32 hex characters".to_string());
+    // Checked up front: `from_str_radix` would also accept a '+' sign
+    if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let mut key = [0u8; 16];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+
+    #[test]
+    fn test_build_by_name() {
+        for (name, expected) in [
+            ("murmur3", "Murmur3Partitioner"),
+            ("XXH3", "Xxh3Partitioner"),
+            ("Blake3Partitioner", "Blake3Partitioner"),
+        ] {
+            let partitioner = PartitionerConfig::named(name).build().unwrap();
+            assert_eq!(partitioner.name(), expected);
+        }
+        let sip = PartitionerConfig::keyed("sip", KEY).build().unwrap();
+        assert_eq!(sip.name(), "SipPartitioner");
+        assert_eq!(
+            PartitionerConfig::default().build().unwrap().name(),
+            "Murmur3Partitioner"
+        );
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        assert!(PartitionerConfig::named("md5").build().is_err());
+        assert!(PartitionerConfig::named("sip").build().is_err());
+        assert!(PartitionerConfig::keyed("sip", "abcd").build().is_err());
+        assert!(PartitionerConfig::keyed("sip", KEY.replace('0', "g"))
+            .build()
+            .is_err());
+        assert!(PartitionerConfig::keyed("sip", KEY.replacen("00", "+f", 1))
+            .build()
+            .is_err());
+        assert!(PartitionerConfig::keyed("xxh3", KEY).build().is_err());
+    }
+
+    #[test]
+    fn test_key_changes_tokens_and_is_redacted() {
+        let a = PartitionerConfig::keyed("sip", KEY).build().unwrap();
+        let b = PartitionerConfig::keyed("sip", "ff".repeat(16))
+            .build()
+            .unwrap();
+        assert_ne!(a.partition(b"key"), b.partition(b"key"));
+        assert_eq!(a.partition(b"key"), a.partition(b"key"));
+
+        let debug = format!("{:?}", PartitionerConfig::keyed("sip", KEY));
+        assert!(!debug.contains(KEY));
+    }
+}
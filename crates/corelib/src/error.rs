@@ -16,6 +16,12 @@ pub enum Error {
     RingOperation(String),
     /// Topology error
     Topology(String),
+    /// Invalid configuration
+    Config(String),
+    /// Encoding or decoding failed
+    Serialization(String),
+    /// Persistent storage I/O or corruption
+    Storage(String),
     /// Internal error
     Internal(String),
 }
@@ -27,6 +33,9 @@ impl fmt::Display for Error {
             Error::InvalidNode(msg) => write!(f, "Invalid node: {}", msg),
             Error::RingOperation(msg) => write!(f, "Ring operation failed: {}", msg),
             Error::Topology(msg) => write!(f, "Topology error: {}", msg),
+            Error::Config(msg) => write!(f, "Configuration error: {}", msg),
+            Error::Serialization(msg) => write!(f, "Serialization error: {}", msg),
+            Error::Storage(msg) => write!(f, "Storage error: {}", msg),
             Error::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
     }
@@ -6,10 +6,13 @@
 //! - Ring position management
 //! - Node and virtual node abstractions
 //! - Ring topology and routing
+//! - Alternative placement algorithms (jump hash) behind a common trait
 
+pub mod config;
 pub mod error;
 pub mod node;
 pub mod partitioner;
+pub mod placement;
 pub mod ring;
 pub mod token;
 pub mod topology;
@@ -18,6 +21,7 @@ pub mod vnode;
 pub use error::{Error, Result};
 pub use node::{Node, NodeId};
 pub use partitioner::Partitioner;
+pub use placement::Placement;
 pub use ring::{Ring, RingBuilder};
 pub use token::Token;
 pub use topology::Topology;
@@ -3,13 +3,14 @@
 //! Nodes represent logical participants in the ring. They are identified by a
 //! compact `NodeId` that is cheap to compare and hash.
 
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Compact identifier for a node in the cluster.
 ///
 /// Newtype over `u128` so comparisons and hashing are very fast while giving
 /// plenty of space for uniqueness.
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct NodeId(pub u128);
 
 impl fmt::Display for NodeId {
@@ -22,7 +23,7 @@ impl fmt::Display for NodeId {
 ///
 /// Keep this struct small and cheap to clone; heavy mutable state (connections,
 /// metrics, etc.) should live elsewhere.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Node {
     pub id: NodeId,
     /// Human‑readable name or hostname.
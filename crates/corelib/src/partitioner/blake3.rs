@@ -0,0 +1,36 @@
+//! BLAKE3 partitioner implementation.
+
+use crate::partitioner::traits::Partitioner;
+use crate::token::murmur3::Murmur3Token;
+use crate::token::Token;
+
+/// BLAKE3 partitioner: cryptographic-quality token distribution.
+///
+/// The token is the first 8 bytes (little-endian) of the BLAKE3 digest.
+/// Slower than `Xxh3Partitioner`, but collisions and skew can't be
+/// engineered without breaking BLAKE3. Tokens share the `Murmur3Token` space.
+#[derive(Clone, Debug, Default)]
+pub struct Blake3Partitioner;
+
+impl Partitioner for Blake3Partitioner {
+    type TokenType = Murmur3Token;
+
+    fn partition(&self, key: &[u8]) -> Self::TokenType {
+        let digest = ::blake3::hash(key);
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest.as_bytes()[..8]);
+        Murmur3Token(u64::from_le_bytes(prefix))
+    }
+
+    fn min_token(&self) -> Self::TokenType {
+        Murmur3Token::zero()
+    }
+
+    fn max_token(&self) -> Self::TokenType {
+        <Murmur3Token as Token>::max()
+    }
+
+    fn name(&self) -> &'static str {
+        "Blake3Partitioner"
+    }
+}
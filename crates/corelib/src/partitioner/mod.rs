@@ -2,10 +2,31 @@
 //!
 //! Partitioners are responsible for converting keys into tokens
 //! that can be placed on the hash ring.
+//!
+//! Partitioners producing 64-bit `Murmur3Token`s can drive a `HashRing`:
+//!
+//! - **Murmur3Partitioner**: default, Cassandra-compatible naming
+//! - **Xxh3Partitioner**: fastest, for trusted internal keys
+//! - **Blake3Partitioner**: cryptographic digest, engineered skew is infeasible
+//! - **SipPartitioner**: keyed with a 128-bit secret, for untrusted keys
+//!
+//! `config::PartitionerConfig` selects one of these by name.
 
+pub mod blake3;
+pub mod byte_ordered;
 pub mod murmur3;
 pub mod random;
-pub mod byte_ordered;
+pub mod sip;
 pub mod traits;
+pub mod xxh3;
 
+pub use self::blake3::Blake3Partitioner;
+pub use murmur3::Murmur3Partitioner;
+pub use sip::SipPartitioner;
 pub use traits::Partitioner;
+pub use xxh3::Xxh3Partitioner;
+
+use crate::token::murmur3::Murmur3Token;
+
+/// Any partitioner that can place keys on a `HashRing`.
+pub type RingPartitioner = dyn Partitioner<TokenType = Murmur3Token>;
@@ -0,0 +1,74 @@
+//! Keyed SipHash partitioner implementation.
+
+use crate::partitioner::traits::Partitioner;
+use crate::token::murmur3::Murmur3Token;
+use crate::token::Token;
+use siphasher::sip::SipHasher13;
+use std::fmt;
+use std::hash::Hasher;
+
+/// Keyed SipHash-1-3 partitioner for untrusted keys.
+///
+/// Without the 128-bit secret key, clients can't predict which tokens their
+/// keys hash to, so they can't pile keys onto one node (hash flooding).
+/// Every process that shares a ring must use the same key, and changing the
+/// key moves every key on the ring.
+///
+/// The key is redacted from `Debug` output.
+///
+/// # Example
+/// ```rust
+/// # use corelib::partitioner::{Partitioner, SipPartitioner};
+/// let partitioner = SipPartitioner::new([7u8; 16]);
+/// let other = SipPartitioner::new([8u8; 16]);
+/// assert_ne!(partitioner.partition(b"key"), other.partition(b"key"));
+/// ```
+#[derive(Clone)]
+pub struct SipPartitioner {
+    key: [u8; 16],
+}
+
+impl SipPartitioner {
+    /// Create a partitioner with a 128-bit secret key.
+    pub fn new(key: [u8; 16]) -> Self {
+        Self { key }
+    }
+
+    /// Create a partitioner from two 64-bit key halves.
+    pub fn from_keys(k0: u64, k1: u64) -> Self {
+        let mut key = [0u8; 16];
+        key[..8].copy_from_slice(&k0.to_le_bytes());
+        key[8..].copy_from_slice(&k1.to_le_bytes());
+        Self::new(key)
+    }
+}
+
+impl fmt::Debug for SipPartitioner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SipPartitioner")
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Partitioner for SipPartitioner {
+    type TokenType = Murmur3Token;
+
+    fn partition(&self, key: &[u8]) -> Self::TokenType {
+        let mut hasher = SipHasher13::new_with_key(&self.key);
+        hasher.write(key);
+        Murmur3Token(hasher.finish())
+    }
+
+    fn min_token(&self) -> Self::TokenType {
+        Murmur3Token::zero()
+    }
+
+    fn max_token(&self) -> Self::TokenType {
+        <Murmur3Token as Token>::max()
+    }
+
+    fn name(&self) -> &'static str {
+        "SipPartitioner"
+    }
+}
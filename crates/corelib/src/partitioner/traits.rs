@@ -6,8 +6,6 @@ use crate::token::Token;
 ///
 /// Partitioners are stateless and thread-safe, allowing concurrent
 /// token generation without synchronization overhead.
-
-
 pub trait Partitioner: Send + Sync + 'static {
     /// The token type produced by this partitioner.
     type TokenType: Token;
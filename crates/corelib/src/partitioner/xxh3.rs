@@ -0,0 +1,34 @@
+//! XXH3 partitioner implementation.
+
+use crate::partitioner::traits::Partitioner;
+use crate::token::murmur3::Murmur3Token;
+use crate::token::Token;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// XXH3 partitioner: the fastest option for trusted, internal keys.
+///
+/// Produces 64-bit tokens in the same space as `Murmur3Partitioner`, so it
+/// can drive a `HashRing` directly. XXH3 is not keyed; use `SipPartitioner`
+/// for keys chosen by untrusted clients.
+#[derive(Clone, Debug, Default)]
+pub struct Xxh3Partitioner;
+
+impl Partitioner for Xxh3Partitioner {
+    type TokenType = Murmur3Token;
+
+    fn partition(&self, key: &[u8]) -> Self::TokenType {
+        Murmur3Token(xxh3_64(key))
+    }
+
+    fn min_token(&self) -> Self::TokenType {
+        Murmur3Token::zero()
+    }
+
+    fn max_token(&self) -> Self::TokenType {
+        <Murmur3Token as Token>::max()
+    }
+
+    fn name(&self) -> &'static str {
+        "Xxh3Partitioner"
+    }
+}
@@ -0,0 +1,375 @@
+//! AnchorHash placement (Mendelson, Vargaftik, Barabash et al., 2020).
+//!
+//! # Algorithm
+//!
+//! The "anchor" is a fixed set of `a` buckets (the capacity); at any time a
+//! subset of them are working. A key first hashes to a bucket in `[0, a)`.
+//! If that bucket was removed, the key re-hashes into the working set *as it
+//! was when the bucket was removed* (recorded in `A[b]`), following
+//! replacement chains (`K`) for buckets removed later still. Because each
+//! removal only redirects the removed bucket's keys, every other key stays
+//! where it was.
+//!
+//! ```text
+//! A[b]  0 if b is working, else working-set size right after b was removed
+//! W     working set, positions [0, N) hold the working buckets
+//! L[b]  position of b in W
+//! K[b]  bucket that took b's position in W when b was removed
+//! R     stack of removed buckets (add pops the most recently removed)
+//! ```
+//!
+//! # Properties
+//!
+//! - **Lookup**: O(1) expected (O(ln(a/N)) rehashes), no allocation
+//! - **Memory**: O(a) - four small arrays sized by capacity
+//! - **Disruption**: minimal - removing *any* bucket only moves its keys,
+//!   adding a bucket only moves keys onto it
+//! - **Balance**: keys spread uniformly over the working buckets
+//!
+//! Unlike `JumpHash`, arbitrary nodes can fail. Re-adding restores the most
+//! recently removed bucket first, so fail/recover sequences undo cleanly.
+
+use crate::error::{Error, Result};
+use crate::node::{Node, NodeId};
+use crate::partitioner::murmur3::Murmur3Partitioner;
+use crate::partitioner::traits::Partitioner;
+use crate::placement::traits::Placement;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+/// The AnchorHash bucket state machine (buckets are plain indices).
+#[derive(Clone, Debug)]
+struct Anchor {
+    a: Vec<u32>,
+    w: Vec<u32>,
+    l: Vec<u32>,
+    k: Vec<u32>,
+    r: Vec<u32>,
+    /// Number of working buckets.
+    n: u32,
+}
+
+impl Anchor {
+    /// Anchor of `capacity` buckets, none working.
+    ///
+    /// The removal stack is seeded so buckets are handed out in order
+    /// 0, 1, 2, ... as they are added.
+    fn new(capacity: u32) -> Self {
+        let buckets: Vec<u32> = (0..capacity).collect();
+        Self {
+            a: buckets.clone(),
+            w: buckets.clone(),
+            l: buckets.clone(),
+            k: buckets.clone(),
+            r: buckets.into_iter().rev().collect(),
+            n: 0,
+        }
+    }
+
+    fn capacity(&self) -> u32 {
+        self.a.len() as u32
+    }
+
+    /// Working bucket for a key.
+    ///
+    /// `token` picks the initial bucket; rehashes are seeded by the removed
+    /// bucket so each removal gives an independent redirect.
+    fn bucket(&self, key: &[u8], token: u64) -> Option<u32> {
+        if self.n == 0 {
+            return None;
+        }
+        let mut b = (token % self.capacity() as u64) as u32;
+        while self.a[b as usize] > 0 {
+            let mut h = (xxh3_64_with_seed(key, b as u64) % self.a[b as usize] as u64) as u32;
+            // Buckets removed after b (smaller A) point at their replacement
+            while self.a[h as usize] >= self.a[b as usize] {
+                h = self.k[h as usize];
+            }
+            b = h;
+        }
+        Some(b)
+    }
+
+    /// Mark a working bucket as removed.
+    fn remove(&mut self, b: u32) {
+        self.r.push(b);
+        self.n -= 1;
+        let last = self.w[self.n as usize];
+        self.a[b as usize] = self.n;
+        self.w[self.l[b as usize] as usize] = last;
+        self.l[last as usize] = self.l[b as usize];
+        self.k[b as usize] = last;
+    }
+
+    /// Restore the most recently removed bucket.
+    fn add(&mut self) -> Option<u32> {
+        let b = self.r.pop()?;
+        self.a[b as usize] = 0;
+        let last = self.w[self.n as usize];
+        self.l[last as usize] = self.n;
+        self.w[self.l[b as usize] as usize] = b;
+        self.k[b as usize] = b;
+        self.n += 1;
+        Some(b)
+    }
+}
+
+#[derive(Debug)]
+struct AnchorInner {
+    anchor: Anchor,
+    /// Bucket → node (None for removed buckets).
+    buckets: Vec<Option<Node>>,
+    /// Node → bucket.
+    index: HashMap<NodeId, u32>,
+}
+
+/// AnchorHash placement over `NodeId`s with a fixed capacity.
+///
+/// # Thread Safety
+///
+/// Same model as `HashRing`: cheap to clone (`Arc` inside), concurrent
+/// lookups under a read lock, mutations under a write lock.
+///
+/// # Example
+///
+/// ```rust
+/// use corelib::placement::{AnchorHash, Placement};
+/// use corelib::{Node, NodeId};
+///
+/// let anchor = AnchorHash::new(16);
+/// for i in 0..4 {
+///     anchor.add_node(Node::new(NodeId(i), format!("queue-{}", i))).unwrap();
+/// }
+///
+/// let owner = anchor.lookup(b"message-key").unwrap();
+///
+/// // Any node can fail; only its keys move
+/// let failed = if owner == NodeId(2) { NodeId(1) } else { NodeId(2) };
+/// anchor.remove_node(&failed);
+/// assert_eq!(anchor.lookup(b"message-key"), Some(owner));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AnchorHash {
+    inner: Arc<RwLock<AnchorInner>>,
+    partitioner: Murmur3Partitioner,
+}
+
+impl AnchorHash {
+    /// Create an empty anchor that can hold up to `capacity` nodes.
+    ///
+    /// Lookup cost grows with `ln(capacity / nodes)`, so keep the capacity
+    /// within a small multiple of the expected node count.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is 0 or does not fit in a `u32`.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "AnchorHash requires a positive capacity");
+        let capacity = u32::try_from(capacity).expect("AnchorHash capacity must fit in u32");
+        Self {
+            inner: Arc::new(RwLock::new(AnchorInner {
+                anchor: Anchor::new(capacity),
+                buckets: vec![None; capacity as usize],
+                index: HashMap::new(),
+            })),
+            partitioner: Murmur3Partitioner,
+        }
+    }
+
+    /// Add a node to the most recently removed bucket.
+    ///
+    /// If the node already exists, its metadata is updated in place.
+    ///
+    /// # Returns
+    /// The bucket the node occupies
+    ///
+    /// # Errors
+    /// `Error::Topology` if all `capacity` buckets are in use.
+    pub fn add_node(&self, node: Node) -> Result<usize> {
+        let mut inner = self.inner.write();
+        if let Some(&bucket) = inner.index.get(&node.id) {
+            inner.buckets[bucket as usize] = Some(node);
+            return Ok(bucket as usize);
+        }
+
+        let bucket = inner.anchor.add().ok_or_else(|| {
+            Error::Topology(format!(
+                "AnchorHash is full ({} buckets), cannot add node {}",
+                inner.anchor.capacity(),
+                node.id
+            ))
+        })?;
+        inner.index.insert(node.id, bucket);
+        inner.buckets[bucket as usize] = Some(node);
+        Ok(bucket as usize)
+    }
+
+    /// Remove a node; only keys it owned move.
+    ///
+    /// # Returns
+    /// `true` if the node was removed, `false` if it didn't exist
+    pub fn remove_node(&self, node_id: &NodeId) -> bool {
+        let mut inner = self.inner.write();
+        match inner.index.remove(node_id) {
+            Some(bucket) => {
+                inner.anchor.remove(bucket);
+                inner.buckets[bucket as usize] = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Node responsible for a key.
+    ///
+    /// # Performance
+    /// - **Time**: O(1) expected
+    ///
+    /// # Returns
+    /// The responsible node, or `None` if no nodes are working
+    pub fn lookup(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup_node(key).map(|node| node.id)
+    }
+
+    /// Look up the node and return full Node metadata.
+    pub fn lookup_node(&self, key: &[u8]) -> Option<Node> {
+        let token = self.partitioner.partition(key);
+        let inner = self.inner.read();
+        let bucket = inner.anchor.bucket(key, token.0)?;
+        inner.buckets[bucket as usize].clone()
+    }
+
+    /// Bucket occupied by a node.
+    pub fn bucket_of(&self, node_id: &NodeId) -> Option<usize> {
+        self.inner
+            .read()
+            .index
+            .get(node_id)
+            .map(|&bucket| bucket as usize)
+    }
+
+    /// Get node metadata by ID.
+    pub fn get_node(&self, node_id: &NodeId) -> Option<Node> {
+        let inner = self.inner.read();
+        let &bucket = inner.index.get(node_id)?;
+        inner.buckets[bucket as usize].clone()
+    }
+
+    /// Get all nodes.
+    pub fn nodes(&self) -> Vec<Node> {
+        self.inner
+            .read()
+            .buckets
+            .iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Get the number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.inner.read().index.len()
+    }
+
+    /// Maximum number of nodes.
+    pub fn capacity(&self) -> usize {
+        self.inner.read().anchor.capacity() as usize
+    }
+}
+
+impl Placement for AnchorHash {
+    fn locate(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup(key)
+    }
+
+    fn node_count(&self) -> usize {
+        AnchorHash::node_count(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "AnchorHash"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populate(anchor: &AnchorHash, n: u128) {
+        for i in 0..n {
+            anchor
+                .add_node(Node::new(NodeId(i), format!("node{}", i)))
+                .unwrap();
+        }
+    }
+
+    fn owners(anchor: &AnchorHash, keys: usize) -> Vec<NodeId> {
+        (0..keys)
+            .map(|i| anchor.lookup(format!("key-{}", i).as_bytes()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_arbitrary_removal_is_minimal() {
+        let anchor = AnchorHash::new(32);
+        populate(&anchor, 10);
+        let before = owners(&anchor, 10_000);
+
+        assert!(anchor.remove_node(&NodeId(3)));
+        assert!(anchor.remove_node(&NodeId(7)));
+        let after = owners(&anchor, 10_000);
+
+        for (old, new) in before.iter().zip(&after) {
+            if *old != NodeId(3) && *old != NodeId(7) {
+                assert_eq!(old, new);
+            } else {
+                assert!(*new != NodeId(3) && *new != NodeId(7));
+            }
+        }
+    }
+
+    #[test]
+    fn test_readd_restores_assignment() {
+        let anchor = AnchorHash::new(16);
+        populate(&anchor, 8);
+        let before = owners(&anchor, 5_000);
+
+        anchor.remove_node(&NodeId(5));
+        anchor.remove_node(&NodeId(1));
+        anchor.add_node(Node::new(NodeId(1), "node1")).unwrap();
+        anchor.add_node(Node::new(NodeId(5), "node5")).unwrap();
+
+        assert_eq!(owners(&anchor, 5_000), before);
+    }
+
+    #[test]
+    fn test_balance() {
+        let anchor = AnchorHash::new(64);
+        populate(&anchor, 16);
+        anchor.remove_node(&NodeId(0));
+        anchor.remove_node(&NodeId(9));
+
+        let mut counts: HashMap<NodeId, usize> = HashMap::new();
+        for owner in owners(&anchor, 70_000) {
+            *counts.entry(owner).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 14);
+        // 5_000 expected per node; allow 10%
+        for count in counts.values() {
+            assert!((4_500..=5_500).contains(count), "count = {}", count);
+        }
+    }
+
+    #[test]
+    fn test_capacity_limits() {
+        let anchor = AnchorHash::new(2);
+        assert_eq!(anchor.lookup(b"key"), None);
+
+        populate(&anchor, 2);
+        assert!(anchor.add_node(Node::new(NodeId(9), "overflow")).is_err());
+        // Re-adding an existing node only updates metadata
+        assert_eq!(anchor.add_node(Node::new(NodeId(1), "renamed")).unwrap(), 1);
+        assert_eq!(anchor.get_node(&NodeId(1)).unwrap().name, "renamed");
+    }
+}
@@ -0,0 +1,217 @@
+//! Jump consistent hash placement (Lamping & Veach, 2014).
+//!
+//! # Algorithm
+//!
+//! Jump hash maps a 64-bit key to a bucket in `[0, n)` using a tiny
+//! pseudo-random walk: starting at bucket 0, it repeatedly "jumps" forward
+//! to the next bucket the key would move to as buckets are added, stopping
+//! once the jump lands past `n - 1`.
+//!
+//! # Properties
+//!
+//! - **Memory**: O(1) - no ring, no tokens (just the ordered node list)
+//! - **Lookup**: O(log n) expected iterations, no locks, no allocation
+//! - **Balance**: perfect in expectation (each bucket gets 1/n of keys)
+//! - **Disruption**: growing from n to n+1 buckets moves exactly the keys
+//!   that land in the new bucket (~1/(n+1) of keys)
+//!
+//! # Limitations
+//!
+//! Buckets are positional: only the **last** node can be removed without
+//! reshuffling. Use `HashRing` when arbitrary nodes can leave, or a
+//! failure-tolerant scheme on top of jump hash.
+
+use crate::node::{Node, NodeId};
+use crate::partitioner::murmur3::Murmur3Partitioner;
+use crate::partitioner::traits::Partitioner;
+use crate::placement::traits::Placement;
+
+/// Jump consistent hash over a key already hashed to 64 bits.
+///
+/// # Arguments
+/// * `key` - 64-bit key hash
+/// * `buckets` - Number of buckets (must be > 0)
+///
+/// # Returns
+/// Bucket index in `[0, buckets)`
+///
+/// # Panics
+/// Panics if `buckets` is 0.
+///
+/// # Example
+/// ```rust
+/// # use corelib::placement::jump::jump_hash;
+/// let bucket = jump_hash(0xdead_beef, 10);
+/// assert!(bucket < 10);
+/// ```
+pub fn jump_hash(mut key: u64, buckets: usize) -> usize {
+    assert!(buckets > 0, "jump_hash requires at least one bucket");
+
+    let mut b: i64 = -1;
+    let mut j: i64 = 0;
+    while j < buckets as i64 {
+        b = j;
+        // 64-bit LCG step from the paper
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as usize
+}
+
+/// Jump consistent hash placement over an ordered, append-only node list.
+///
+/// Keys are hashed with the `Murmur3Partitioner` (same token space as
+/// `HashRing`) and the token is fed to `jump_hash()` to pick a node by
+/// position.
+///
+/// # Thread Safety
+///
+/// `JumpHash` is immutable during lookups; mutation (`push`/`pop`) needs
+/// `&mut self`. Share behind `Arc` for read-only use, or rebuild and swap.
+///
+/// # Example
+///
+/// ```rust
+/// use corelib::placement::{JumpHash, Placement};
+/// use corelib::{Node, NodeId};
+///
+/// let mut jump = JumpHash::new(vec![
+///     Node::new(NodeId(1), "shard-0"),
+///     Node::new(NodeId(2), "shard-1"),
+/// ]);
+/// jump.push(Node::new(NodeId(3), "shard-2"));
+///
+/// let node_id = jump.locate(b"my-key").unwrap();
+/// assert!([NodeId(1), NodeId(2), NodeId(3)].contains(&node_id));
+/// ```
+#[derive(Clone, Debug)]
+pub struct JumpHash {
+    /// Ordered buckets: `nodes[i]` owns bucket `i`.
+    nodes: Vec<Node>,
+    /// Key → 64-bit token hashing.
+    partitioner: Murmur3Partitioner,
+}
+
+impl JumpHash {
+    /// Create a jump hash over the given nodes (bucket order = vec order).
+    ///
+    /// # Performance
+    /// - **Time**: O(1) - takes ownership of the list
+    /// - **Space**: O(n) for node metadata only
+    pub fn new(nodes: Vec<Node>) -> Self {
+        Self {
+            nodes,
+            partitioner: Murmur3Partitioner,
+        }
+    }
+
+    /// Append a node as the new last bucket.
+    ///
+    /// Only keys that move to the new bucket change owner (~1/(n+1)).
+    pub fn push(&mut self, node: Node) {
+        self.nodes.push(node);
+    }
+
+    /// Remove the last bucket.
+    ///
+    /// Only keys that lived in the last bucket change owner.
+    ///
+    /// # Returns
+    /// The removed node, or `None` if empty
+    pub fn pop(&mut self) -> Option<Node> {
+        self.nodes.pop()
+    }
+
+    /// Bucket index for a key.
+    ///
+    /// # Returns
+    /// Index into `nodes()`, or `None` if there are no nodes
+    #[inline]
+    pub fn bucket_for(&self, key: &[u8]) -> Option<usize> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let token = self.partitioner.partition(key);
+        Some(jump_hash(token.0, self.nodes.len()))
+    }
+
+    /// Look up the node metadata responsible for a key.
+    pub fn lookup_node(&self, key: &[u8]) -> Option<&Node> {
+        self.bucket_for(key).map(|bucket| &self.nodes[bucket])
+    }
+
+    /// The ordered node list (bucket `i` is `nodes()[i]`).
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+}
+
+impl Placement for JumpHash {
+    fn locate(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup_node(key).map(|node| node.id)
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn name(&self) -> &'static str {
+        "JumpHash"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(n: u128) -> Vec<Node> {
+        (0..n)
+            .map(|i| Node::new(NodeId(i), format!("shard-{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn test_jump_hash_single_bucket() {
+        for key in [0, 1, 42, u64::MAX] {
+            assert_eq!(jump_hash(key, 1), 0);
+        }
+    }
+
+    #[test]
+    fn test_jump_hash_minimal_movement() {
+        // Growing n -> n+1 only moves keys into the new bucket
+        for key in 0..10_000u64 {
+            let key = key.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            let before = jump_hash(key, 10);
+            let after = jump_hash(key, 11);
+            assert!(after == before || after == 10);
+        }
+    }
+
+    #[test]
+    fn test_jump_hash_balance() {
+        let mut counts = [0usize; 8];
+        let jump = JumpHash::new(nodes(8));
+        for i in 0..80_000 {
+            let bucket = jump.bucket_for(format!("key-{}", i).as_bytes()).unwrap();
+            counts[bucket] += 1;
+        }
+        // Each bucket expects 10_000; allow 5%
+        for count in counts {
+            assert!((9_500..=10_500).contains(&count), "count = {}", count);
+        }
+    }
+
+    #[test]
+    fn test_jump_placement_push_pop() {
+        let mut jump = JumpHash::new(Vec::new());
+        assert_eq!(jump.locate(b"key"), None);
+
+        jump.push(Node::new(NodeId(7), "only"));
+        assert_eq!(jump.locate(b"key"), Some(NodeId(7)));
+        assert_eq!(jump.node_count(), 1);
+
+        assert_eq!(jump.pop().map(|n| n.id), Some(NodeId(7)));
+        assert_eq!(jump.node_count(), 0);
+    }
+}
@@ -0,0 +1,608 @@
+//! Maglev consistent hashing lookup tables (Eisenbud et al., NSDI 2016).
+//!
+//! # Algorithm
+//!
+//! Every node gets a pseudo-random permutation of the table slots derived
+//! from two hashes of its ID (`offset` and `skip`). Nodes then take turns
+//! claiming their next preferred empty slot until the table is full.
+//! Lookups hash the key and index the table: **O(1)**, no tree walk.
+//!
+//! # Properties
+//!
+//! - **Lookup**: O(1) - one hash, one array read
+//! - **Balance**: near perfect - each node's entry count differs by at most
+//!   one turn from its weighted share
+//! - **Build**: O(M log M) expected for table size M (O(M) per rebuild with
+//!   cached permutations)
+//! - **Disruption**: small but non-zero; `Disruption` reports exactly how
+//!   many entries moved compared to the theoretical minimum
+//!
+//! # Weights
+//!
+//! Weighted population follows the approach used by production Maglev
+//! implementations: a node with half the maximum weight only takes a turn
+//! every other round, so entry counts are proportional to weight.
+//!
+//! # Table Size
+//!
+//! The size must be prime (so every `skip` generates a full permutation) and
+//! larger than the node count; ~100x the node count keeps imbalance below 1%.
+//! `DEFAULT_TABLE_SIZE` (65537) suits up to a few hundred nodes.
+
+use crate::error::{Error, Result};
+use crate::node::{Node, NodeId};
+use crate::partitioner::murmur3::Murmur3Partitioner;
+use crate::partitioner::traits::Partitioner;
+use crate::placement::traits::Placement;
+use crate::ring::HashRing;
+use std::collections::HashMap;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+/// Default lookup table size (prime).
+pub const DEFAULT_TABLE_SIZE: usize = 65_537;
+
+/// Hash seed for a node's permutation offset.
+const OFFSET_SEED: u64 = 0x6d61_676c_6576_0001;
+/// Hash seed for a node's permutation skip.
+const SKIP_SEED: u64 = 0x6d61_676c_6576_0002;
+
+/// Trial-division primality test (table sizes are small enough).
+fn is_prime(n: usize) -> bool {
+    if n < 2 {
+        return false;
+    }
+    if n.is_multiple_of(2) {
+        return n == 2;
+    }
+    let mut d = 3;
+    while d * d <= n {
+        if n.is_multiple_of(d) {
+            return false;
+        }
+        d += 2;
+    }
+    true
+}
+
+/// A node's entry in the table build: metadata, weight and permutation.
+#[derive(Clone, Debug)]
+struct Backend {
+    node: Node,
+    weight: u32,
+    /// First preferred slot.
+    offset: usize,
+    /// Stride between preferred slots (in `[1, M)`).
+    skip: usize,
+}
+
+impl Backend {
+    fn new(node: Node, weight: u32, size: usize) -> Self {
+        let id = node.id.0.to_le_bytes();
+        let offset = (xxh3_64_with_seed(&id, OFFSET_SEED) % size as u64) as usize;
+        let skip = (xxh3_64_with_seed(&id, SKIP_SEED) % (size as u64 - 1) + 1) as usize;
+        Self {
+            node,
+            weight,
+            offset,
+            skip,
+        }
+    }
+}
+
+/// Disruption caused by rebuilding a table.
+///
+/// Maglev trades a little extra churn for O(1) lookups and near-perfect
+/// balance. `excess()` is how many entries moved beyond what any
+/// algorithm would have to move to reach the new entry counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disruption {
+    /// Entries whose owner changed.
+    pub changed_entries: usize,
+    /// Minimum entries that had to change to reach the new per-node counts.
+    pub minimum_entries: usize,
+    /// Table size.
+    pub total_entries: usize,
+}
+
+impl Disruption {
+    /// Fraction of keys that changed owner (0.0 - 1.0).
+    pub fn changed_fraction(&self) -> f64 {
+        self.changed_entries as f64 / self.total_entries as f64
+    }
+
+    /// Lowest achievable fraction for the same change in entry counts.
+    pub fn minimum_fraction(&self) -> f64 {
+        self.minimum_entries as f64 / self.total_entries as f64
+    }
+
+    /// Entries moved beyond the minimum.
+    pub fn excess(&self) -> usize {
+        self.changed_entries - self.minimum_entries
+    }
+}
+
+/// Result of an incremental rebuild: the new table and what it cost.
+#[derive(Clone, Debug)]
+pub struct Rebuild {
+    /// The rebuilt table.
+    pub table: MaglevTable,
+    /// How much the rebuild disrupted key assignment.
+    pub disruption: Disruption,
+}
+
+/// Immutable Maglev lookup table.
+///
+/// Tables are cheap to read from many threads and are replaced wholesale
+/// on membership change (`with_node`, `without_node`), typically by swapping
+/// an `Arc<MaglevTable>`.
+///
+/// # Example
+///
+/// ```rust
+/// use corelib::placement::maglev::{MaglevBuilder, MaglevTable};
+/// use corelib::{Node, NodeId};
+///
+/// let table = MaglevBuilder::new()
+///     .table_size(251)
+///     .add_node(Node::new(NodeId(1), "lb-1"))
+///     .add_node_weighted(Node::new(NodeId(2), "lb-2"), 2)
+///     .build()
+///     .unwrap();
+///
+/// let node_id = table.lookup(b"flow-5tuple").unwrap();
+///
+/// let rebuild = table.with_node(Node::new(NodeId(3), "lb-3"), 1).unwrap();
+/// assert!(rebuild.disruption.changed_fraction() < 0.5);
+/// # let _ = node_id;
+/// ```
+#[derive(Clone, Debug)]
+pub struct MaglevTable {
+    /// Backends sorted by NodeId (population order must be deterministic).
+    backends: Vec<Backend>,
+    /// Slot → index into `backends`.
+    table: Vec<u32>,
+    /// Key → 64-bit token hashing.
+    partitioner: Murmur3Partitioner,
+}
+
+impl MaglevTable {
+    /// Build a table with `DEFAULT_TABLE_SIZE` from the nodes of a `HashRing`.
+    ///
+    /// Every node gets weight 1 (vnode counts are not carried over).
+    ///
+    /// # Errors
+    /// See `MaglevBuilder::build()`.
+    pub fn from_ring(ring: &HashRing) -> Result<Self> {
+        ring.nodes()
+            .into_iter()
+            .fold(MaglevBuilder::new(), MaglevBuilder::add_node)
+            .build()
+    }
+
+    /// Build a table with `DEFAULT_TABLE_SIZE` from a node list (weight 1 each).
+    ///
+    /// # Errors
+    /// See `MaglevBuilder::build()`.
+    pub fn from_nodes(nodes: impl IntoIterator<Item = Node>) -> Result<Self> {
+        nodes
+            .into_iter()
+            .fold(MaglevBuilder::new(), MaglevBuilder::add_node)
+            .build()
+    }
+
+    /// Populate the lookup table.
+    ///
+    /// # Algorithm
+    ///
+    /// 1. Each backend keeps a cursor `j` into its permutation
+    ///    `(offset + j * skip) mod M`
+    /// 2. Round `r` gives backend `i` a turn if it has accumulated enough
+    ///    weight (`r * weight_i >= target_i`), then bumps its target by the
+    ///    maximum weight
+    /// 3. On its turn a backend claims its next preferred empty slot
+    /// 4. Stop when all M slots are filled
+    ///
+    /// # Performance
+    /// - **Time**: O(M log M) expected (cursor skips over filled slots)
+    /// - **Space**: O(M + n)
+    fn populate(backends: &[Backend], size: usize) -> Vec<u32> {
+        const EMPTY: u32 = u32::MAX;
+
+        if backends.is_empty() {
+            return vec![EMPTY; size];
+        }
+
+        let max_weight = backends.iter().map(|b| b.weight).max().unwrap_or(1) as u64;
+        let mut table = vec![EMPTY; size];
+        let mut cursors = vec![0usize; backends.len()];
+        let mut targets = vec![0u64; backends.len()];
+        let mut filled = 0;
+
+        let mut round: u64 = 1;
+        while filled < size {
+            for (i, backend) in backends.iter().enumerate() {
+                if round * (backend.weight as u64) < targets[i] {
+                    continue;
+                }
+                targets[i] += max_weight;
+
+                // Next preferred empty slot for this backend
+                let mut slot = (backend.offset + cursors[i] * backend.skip) % size;
+                while table[slot] != EMPTY {
+                    cursors[i] += 1;
+                    slot = (backend.offset + cursors[i] * backend.skip) % size;
+                }
+                table[slot] = i as u32;
+                cursors[i] += 1;
+                filled += 1;
+                if filled == size {
+                    break;
+                }
+            }
+            round += 1;
+        }
+        table
+    }
+
+    fn from_backends(mut backends: Vec<Backend>, size: usize) -> Self {
+        backends.sort_by_key(|b| b.node.id);
+        let table = Self::populate(&backends, size);
+        Self {
+            backends,
+            table,
+            partitioner: Murmur3Partitioner,
+        }
+    }
+
+    /// Look up the node responsible for a key.
+    ///
+    /// # Performance
+    /// - **Time**: O(1) - one hash, one table read
+    ///
+    /// # Returns
+    /// The responsible node, or `None` if the table has no nodes
+    #[inline]
+    pub fn lookup(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup_node(key).map(|node| node.id)
+    }
+
+    /// Look up the node metadata responsible for a key.
+    #[inline]
+    pub fn lookup_node(&self, key: &[u8]) -> Option<&Node> {
+        if self.backends.is_empty() {
+            return None;
+        }
+        let token = self.partitioner.partition(key);
+        let slot = (token.0 % self.table.len() as u64) as usize;
+        Some(&self.backends[self.table[slot] as usize].node)
+    }
+
+    /// Table size (number of slots, prime).
+    pub fn size(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Nodes in population order (sorted by NodeId).
+    pub fn nodes(&self) -> Vec<&Node> {
+        self.backends.iter().map(|b| &b.node).collect()
+    }
+
+    /// Weight of a node, or `None` if it is not in the table.
+    pub fn weight(&self, node_id: &NodeId) -> Option<u32> {
+        self.backends
+            .iter()
+            .find(|b| b.node.id == *node_id)
+            .map(|b| b.weight)
+    }
+
+    /// Number of slots owned by each node (for balance inspection).
+    ///
+    /// Empty for a table without nodes, whose slots are all unowned.
+    ///
+    /// # Performance
+    /// - **Time**: O(M)
+    pub fn entry_counts(&self) -> HashMap<NodeId, usize> {
+        let mut counts = HashMap::with_capacity(self.backends.len());
+        if self.backends.is_empty() {
+            return counts;
+        }
+        for &index in &self.table {
+            *counts
+                .entry(self.backends[index as usize].node.id)
+                .or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Rebuild with a node added (or its metadata/weight replaced).
+    ///
+    /// Existing permutations are reused; only the new node is hashed.
+    ///
+    /// # Errors
+    /// `Error::InvalidNode` if `weight` is 0 or the table would be too small.
+    pub fn with_node(&self, node: Node, weight: u32) -> Result<Rebuild> {
+        validate_weight(&node, weight)?;
+        let mut backends: Vec<Backend> = self
+            .backends
+            .iter()
+            .filter(|b| b.node.id != node.id)
+            .cloned()
+            .collect();
+        backends.push(Backend::new(node, weight, self.size()));
+        validate_size(backends.len(), self.size())?;
+        Ok(self.rebuild(backends))
+    }
+
+    /// Rebuild with a node removed.
+    ///
+    /// # Errors
+    /// `Error::InvalidNode` if the node is not in the table.
+    pub fn without_node(&self, node_id: &NodeId) -> Result<Rebuild> {
+        if !self.backends.iter().any(|b| b.node.id == *node_id) {
+            return Err(Error::InvalidNode(format!(
+                "node {} is not in the Maglev table",
+                node_id
+            )));
+        }
+        let backends = self
+            .backends
+            .iter()
+            .filter(|b| b.node.id != *node_id)
+            .cloned()
+            .collect();
+        Ok(self.rebuild(backends))
+    }
+
+    fn rebuild(&self, backends: Vec<Backend>) -> Rebuild {
+        let table = Self::from_backends(backends, self.size());
+        let disruption = self.disruption_to(&table);
+        Rebuild { table, disruption }
+    }
+
+    /// Compare slot ownership against another table of the same size.
+    ///
+    /// # Panics
+    /// Panics if the tables have different sizes.
+    pub fn disruption_to(&self, other: &MaglevTable) -> Disruption {
+        assert_eq!(self.size(), other.size(), "tables must have the same size");
+
+        let owner = |table: &MaglevTable, slot: usize| {
+            (!table.backends.is_empty()).then(|| table.backends[table.table[slot] as usize].node.id)
+        };
+        let changed_entries = (0..self.size())
+            .filter(|&slot| owner(self, slot) != owner(other, slot))
+            .count();
+
+        // Minimum churn: entries gained by nodes that grew
+        let before = self.entry_counts();
+        let minimum_entries = other
+            .entry_counts()
+            .into_iter()
+            .map(|(id, count)| count.saturating_sub(before.get(&id).copied().unwrap_or(0)))
+            .sum();
+
+        Disruption {
+            changed_entries,
+            minimum_entries,
+            total_entries: self.size(),
+        }
+    }
+}
+
+impl Placement for MaglevTable {
+    fn locate(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup(key)
+    }
+
+    fn node_count(&self) -> usize {
+        self.backends.len()
+    }
+
+    fn name(&self) -> &'static str {
+        "MaglevTable"
+    }
+}
+
+fn validate_weight(node: &Node, weight: u32) -> Result<()> {
+    if weight == 0 {
+        return Err(Error::InvalidNode(format!(
+            "weight for node {} must be positive",
+            node.id
+        )));
+    }
+    Ok(())
+}
+
+fn validate_size(nodes: usize, size: usize) -> Result<()> {
+    if nodes >= size {
+        return Err(Error::InvalidNode(format!(
+            "{} nodes do not fit in a Maglev table of size {}",
+            nodes, size
+        )));
+    }
+    Ok(())
+}
+
+/// Builder for `MaglevTable`.
+///
+/// # Example
+/// ```rust
+/// # use corelib::placement::maglev::MaglevBuilder;
+/// # use corelib::{Node, NodeId};
+/// let table = MaglevBuilder::new()
+///     .add_node(Node::new(NodeId(1), "a"))
+///     .add_node(Node::new(NodeId(2), "b"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(table.size(), 65_537);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MaglevBuilder {
+    size: usize,
+    nodes: Vec<(Node, u32)>,
+}
+
+impl MaglevBuilder {
+    /// Create a builder with `DEFAULT_TABLE_SIZE` and no nodes.
+    pub fn new() -> Self {
+        Self {
+            size: DEFAULT_TABLE_SIZE,
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Set the table size (must be prime and larger than the node count).
+    pub fn table_size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Add a node with weight 1.
+    pub fn add_node(self, node: Node) -> Self {
+        self.add_node_weighted(node, 1)
+    }
+
+    /// Add a node with a relative weight.
+    ///
+    /// Adding the same NodeId twice keeps the last one.
+    pub fn add_node_weighted(mut self, node: Node, weight: u32) -> Self {
+        self.nodes.retain(|(existing, _)| existing.id != node.id);
+        self.nodes.push((node, weight));
+        self
+    }
+
+    /// Populate the table.
+    ///
+    /// # Errors
+    /// - `Error::RingOperation` if the table size is not prime
+    /// - `Error::InvalidNode` if a weight is 0 or nodes don't fit
+    pub fn build(self) -> Result<MaglevTable> {
+        if !is_prime(self.size) {
+            return Err(Error::RingOperation(format!(
+                "Maglev table size {} is not prime",
+                self.size
+            )));
+        }
+        validate_size(self.nodes.len(), self.size)?;
+
+        let mut backends = Vec::with_capacity(self.nodes.len());
+        for (node, weight) in self.nodes {
+            validate_weight(&node, weight)?;
+            backends.push(Backend::new(node, weight, self.size));
+        }
+        Ok(MaglevTable::from_backends(backends, self.size))
+    }
+}
+
+impl Default for MaglevBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(n: u128) -> Vec<Node> {
+        (1..=n)
+            .map(|i| Node::new(NodeId(i), format!("node{}", i)))
+            .collect()
+    }
+
+    #[test]
+    fn test_is_prime() {
+        assert!(is_prime(2) && is_prime(251) && is_prime(65_537));
+        assert!(!is_prime(1) && !is_prime(65_536) && !is_prime(1_001));
+    }
+
+    #[test]
+    fn test_balance() {
+        let table = MaglevTable::from_nodes(nodes(10)).unwrap();
+        let counts = table.entry_counts();
+        let (min, max) = (
+            *counts.values().min().unwrap(),
+            *counts.values().max().unwrap(),
+        );
+        // Round-robin fill: counts differ by at most one turn
+        assert!(max - min <= 1, "min = {}, max = {}", min, max);
+    }
+
+    #[test]
+    fn test_weighted_balance() {
+        let table = MaglevBuilder::new()
+            .table_size(10_007)
+            .add_node(Node::new(NodeId(1), "a"))
+            .add_node_weighted(Node::new(NodeId(2), "b"), 3)
+            .build()
+            .unwrap();
+        let counts = table.entry_counts();
+        let share = counts[&NodeId(2)] as f64 / table.size() as f64;
+        assert!((share - 0.75).abs() < 0.01, "share = {}", share);
+    }
+
+    #[test]
+    fn test_rebuild_disruption() {
+        let table = MaglevTable::from_nodes(nodes(10)).unwrap();
+
+        let removed = table.without_node(&NodeId(4)).unwrap();
+        assert_eq!(removed.table.node_count(), 9);
+        // Removing 1 of 10 must move at least 10%, and Maglev stays close to it
+        let disruption = removed.disruption;
+        assert!(disruption.minimum_fraction() >= 0.09);
+        assert!(disruption.changed_fraction() < disruption.minimum_fraction() + 0.05);
+
+        let added = removed
+            .table
+            .with_node(Node::new(NodeId(4), "node4"), 1)
+            .unwrap();
+        assert_eq!(
+            added.table.table, table.table,
+            "re-adding restores the table"
+        );
+    }
+
+    #[test]
+    fn test_invalid_configuration() {
+        assert!(MaglevBuilder::new().table_size(100).build().is_err());
+        assert!(MaglevBuilder::new()
+            .table_size(3)
+            .add_node(Node::new(NodeId(1), "a"))
+            .add_node(Node::new(NodeId(2), "b"))
+            .add_node(Node::new(NodeId(3), "c"))
+            .build()
+            .is_err());
+        assert!(MaglevBuilder::new()
+            .add_node_weighted(Node::new(NodeId(1), "a"), 0)
+            .build()
+            .is_err());
+
+        let empty = MaglevBuilder::new().table_size(7).build().unwrap();
+        assert_eq!(empty.lookup(b"key"), None);
+        assert!(empty.entry_counts().is_empty());
+    }
+
+    #[test]
+    fn test_remove_last_node() {
+        let table = MaglevBuilder::new()
+            .table_size(7)
+            .add_node(Node::new(NodeId(1), "a"))
+            .build()
+            .unwrap();
+        let removed = table.without_node(&NodeId(1)).unwrap();
+        assert_eq!(removed.table.node_count(), 0);
+        assert_eq!(removed.table.lookup(b"key"), None);
+        assert_eq!(removed.disruption.changed_entries, 7);
+    }
+
+    #[test]
+    fn test_from_ring() {
+        let ring = HashRing::new();
+        for node in nodes(3) {
+            ring.add_node(node, 8);
+        }
+        let table = MaglevTable::from_ring(&ring).unwrap();
+        assert_eq!(table.node_count(), 3);
+        assert!(table.lookup(b"key").is_some());
+    }
+}
@@ -0,0 +1,33 @@
+//! Alternative placement algorithms behind a common trait.
+//!
+//! The vnode-based `HashRing` is one way to map keys to nodes. This module
+//! defines the `Placement` trait it shares with other algorithms so callers
+//! can switch (or benchmark) algorithms without changing call sites:
+//!
+//! - **HashRing**: vnode ring, arbitrary joins/leaves, O(log n) lookup
+//! - **MaglevTable**: prime-sized lookup table, O(1) lookup, weighted,
+//!   rebuilt on membership change
+//! - **AnchorHash**: fixed-capacity bucket set, arbitrary removals, O(1)
+//!   expected lookup with minimal disruption
+//! - **JumpHash**: ordered, append-only bucket list, zero memory, perfect balance
+//! - **MultiProbeRing**: one token per node, k probes per key, vnode-level
+//!   balance in O(n) memory
+//! - **RendezvousRing**: highest-random-weight top-k selection, weighted, O(n)
+//!   or O(log n) with a skeleton
+//!
+//! Algorithms that can rank several distinct nodes per key also implement
+//! `ReplicaSelection`, which replication strategies are written against.
+
+pub mod anchor;
+pub mod jump;
+pub mod maglev;
+pub mod multiprobe;
+pub mod rendezvous;
+pub mod traits;
+
+pub use anchor::AnchorHash;
+pub use jump::JumpHash;
+pub use maglev::{MaglevBuilder, MaglevTable};
+pub use multiprobe::MultiProbeRing;
+pub use rendezvous::RendezvousRing;
+pub use traits::{Placement, ReplicaSelection};
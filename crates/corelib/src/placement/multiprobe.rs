@@ -0,0 +1,332 @@
+//! Multi-probe consistent hashing (Appleton & O'Reilly, 2015).
+//!
+//! # Algorithm
+//!
+//! Every node owns exactly **one** token on a 64-bit ring. Instead of
+//! spreading each node over many virtual nodes, each key is hashed `k`
+//! times ("probes"); for every probe we find the next node clockwise and
+//! measure the clockwise distance to it. The key belongs to the node with
+//! the smallest distance over all probes.
+//!
+//! # Properties
+//!
+//! - **Memory**: O(n) - one token per node (vs O(n * vnodes) for `HashRing`)
+//! - **Lookup**: O(k log n) - k binary searches over the token list
+//! - **Balance**: with k = 21 probes the peak-to-mean load ratio is ~1.05,
+//!   comparable to a vnode ring with hundreds of tokens per node
+//! - **Disruption**: adding or removing a node only moves keys to or from
+//!   that node
+//!
+//! # When to Use
+//!
+//! Memory-constrained routers that need vnode-level balance without storing
+//! vnode tables. Lookups cost more hashing than `HashRing`, so prefer the
+//! vnode ring when memory is not a concern.
+
+use crate::error::{Error, Result};
+use crate::node::{Node, NodeId};
+use crate::placement::traits::{Placement, ReplicaSelection};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+/// Default number of probes per key (~1.05 peak-to-mean ratio).
+pub const DEFAULT_PROBES: usize = 21;
+
+/// Seed for node token hashing (kept apart from probe seeds `0..k`).
+const NODE_SEED: u64 = 0x6d70_6368_0000_0001;
+
+/// Token of a node on the 64-bit ring.
+#[inline]
+fn node_token(node_id: NodeId) -> u64 {
+    xxh3_64_with_seed(&node_id.0.to_le_bytes(), NODE_SEED)
+}
+
+#[derive(Debug, Default)]
+struct MultiProbeInner {
+    /// One `(token, node)` pair per node, sorted by token.
+    tokens: Vec<(u64, NodeId)>,
+    /// Node metadata.
+    nodes: HashMap<NodeId, Node>,
+}
+
+impl MultiProbeInner {
+    /// Index of the first token at or after `hash`, wrapping to 0.
+    #[inline]
+    fn successor(&self, hash: u64) -> usize {
+        let index = self.tokens.partition_point(|&(token, _)| token < hash);
+        if index == self.tokens.len() {
+            0
+        } else {
+            index
+        }
+    }
+
+    /// Index (into `tokens`) of the closest node over all probes.
+    fn closest(&self, key: &[u8], probes: usize) -> Option<usize> {
+        if self.tokens.is_empty() {
+            return None;
+        }
+        (0..probes as u64)
+            .map(|seed| {
+                let hash = xxh3_64_with_seed(key, seed);
+                let index = self.successor(hash);
+                (self.tokens[index].0.wrapping_sub(hash), index)
+            })
+            .min()
+            .map(|(_, index)| index)
+    }
+}
+
+/// Multi-probe consistent hash ring: one token per node, `k` probes per key.
+///
+/// # Thread Safety
+///
+/// Same model as `HashRing`: cheap to clone (`Arc` inside), concurrent
+/// lookups under a read lock, mutations under a write lock.
+///
+/// # Example
+///
+/// ```rust
+/// use corelib::placement::{MultiProbeRing, Placement};
+/// use corelib::{Node, NodeId};
+///
+/// let ring = MultiProbeRing::new();
+/// ring.add_node(Node::new(NodeId(1), "router-a")).unwrap();
+/// ring.add_node(Node::new(NodeId(2), "router-b")).unwrap();
+///
+/// let node_id = ring.lookup(b"session-42").unwrap();
+/// assert!(node_id == NodeId(1) || node_id == NodeId(2));
+/// assert_eq!(ring.token_count(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultiProbeRing {
+    inner: Arc<RwLock<MultiProbeInner>>,
+    probes: usize,
+}
+
+impl MultiProbeRing {
+    /// Create an empty ring with `DEFAULT_PROBES` probes per key.
+    pub fn new() -> Self {
+        Self::with_probes(DEFAULT_PROBES)
+    }
+
+    /// Create an empty ring with a custom probe count.
+    ///
+    /// # Arguments
+    /// * `probes` - Hashes per key (clamped to at least 1). More probes give
+    ///   better balance at a linear lookup cost; 1 probe is a plain ring
+    ///   with one token per node.
+    pub fn with_probes(probes: usize) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(MultiProbeInner::default())),
+            probes: probes.max(1),
+        }
+    }
+
+    /// Add a node (or update the metadata of an existing one).
+    ///
+    /// # Performance
+    /// - **Time**: O(n) - sorted insertion into the token list
+    ///
+    /// # Errors
+    /// `Error::InvalidToken` if the node's token collides with another node's.
+    pub fn add_node(&self, node: Node) -> Result<()> {
+        let mut inner = self.inner.write();
+        if let Some(existing) = inner.nodes.get_mut(&node.id) {
+            *existing = node;
+            return Ok(());
+        }
+
+        let token = node_token(node.id);
+        let index = inner.tokens.partition_point(|&(t, _)| t < token);
+        if let Some(&(existing, owner)) = inner.tokens.get(index) {
+            if existing == token {
+                return Err(Error::InvalidToken(format!(
+                    "token {} of node {} is already owned by node {}",
+                    token, node.id, owner
+                )));
+            }
+        }
+        inner.tokens.insert(index, (token, node.id));
+        inner.nodes.insert(node.id, node);
+        Ok(())
+    }
+
+    /// Remove a node.
+    ///
+    /// # Returns
+    /// `true` if the node was removed, `false` if it didn't exist
+    pub fn remove_node(&self, node_id: &NodeId) -> bool {
+        let mut inner = self.inner.write();
+        if inner.nodes.remove(node_id).is_none() {
+            return false;
+        }
+        inner.tokens.retain(|&(_, owner)| owner != *node_id);
+        true
+    }
+
+    /// Closest node over all probes for a key.
+    ///
+    /// # Performance
+    /// - **Time**: O(k log n)
+    ///
+    /// # Returns
+    /// The responsible node, or `None` if the ring is empty
+    pub fn lookup(&self, key: &[u8]) -> Option<NodeId> {
+        let inner = self.inner.read();
+        inner
+            .closest(key, self.probes)
+            .map(|index| inner.tokens[index].1)
+    }
+
+    /// Look up the node and return full Node metadata.
+    pub fn lookup_node(&self, key: &[u8]) -> Option<Node> {
+        let inner = self.inner.read();
+        let index = inner.closest(key, self.probes)?;
+        inner.nodes.get(&inner.tokens[index].1).cloned()
+    }
+
+    /// Primary node followed by its clockwise successors.
+    ///
+    /// Every token belongs to a different node, so the walk yields
+    /// `min(count, n)` distinct nodes.
+    pub fn replicas(&self, key: &[u8], count: usize) -> Vec<NodeId> {
+        let inner = self.inner.read();
+        let Some(start) = inner.closest(key, self.probes) else {
+            return Vec::new();
+        };
+        let len = inner.tokens.len();
+        (0..count.min(len))
+            .map(|offset| inner.tokens[(start + offset) % len].1)
+            .collect()
+    }
+
+    /// Get node metadata by ID.
+    pub fn get_node(&self, node_id: &NodeId) -> Option<Node> {
+        self.inner.read().nodes.get(node_id).cloned()
+    }
+
+    /// Get all nodes.
+    pub fn nodes(&self) -> Vec<Node> {
+        self.inner.read().nodes.values().cloned().collect()
+    }
+
+    /// Get the number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.inner.read().nodes.len()
+    }
+
+    /// Get the number of tokens (always equal to `node_count()`).
+    pub fn token_count(&self) -> usize {
+        self.inner.read().tokens.len()
+    }
+
+    /// Probes per key.
+    pub fn probes(&self) -> usize {
+        self.probes
+    }
+}
+
+impl Default for MultiProbeRing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Placement for MultiProbeRing {
+    fn locate(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup(key)
+    }
+
+    fn node_count(&self) -> usize {
+        MultiProbeRing::node_count(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "MultiProbeRing"
+    }
+}
+
+impl ReplicaSelection for MultiProbeRing {
+    fn replicas(&self, key: &[u8], count: usize) -> Vec<NodeId> {
+        MultiProbeRing::replicas(self, key, count)
+    }
+
+    fn node(&self, node_id: &NodeId) -> Option<Node> {
+        self.get_node(node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populate(ring: &MultiProbeRing, n: u128) {
+        for i in 0..n {
+            ring.add_node(Node::new(NodeId(i), format!("node{}", i)))
+                .unwrap();
+        }
+    }
+
+    fn peak_to_mean(ring: &MultiProbeRing, keys: usize) -> f64 {
+        let mut counts: HashMap<NodeId, usize> = HashMap::new();
+        for i in 0..keys {
+            let node_id = ring.lookup(format!("key-{}", i).as_bytes()).unwrap();
+            *counts.entry(node_id).or_insert(0) += 1;
+        }
+        let mean = keys as f64 / ring.node_count() as f64;
+        *counts.values().max().unwrap() as f64 / mean
+    }
+
+    #[test]
+    fn test_probes_improve_balance() {
+        let single = MultiProbeRing::with_probes(1);
+        let multi = MultiProbeRing::new();
+        populate(&single, 20);
+        populate(&multi, 20);
+
+        let single_ratio = peak_to_mean(&single, 100_000);
+        let multi_ratio = peak_to_mean(&multi, 100_000);
+        assert!(multi_ratio < single_ratio);
+        assert!(multi_ratio < 1.25, "peak-to-mean = {}", multi_ratio);
+    }
+
+    #[test]
+    fn test_remove_only_moves_removed_keys() {
+        let ring = MultiProbeRing::new();
+        populate(&ring, 10);
+        let keys: Vec<Vec<u8>> = (0..5_000).map(|i| format!("k{}", i).into_bytes()).collect();
+        let before: Vec<NodeId> = keys.iter().map(|k| ring.lookup(k).unwrap()).collect();
+
+        assert!(ring.remove_node(&NodeId(3)));
+        for (key, owner) in keys.iter().zip(&before) {
+            if *owner != NodeId(3) {
+                assert_eq!(ring.lookup(key), Some(*owner));
+            }
+        }
+    }
+
+    #[test]
+    fn test_replicas_distinct_primary_first() {
+        let ring = MultiProbeRing::new();
+        populate(&ring, 5);
+
+        let replicas = ring.replicas(b"key", 3);
+        assert_eq!(replicas.len(), 3);
+        assert_eq!(replicas[0], ring.lookup(b"key").unwrap());
+        assert_eq!(ring.replicas(b"key", 10).len(), 5);
+    }
+
+    #[test]
+    fn test_add_existing_updates_metadata() {
+        let ring = MultiProbeRing::new();
+        assert_eq!(ring.lookup(b"key"), None);
+
+        ring.add_node(Node::new(NodeId(1), "old")).unwrap();
+        ring.add_node(Node::new(NodeId(1), "new")).unwrap();
+        assert_eq!(ring.token_count(), 1);
+        assert_eq!(ring.get_node(&NodeId(1)).unwrap().name, "new");
+    }
+}
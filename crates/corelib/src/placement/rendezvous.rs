@@ -0,0 +1,599 @@
+//! Rendezvous (highest-random-weight) hashing.
+//!
+//! # Algorithm
+//!
+//! Every node is scored against the key with an independent hash; the key
+//! belongs to the highest-scoring node, and its k replicas are the k
+//! highest-scoring nodes. Removing a node only moves the keys it owned,
+//! and each of those keys moves to its next-best node, so k-of-n selection
+//! is minimally disrupted.
+//!
+//! # Weighted HRW
+//!
+//! Scores use the logarithmic method (Schindelhauer & Schomaker):
+//! `score = -capacity / ln(u)` where `u ∈ (0, 1)` is the hash normalised
+//! to the unit interval. A node with twice the capacity wins twice as many
+//! keys, and equal capacities reduce to plain HRW ordering.
+//!
+//! # Skeleton Mode
+//!
+//! Plain HRW scores every node: O(n) per lookup. `with_skeleton(fanout)`
+//! arranges nodes as leaves of a virtual tree with the given fanout and
+//! descends from the root, at each level running weighted HRW over at most
+//! `fanout` children (whose weight is the sum of their live leaves). This
+//! is O(fanout * log_fanout(n)) per lookup and preserves each node's share
+//! exactly. The cost is extra disruption: a membership change alters the
+//! weight of every cluster above the node, so some keys also move between
+//! surviving nodes in sibling clusters (a few percent of keys when removing
+//! one of 16 nodes with fanout 4, versus zero for flat HRW).
+//!
+//! # Removal
+//!
+//! Nodes are tombstoned rather than compacted so every other node keeps
+//! its position in the skeleton. A later `add_node` reuses the first free
+//! slot.
+
+use crate::error::{Error, Result};
+use crate::node::{Node, NodeId};
+use crate::placement::traits::{Placement, ReplicaSelection};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+/// Default skeleton fanout (children per virtual cluster).
+pub const DEFAULT_SKELETON_FANOUT: usize = 8;
+
+/// Seed domain for virtual skeleton clusters (kept apart from node seeds).
+const SKELETON_SEED: u64 = 0x5bd1_e995_9e37_79b9;
+
+/// Fold a `NodeId` into a 64-bit hash seed.
+#[inline]
+fn node_seed(node_id: NodeId) -> u64 {
+    splitmix64((node_id.0 >> 64) as u64 ^ node_id.0 as u64)
+}
+
+/// Seed for an internal skeleton cluster.
+#[inline]
+fn cluster_seed(level: usize, index: usize) -> u64 {
+    splitmix64(SKELETON_SEED ^ ((level as u64) << 56) ^ index as u64)
+}
+
+/// SplitMix64 finaliser: cheap, well-mixed seed derivation.
+#[inline]
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Weighted HRW score of a hash for the given weight.
+///
+/// Maps the top 53 bits of the hash to `u ∈ (0, 1)` and returns
+/// `-weight / ln(u)`, which is positive and monotone in both `u` and
+/// `weight`.
+#[inline]
+fn weighted_score(hash: u64, weight: f64) -> f64 {
+    let u = ((hash >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+    -weight / u.ln()
+}
+
+/// A node slot in the rendezvous table.
+#[derive(Clone, Debug)]
+struct Entry {
+    node: Node,
+    /// Relative capacity (weight); always finite and > 0.
+    capacity: f64,
+    /// Precomputed hash seed derived from the node ID.
+    seed: u64,
+}
+
+impl Entry {
+    #[inline]
+    fn score(&self, key: &[u8]) -> f64 {
+        weighted_score(xxh3_64_with_seed(key, self.seed), self.capacity)
+    }
+}
+
+/// Virtual tree over node slots for O(log n) lookups.
+///
+/// `weights[0]` / `live[0]` are per-slot (0 for tombstones); level `l + 1`
+/// aggregates consecutive groups of `fanout` clusters from level `l`. The
+/// last level always has exactly one cluster (the root).
+#[derive(Clone, Debug)]
+struct Skeleton {
+    fanout: usize,
+    weights: Vec<Vec<f64>>,
+    live: Vec<Vec<usize>>,
+}
+
+impl Skeleton {
+    /// Build the skeleton for the current slots.
+    ///
+    /// # Performance
+    /// - **Time**: O(n) - each level is 1/fanout the size of the one below
+    fn build(fanout: usize, slots: &[Option<Entry>]) -> Self {
+        let mut weights = vec![slots
+            .iter()
+            .map(|slot| slot.as_ref().map_or(0.0, |entry| entry.capacity))
+            .collect::<Vec<f64>>()];
+        let mut live = vec![slots
+            .iter()
+            .map(|slot| slot.is_some() as usize)
+            .collect::<Vec<_>>()];
+
+        loop {
+            let last_weights = weights.last().expect("at least one level");
+            let last_live = live.last().expect("at least one level");
+            let next_weights: Vec<f64> = last_weights
+                .chunks(fanout)
+                .map(|c| c.iter().sum())
+                .collect();
+            let next_live: Vec<usize> = last_live.chunks(fanout).map(|c| c.iter().sum()).collect();
+            let done = next_weights.len() <= 1;
+            weights.push(next_weights);
+            live.push(next_live);
+            if done {
+                break;
+            }
+        }
+
+        Self {
+            fanout,
+            weights,
+            live,
+        }
+    }
+
+    /// Descend from the root to a slot, skipping slots in `chosen`.
+    ///
+    /// Cluster weights are reduced by the capacity of already-chosen slots
+    /// beneath them, so repeated descents sample without replacement in
+    /// proportion to capacity.
+    fn descend(&self, key: &[u8], slots: &[Option<Entry>], chosen: &[usize]) -> Option<usize> {
+        let top = self.weights.len() - 1;
+        if self.live[top].first().copied().unwrap_or(0) <= chosen.len() {
+            return None;
+        }
+
+        let mut cluster = 0;
+        for level in (0..top).rev() {
+            let span = self.fanout.pow(level as u32);
+            let start = cluster * self.fanout;
+            let end = (start + self.fanout).min(self.weights[level].len());
+
+            let mut best: Option<(f64, usize)> = None;
+            for child in start..end {
+                // Remove already-chosen slots under this child
+                let (lo, hi) = (child * span, (child + 1) * span);
+                let mut taken = 0;
+                let mut taken_weight = 0.0;
+                for &slot in chosen.iter().filter(|&&slot| slot >= lo && slot < hi) {
+                    taken += 1;
+                    taken_weight += slots[slot].as_ref().map_or(0.0, |entry| entry.capacity);
+                }
+                if self.live[level][child] <= taken {
+                    continue;
+                }
+
+                let score = if level == 0 {
+                    slots[child].as_ref()?.score(key)
+                } else {
+                    let weight = (self.weights[level][child] - taken_weight).max(f64::MIN_POSITIVE);
+                    weighted_score(xxh3_64_with_seed(key, cluster_seed(level, child)), weight)
+                };
+                if best.is_none_or(|(best_score, _)| score > best_score) {
+                    best = Some((score, child));
+                }
+            }
+            cluster = best?.1;
+        }
+        Some(cluster)
+    }
+}
+
+/// Internal rendezvous state (always behind `RwLock`).
+#[derive(Debug)]
+struct RendezvousInner {
+    /// Node slots; `None` marks a tombstone left by `remove_node`.
+    slots: Vec<Option<Entry>>,
+    /// NodeId → slot index.
+    index: HashMap<NodeId, usize>,
+    /// Skeleton fanout, or `None` for flat HRW.
+    fanout: Option<usize>,
+    /// Skeleton built from `slots` (only in skeleton mode).
+    skeleton: Option<Skeleton>,
+}
+
+impl RendezvousInner {
+    fn rebuild(&mut self) {
+        self.skeleton = self
+            .fanout
+            .map(|fanout| Skeleton::build(fanout, &self.slots));
+    }
+
+    fn live(&self) -> impl Iterator<Item = &Entry> {
+        self.slots.iter().flatten()
+    }
+
+    /// Top-k slots for a key, best first.
+    fn top_k(&self, key: &[u8], k: usize) -> Vec<NodeId> {
+        if k == 0 || self.index.is_empty() {
+            return Vec::new();
+        }
+
+        match &self.skeleton {
+            Some(skeleton) => {
+                let mut chosen = Vec::with_capacity(k.min(self.index.len()));
+                while chosen.len() < k {
+                    match skeleton.descend(key, &self.slots, &chosen) {
+                        Some(slot) => chosen.push(slot),
+                        None => break,
+                    }
+                }
+                chosen
+                    .into_iter()
+                    .filter_map(|slot| self.slots[slot].as_ref().map(|entry| entry.node.id))
+                    .collect()
+            }
+            None => {
+                let mut scored: Vec<(f64, NodeId)> = self
+                    .live()
+                    .map(|entry| (entry.score(key), entry.node.id))
+                    .collect();
+                // Highest score first; ties broken by NodeId for determinism
+                let by_score = |a: &(f64, NodeId), b: &(f64, NodeId)| {
+                    b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1))
+                };
+                if k < scored.len() {
+                    scored.select_nth_unstable_by(k - 1, by_score);
+                    scored.truncate(k);
+                }
+                scored.sort_unstable_by(by_score);
+                scored.into_iter().map(|(_, node_id)| node_id).collect()
+            }
+        }
+    }
+
+    /// Highest-scoring node for a key.
+    fn lookup(&self, key: &[u8]) -> Option<NodeId> {
+        match &self.skeleton {
+            Some(_) => self.top_k(key, 1).into_iter().next(),
+            None => self
+                .live()
+                .map(|entry| (entry.score(key), entry.node.id))
+                .max_by(|a, b| a.0.total_cmp(&b.0).then_with(|| b.1.cmp(&a.1)))
+                .map(|(_, node_id)| node_id),
+        }
+    }
+}
+
+/// Thread-safe rendezvous (HRW) placement over a set of weighted nodes.
+///
+/// # Performance Characteristics
+///
+/// | Mode     | Lookup                    | Top-k                          | Add/remove |
+/// |----------|---------------------------|--------------------------------|------------|
+/// | Flat     | O(n)                      | O(n + k log k)                 | O(1)       |
+/// | Skeleton | O(f · log_f n)            | O(k · f · log_f n)             | O(n)       |
+///
+/// # Example
+///
+/// ```rust
+/// use corelib::placement::RendezvousRing;
+/// use corelib::{Node, NodeId};
+///
+/// let ring = RendezvousRing::new();
+/// ring.add_node(Node::new(NodeId(1), "a"));
+/// ring.add_node(Node::new(NodeId(2), "b"));
+/// ring.add_node_weighted(Node::new(NodeId(3), "big"), 2.0).unwrap();
+///
+/// let replicas = ring.top_k(b"object-42", 2);
+/// assert_eq!(replicas.len(), 2);
+/// assert_eq!(ring.lookup(b"object-42"), Some(replicas[0]));
+/// ```
+#[derive(Clone, Debug)]
+pub struct RendezvousRing {
+    inner: Arc<RwLock<RendezvousInner>>,
+}
+
+impl RendezvousRing {
+    /// Create an empty ring using flat (O(n)) HRW.
+    pub fn new() -> Self {
+        Self::with_mode(None)
+    }
+
+    /// Create an empty ring using skeleton-based O(log n) HRW.
+    ///
+    /// # Arguments
+    /// * `fanout` - Children per virtual cluster (clamped to at least 2).
+    ///   Larger fanouts mean shallower trees but more scoring per level;
+    ///   `DEFAULT_SKELETON_FANOUT` is a good default.
+    pub fn with_skeleton(fanout: usize) -> Self {
+        Self::with_mode(Some(fanout.max(2)))
+    }
+
+    fn with_mode(fanout: Option<usize>) -> Self {
+        let mut inner = RendezvousInner {
+            slots: Vec::new(),
+            index: HashMap::new(),
+            fanout,
+            skeleton: None,
+        };
+        inner.rebuild();
+        Self {
+            inner: Arc::new(RwLock::new(inner)),
+        }
+    }
+
+    /// Add a node with capacity 1.0.
+    ///
+    /// If the node already exists, its metadata is updated and its capacity
+    /// reset to 1.0 (its slot is kept).
+    pub fn add_node(&self, node: Node) {
+        self.insert(node, 1.0);
+    }
+
+    /// Add a node with a relative capacity (weighted HRW).
+    ///
+    /// A node with capacity 2.0 receives twice the keys of a node with 1.0.
+    ///
+    /// # Errors
+    /// `Error::InvalidNode` if `capacity` is not finite and positive.
+    pub fn add_node_weighted(&self, node: Node, capacity: f64) -> Result<()> {
+        if !capacity.is_finite() || capacity <= 0.0 {
+            return Err(Error::InvalidNode(format!(
+                "capacity for node {} must be finite and positive, got {}",
+                node.id, capacity
+            )));
+        }
+        self.insert(node, capacity);
+        Ok(())
+    }
+
+    fn insert(&self, node: Node, capacity: f64) {
+        let mut inner = self.inner.write();
+        let entry = Entry {
+            seed: node_seed(node.id),
+            node,
+            capacity,
+        };
+
+        let slot = match inner.index.get(&entry.node.id) {
+            Some(&slot) => slot,
+            None => match inner.slots.iter().position(Option::is_none) {
+                Some(free) => free,
+                None => {
+                    inner.slots.push(None);
+                    inner.slots.len() - 1
+                }
+            },
+        };
+        inner.index.insert(entry.node.id, slot);
+        inner.slots[slot] = Some(entry);
+        inner.rebuild();
+    }
+
+    /// Remove a node (its slot becomes a tombstone).
+    ///
+    /// # Returns
+    /// `true` if the node was removed, `false` if it didn't exist
+    pub fn remove_node(&self, node_id: &NodeId) -> bool {
+        let mut inner = self.inner.write();
+        match inner.index.remove(node_id) {
+            Some(slot) => {
+                inner.slots[slot] = None;
+                inner.rebuild();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Highest-scoring node for a key.
+    ///
+    /// # Returns
+    /// The responsible node, or `None` if the ring is empty
+    pub fn lookup(&self, key: &[u8]) -> Option<NodeId> {
+        self.inner.read().lookup(key)
+    }
+
+    /// Look up the node and return full Node metadata.
+    pub fn lookup_node(&self, key: &[u8]) -> Option<Node> {
+        let inner = self.inner.read();
+        let node_id = inner.lookup(key)?;
+        inner
+            .index
+            .get(&node_id)
+            .and_then(|&slot| inner.slots[slot].as_ref())
+            .map(|e| e.node.clone())
+    }
+
+    /// The `k` highest-scoring distinct nodes for a key, best first.
+    ///
+    /// Returns fewer than `k` nodes if the ring has fewer than `k`.
+    pub fn top_k(&self, key: &[u8], k: usize) -> Vec<NodeId> {
+        self.inner.read().top_k(key, k)
+    }
+
+    /// Get node metadata by ID.
+    pub fn get_node(&self, node_id: &NodeId) -> Option<Node> {
+        let inner = self.inner.read();
+        inner
+            .index
+            .get(node_id)
+            .and_then(|&slot| inner.slots[slot].as_ref())
+            .map(|e| e.node.clone())
+    }
+
+    /// Get a node's capacity.
+    pub fn capacity(&self, node_id: &NodeId) -> Option<f64> {
+        let inner = self.inner.read();
+        inner
+            .index
+            .get(node_id)
+            .and_then(|&slot| inner.slots[slot].as_ref())
+            .map(|e| e.capacity)
+    }
+
+    /// Get all nodes.
+    pub fn nodes(&self) -> Vec<Node> {
+        self.inner
+            .read()
+            .live()
+            .map(|entry| entry.node.clone())
+            .collect()
+    }
+
+    /// Get the number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.inner.read().index.len()
+    }
+
+    /// True if lookups use the skeleton (O(log n)) variant.
+    pub fn is_skeleton(&self) -> bool {
+        self.inner.read().fanout.is_some()
+    }
+}
+
+impl Default for RendezvousRing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Placement for RendezvousRing {
+    fn locate(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup(key)
+    }
+
+    fn node_count(&self) -> usize {
+        RendezvousRing::node_count(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "RendezvousRing"
+    }
+}
+
+impl ReplicaSelection for RendezvousRing {
+    fn replicas(&self, key: &[u8], count: usize) -> Vec<NodeId> {
+        self.top_k(key, count)
+    }
+
+    fn node(&self, node_id: &NodeId) -> Option<Node> {
+        self.get_node(node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populate(ring: &RendezvousRing, n: u128) {
+        for i in 0..n {
+            ring.add_node(Node::new(NodeId(i), format!("node{}", i)));
+        }
+    }
+
+    fn keys(n: usize) -> Vec<Vec<u8>> {
+        (0..n).map(|i| format!("key-{}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_top_k_distinct_and_ordered() {
+        for ring in [RendezvousRing::new(), RendezvousRing::with_skeleton(4)] {
+            populate(&ring, 20);
+            for key in keys(100) {
+                let top = ring.top_k(&key, 5);
+                assert_eq!(top.len(), 5);
+                let unique: std::collections::HashSet<_> = top.iter().collect();
+                assert_eq!(unique.len(), 5);
+                assert_eq!(ring.lookup(&key), Some(top[0]));
+            }
+            assert_eq!(ring.top_k(b"key", 50).len(), 20);
+        }
+    }
+
+    /// Fraction of keys that moved between surviving nodes after removing one.
+    fn survivor_churn(ring: &RendezvousRing, removed: NodeId) -> f64 {
+        let keys = keys(4_000);
+        let before: Vec<_> = keys.iter().map(|k| ring.lookup(k).unwrap()).collect();
+
+        assert!(ring.remove_node(&removed));
+        assert!(!ring.remove_node(&removed));
+
+        let mut moved = 0;
+        for (key, owner) in keys.iter().zip(before) {
+            let now = ring.lookup(key).unwrap();
+            assert_ne!(now, removed);
+            if owner != removed && now != owner {
+                moved += 1;
+            }
+        }
+        moved as f64 / keys.len() as f64
+    }
+
+    #[test]
+    fn test_remove_only_moves_removed_keys() {
+        let ring = RendezvousRing::new();
+        populate(&ring, 16);
+        assert_eq!(survivor_churn(&ring, NodeId(5)), 0.0);
+    }
+
+    #[test]
+    fn test_skeleton_remove_bounded_churn() {
+        // Sibling clusters rebalance, but most survivors keep their keys
+        let ring = RendezvousRing::with_skeleton(4);
+        populate(&ring, 16);
+        let churn = survivor_churn(&ring, NodeId(5));
+        assert!(churn < 0.1, "churn = {}", churn);
+    }
+
+    #[test]
+    fn test_weighted_distribution() {
+        for ring in [RendezvousRing::new(), RendezvousRing::with_skeleton(2)] {
+            ring.add_node(Node::new(NodeId(1), "small"));
+            ring.add_node(Node::new(NodeId(2), "small"));
+            ring.add_node_weighted(Node::new(NodeId(3), "big"), 2.0)
+                .unwrap();
+
+            let mut big = 0;
+            let total = 40_000;
+            for key in keys(total) {
+                if ring.lookup(&key) == Some(NodeId(3)) {
+                    big += 1;
+                }
+            }
+            // Expect 50% for the double-capacity node
+            let share = big as f64 / total as f64;
+            assert!((share - 0.5).abs() < 0.02, "share = {}", share);
+        }
+    }
+
+    #[test]
+    fn test_invalid_capacity() {
+        let ring = RendezvousRing::new();
+        assert!(ring
+            .add_node_weighted(Node::new(NodeId(1), "a"), 0.0)
+            .is_err());
+        assert!(ring
+            .add_node_weighted(Node::new(NodeId(1), "a"), f64::NAN)
+            .is_err());
+        assert_eq!(ring.node_count(), 0);
+        assert_eq!(ring.lookup(b"key"), None);
+    }
+
+    #[test]
+    fn test_skeleton_reuses_tombstone() {
+        let ring = RendezvousRing::with_skeleton(2);
+        populate(&ring, 5);
+        ring.remove_node(&NodeId(1));
+        ring.add_node(Node::new(NodeId(9), "replacement"));
+
+        assert_eq!(ring.node_count(), 5);
+        assert_eq!(ring.inner.read().slots.len(), 5);
+        assert!(ring.is_skeleton());
+    }
+}
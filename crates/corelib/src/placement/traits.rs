@@ -0,0 +1,44 @@
+//! Core placement trait definitions.
+
+use crate::node::{Node, NodeId};
+
+/// A placement algorithm maps keys to nodes.
+///
+/// Implementations must be deterministic: the same key and the same node
+/// set always yield the same node. They must also be thread-safe so a
+/// single instance can serve lookups from many threads.
+pub trait Placement: Send + Sync {
+    /// Find the node responsible for a key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to place
+    ///
+    /// # Returns
+    ///
+    /// The responsible node, or `None` if there are no nodes
+    fn locate(&self, key: &[u8]) -> Option<NodeId>;
+
+    /// Returns the number of nodes keys can be placed on.
+    fn node_count(&self) -> usize;
+
+    /// Returns the name of this placement algorithm.
+    fn name(&self) -> &'static str;
+}
+
+/// A placement algorithm that can also choose replica sets.
+///
+/// This is the interface replication strategies run on top of: anything
+/// that can rank distinct nodes for a key (a vnode ring walking clockwise,
+/// rendezvous hashing picking the top-k scores, ...) can back a
+/// `ReplicationStrategy`.
+pub trait ReplicaSelection: Placement {
+    /// Find up to `count` distinct nodes for a key, in preference order.
+    ///
+    /// The first element must equal `locate(key)`. Returns fewer than
+    /// `count` nodes if fewer exist.
+    fn replicas(&self, key: &[u8], count: usize) -> Vec<NodeId>;
+
+    /// Get node metadata by ID (for topology-aware strategies).
+    fn node(&self, node_id: &NodeId) -> Option<Node>;
+}
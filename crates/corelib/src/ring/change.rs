@@ -0,0 +1,131 @@
+//! Batched topology changes for the hash ring.
+//!
+//! A `RingChange` describes one or more mutations (add, remove, token move,
+//! metadata update) that `HashRing::apply()` validates and applies under a
+//! single write lock acquisition and a single epoch bump.
+//!
+//! # Why Batches?
+//!
+//! `HashRing::add_node()` and `HashRing::remove_node()` each take the write
+//! lock separately, so a multi-step operation (replacing a node, adding a
+//! whole rack) exposes intermediate ring states to readers. A batch is
+//! all-or-nothing: if any step fails validation, every step already applied
+//! is rolled back before the lock is released.
+//!
+//! # Example
+//!
+//! ```rust
+//! use corelib::ring::{HashRing, RingChange};
+//! use corelib::{Node, NodeId};
+//!
+//! let ring = HashRing::new();
+//! ring.add_node(Node::new(NodeId(1), "old"), 8);
+//!
+//! // Replace node 1 with node 2 atomically
+//! let epoch = ring
+//!     .apply(RingChange::batch([
+//!         RingChange::add_node(Node::new(NodeId(2), "new"), 8),
+//!         RingChange::remove_node(NodeId(1)),
+//!     ]))
+//!     .unwrap();
+//!
+//! assert_eq!(ring.epoch(), epoch);
+//! assert_eq!(ring.node_count(), 1);
+//! ```
+
+use crate::node::{Node, NodeId};
+use crate::token::murmur3::Murmur3Token;
+use serde::{Deserialize, Serialize};
+
+/// A topology change to apply to a `HashRing`.
+///
+/// Changes are applied in order; later changes observe the effects of
+/// earlier ones within the same batch (e.g. a batch may add a node and then
+/// update its metadata).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RingChange {
+    /// Add a node with the given number of virtual nodes.
+    ///
+    /// Fails if any generated token is already owned by a different node.
+    AddNode {
+        /// The node to add.
+        node: Node,
+        /// Number of virtual nodes to generate.
+        vnodes: usize,
+    },
+
+    /// Remove a node and all of its tokens.
+    ///
+    /// Fails if the node does not exist.
+    RemoveNode(NodeId),
+
+    /// Move a single token to a new position, keeping its owner.
+    ///
+    /// Fails if `from` is not on the ring or `to` is already occupied.
+    MoveToken {
+        /// Current token position.
+        from: Murmur3Token,
+        /// New token position.
+        to: Murmur3Token,
+    },
+
+    /// Replace a node's metadata (name, datacenter, rack) without touching
+    /// its tokens.
+    ///
+    /// Fails if the node does not exist.
+    UpdateNode(Node),
+
+    /// A sequence of changes applied atomically.
+    Batch(Vec<RingChange>),
+}
+
+impl RingChange {
+    /// Create an `AddNode` change.
+    pub fn add_node(node: Node, vnodes: usize) -> Self {
+        RingChange::AddNode { node, vnodes }
+    }
+
+    /// Create a `RemoveNode` change.
+    pub fn remove_node(node_id: NodeId) -> Self {
+        RingChange::RemoveNode(node_id)
+    }
+
+    /// Create a `MoveToken` change.
+    pub fn move_token(from: Murmur3Token, to: Murmur3Token) -> Self {
+        RingChange::MoveToken { from, to }
+    }
+
+    /// Create an `UpdateNode` change.
+    pub fn update_node(node: Node) -> Self {
+        RingChange::UpdateNode(node)
+    }
+
+    /// Create a `Batch` from any iterator of changes.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::RingChange;
+    /// # use corelib::NodeId;
+    /// let change = RingChange::batch([
+    ///     RingChange::remove_node(NodeId(1)),
+    ///     RingChange::remove_node(NodeId(2)),
+    /// ]);
+    /// assert_eq!(change.len(), 2);
+    /// ```
+    pub fn batch(changes: impl IntoIterator<Item = RingChange>) -> Self {
+        RingChange::Batch(changes.into_iter().collect())
+    }
+
+    /// Number of primitive (non-batch) changes, counting nested batches.
+    pub fn len(&self) -> usize {
+        match self {
+            RingChange::Batch(changes) => changes.iter().map(RingChange::len).sum(),
+            _ => 1,
+        }
+    }
+
+    /// True if this is a batch containing no primitive changes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,72 @@
+//! Human-readable explanation of a key's placement.
+
+use crate::node::Node;
+use crate::token::murmur3::Murmur3Token;
+use std::fmt;
+
+/// Why a key maps to the node it does, as returned by `HashRing::explain()`.
+///
+/// The owning vnode covers the token range `(range_start, vnode_token]`;
+/// the key's token falls inside it. When `wrapped` is true the key's token
+/// is past the last vnode, so the range crosses the top of the ring and the
+/// key belongs to the first vnode.
+///
+/// # Example
+/// ```rust
+/// # use corelib::ring::HashRing;
+/// # use corelib::{Node, NodeId};
+/// let ring = HashRing::new();
+/// ring.add_node(Node::new(NodeId(1), "node1"), 8);
+///
+/// let explanation = ring.explain(b"user:42").unwrap();
+/// assert_eq!(explanation.node.id, NodeId(1));
+/// println!("{}", explanation);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyExplanation {
+    /// Token the key hashes to.
+    pub key_token: Murmur3Token,
+    /// Token of the vnode that owns the key (first at or after `key_token`).
+    pub vnode_token: Murmur3Token,
+    /// Token of the preceding vnode: the exclusive start of the owning range.
+    /// Equal to `vnode_token` when the ring has a single vnode.
+    pub range_start: Murmur3Token,
+    /// The owning node's metadata.
+    pub node: Node,
+    /// True if the lookup wrapped past the largest token to the first one.
+    pub wrapped: bool,
+    /// Partitioner used to hash the key.
+    pub partitioner: &'static str,
+    /// Ring epoch the explanation was computed at.
+    pub epoch: u64,
+}
+
+impl fmt::Display for KeyExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "key token {:#018x} ({}, epoch {})",
+            self.key_token.0, self.partitioner, self.epoch
+        )?;
+        writeln!(
+            f,
+            "owned by vnode {:#018x}, range ({:#018x}, {:#018x}]{}",
+            self.vnode_token.0,
+            self.range_start.0,
+            self.vnode_token.0,
+            if self.wrapped {
+                " (wrapped around)"
+            } else {
+                ""
+            }
+        )?;
+        write!(f, "node {} ({})", self.node.id, self.node.name)?;
+        if let Some(datacenter) = &self.node.datacenter {
+            write!(f, " dc={}", datacenter)?;
+        }
+        if let Some(rack) = &self.node.rack {
+            write!(f, " rack={}", rack)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+//! Per-node in-flight load tracking for bounded-load lookups.
+//!
+//! Implements the bookkeeping behind "Consistent Hashing with Bounded Loads"
+//! (Mirrokni, Thorup, Zadimoghaddam): every node has a capacity of
+//! `ceil((1 + ε) * (L + 1) / n)` where `L` is the total in-flight load and
+//! `n` the number of nodes. A key is assigned to the first node clockwise
+//! from its token whose load is below that capacity.
+//!
+//! # Why a Separate Tracker?
+//!
+//! Load changes on every request, while topology changes are rare. Keeping
+//! load counters out of `RingInner` means acquiring/releasing load never
+//! takes the ring's write lock (and never bumps its epoch).
+//!
+//! # Thread Safety
+//!
+//! - Per-node counters live in a `DashMap` (sharded locks, no global lock)
+//! - The total is a single `AtomicUsize`
+//! - `try_acquire()` checks and increments a node's counter under its shard
+//!   lock, so a node never exceeds the capacity it was checked against.
+//!   The capacity itself is computed from a racy snapshot of the total, so
+//!   under heavy contention a node may briefly exceed the *current* bound by
+//!   the number of concurrent acquirers.
+
+use crate::node::NodeId;
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// In-flight load counters shared by all clones of a `HashRing`.
+#[derive(Debug, Default)]
+pub(crate) struct LoadTracker {
+    /// Current in-flight load per node.
+    loads: DashMap<NodeId, usize>,
+    /// Sum of all per-node loads.
+    total: AtomicUsize,
+}
+
+impl LoadTracker {
+    /// Create an empty tracker.
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current load of a node (0 if never loaded).
+    pub(crate) fn load(&self, node_id: &NodeId) -> usize {
+        self.loads.get(node_id).map(|load| *load).unwrap_or(0)
+    }
+
+    /// Total in-flight load across all nodes.
+    pub(crate) fn total(&self) -> usize {
+        self.total.load(Ordering::Acquire)
+    }
+
+    /// Per-node capacity for the next assignment.
+    ///
+    /// `ceil((1 + ε) * (total + 1) / nodes)`; the `+ 1` accounts for the
+    /// request being placed, which guarantees at least one node has room.
+    ///
+    /// # Arguments
+    /// * `nodes` - Number of nodes that can receive load (must be > 0)
+    /// * `epsilon` - Imbalance factor; negative or NaN values are treated as 0
+    pub(crate) fn capacity(&self, nodes: usize, epsilon: f64) -> usize {
+        let epsilon = epsilon.max(0.0);
+        let average = (self.total() + 1) as f64 / nodes as f64;
+        ((1.0 + epsilon) * average).ceil() as usize
+    }
+
+    /// Increment a node's load unconditionally.
+    pub(crate) fn acquire(&self, node_id: NodeId) {
+        *self.loads.entry(node_id).or_insert(0) += 1;
+        self.total.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Increment a node's load only if it is below `capacity`.
+    ///
+    /// # Returns
+    /// `true` if the load was acquired
+    pub(crate) fn try_acquire(&self, node_id: NodeId, capacity: usize) -> bool {
+        let mut load = self.loads.entry(node_id).or_insert(0);
+        if *load >= capacity {
+            return false;
+        }
+        *load += 1;
+        self.total.fetch_add(1, Ordering::AcqRel);
+        true
+    }
+
+    /// Decrement a node's load (saturating at zero).
+    pub(crate) fn release(&self, node_id: &NodeId) {
+        let released = match self.loads.get_mut(node_id) {
+            Some(mut load) if *load > 0 => {
+                *load -= 1;
+                true
+            }
+            _ => false,
+        };
+        if released {
+            self.total.fetch_sub(1, Ordering::AcqRel);
+        }
+        // Drop zero entries so removed nodes don't linger forever
+        self.loads.remove_if(node_id, |_, load| *load == 0);
+    }
+}
+
+/// RAII guard for one unit of in-flight load on a node.
+///
+/// Obtained from `HashRing::acquire_load()` or
+/// `HashRing::lookup_bounded_acquire()`. The load is released when the
+/// guard is dropped, so callers can't forget to decrement after a request
+/// completes (or panics).
+///
+/// # Example
+/// ```rust
+/// # use corelib::ring::HashRing;
+/// # use corelib::{Node, NodeId};
+/// let ring = HashRing::new();
+/// ring.add_node(Node::new(NodeId(1), "node1"), 4);
+///
+/// {
+///     let guard = ring.lookup_bounded_acquire(b"key", 0.25).unwrap();
+///     assert_eq!(ring.load(&guard.node_id()), 1);
+/// } // released here
+///
+/// assert_eq!(ring.total_load(), 0);
+/// ```
+#[must_use = "load is released as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct LoadGuard {
+    tracker: Arc<LoadTracker>,
+    node_id: NodeId,
+}
+
+impl LoadGuard {
+    /// Wrap an already-acquired unit of load.
+    pub(crate) fn new(tracker: Arc<LoadTracker>, node_id: NodeId) -> Self {
+        Self { tracker, node_id }
+    }
+
+    /// The node this load is charged to.
+    #[inline]
+    pub fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+}
+
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        self.tracker.release(&self.node_id);
+    }
+}
@@ -3,12 +3,23 @@
 //! The ring manages token positions and provides efficient lookup
 //! operations for finding nodes responsible for keys.
 
+#[allow(clippy::module_inception)]
 pub mod ring;
+pub mod change;
+pub mod explain;
+pub mod load;
 pub mod position;
+pub mod state;
+pub mod store;
 pub mod topology;
 
+pub use change::RingChange;
+pub use explain::KeyExplanation;
+pub use load::LoadGuard;
 pub use position::RingPosition;
 pub use ring::{HashRing, RingBuilder};
+pub use state::RingState;
+pub use store::RingStore;
 pub use topology::RingTopology;
 
 /// Alias for the main ring type (used by lib.rs).
@@ -5,6 +5,7 @@
 //! The ring is the core data structure for consistent hashing. It maintains:
 //! 1. **Token → Node mapping**: `BTreeMap<Token, NodeId>` for O(log n) ordered lookups
 //! 2. **Node registry**: `HashMap<NodeId, Node>` for fast node metadata access
+//! 3. **Per-node token index**: `HashMap<NodeId, Vec<Token>>` for O(v) access to a node's vnodes
 //!
 //! # Performance Characteristics
 //!
@@ -13,17 +14,29 @@
 //!   - Single read lock acquisition (no double locking)
 //! - **Add node**: O(v * log n) where v = vnodes per node
 //!   - BTreeMap insertion is O(log n) per token
-//! - **Remove node**: O(n) worst case (must scan all tokens)
-//!   - Uses retain() which is efficient for sparse removals
+//!   - Existing nodes are reconciled via the per-node token index (no ring scan)
+//! - **Update node**: O(1) - metadata only, tokens untouched
+//! - **Remove node**: O(v * log n) - removes exactly the node's indexed tokens
+//! - **Batch apply**: O(c * v * log n) where c = primitive changes in the batch
+//!   - Rollback is O(c * log n) using an undo journal (no full-ring copy)
 //!
 //! # Thread Safety
 //!
 //! - **Read operations** (lookup): Concurrent, lock-free after acquiring read lock
-//! - **Write operations** (add/remove): Exclusive, blocks all readers
+//! - **Write operations** (add/remove/apply): Exclusive, blocks all readers
+//! - Every successful mutation bumps the ring **epoch** exactly once, so a
+//!   batch applied via `HashRing::apply()` is observed as a single transition
 //! - Uses `parking_lot::RwLock` for better performance than std::sync::RwLock
 //!   - Faster read path (no system calls in uncontended case)
 //!   - Writer fairness (prevents reader starvation)
 //!
+//! # Bounded-Load Lookups
+//!
+//! `lookup_bounded()` implements "Consistent Hashing with Bounded Loads"
+//! (Mirrokni et al.): the ring tracks per-node in-flight load (see
+//! `ring::load`) and skips clockwise past nodes whose load would exceed
+//! `(1 + ε)` times the average. Plain `lookup()` ignores load entirely.
+//!
 //! # Virtual Nodes (VNodes)
 //!
 //! Each physical node is represented by multiple virtual nodes (tokens) on the ring.
@@ -32,12 +45,19 @@
 //! - **Gradual rebalancing**: When nodes join/leave, only a fraction of keys move
 //! - **Default**: 256 vnodes per node (good balance of distribution vs memory)
 
+use crate::error::{Error, Result};
 use crate::node::{Node, NodeId};
-use crate::partitioner::traits::Partitioner;
+use crate::partitioner::murmur3::Murmur3Partitioner;
+use crate::partitioner::RingPartitioner;
+use crate::placement::traits::{Placement, ReplicaSelection};
+use crate::ring::change::RingChange;
+use crate::ring::explain::KeyExplanation;
+use crate::ring::load::{LoadGuard, LoadTracker};
+use crate::ring::state::{RingState, RING_STATE_FORMAT_VERSION};
+use crate::token::derivation::{derivation_for_version, DefaultTokenDerivation, TokenDerivation};
 use crate::token::murmur3::Murmur3Token;
-use crate::token::Token;
 use parking_lot::RwLock;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 
 // ============================================================================
@@ -54,6 +74,8 @@ use std::sync::Arc;
 /// 1. Every token in `tokens` maps to a node that exists in `nodes`
 /// 2. `tokens` is always sorted (BTreeMap maintains order)
 /// 3. `tokens` may be empty (ring has no nodes), but `nodes` should match
+/// 4. `node_tokens[id]` holds exactly the tokens `t` with `tokens[t] == id`, sorted
+/// 5. `epoch` increases by exactly one per successful mutation
 struct RingInner {
     /// Token → NodeId mapping (ordered for efficient range queries).
     ///
@@ -76,6 +98,45 @@ struct RingInner {
     /// - Node lookups are frequent and don't need ordering
     /// - Fast node existence checks before operations
     nodes: HashMap<NodeId, Node>,
+
+    /// Reverse index: NodeId → sorted tokens owned by that node.
+    ///
+    /// **Why?**
+    /// - Removal touches only the node's v tokens instead of scanning all n
+    ///   (500 nodes × 256 vnodes = 128k entries per scan under the write lock)
+    /// - Reconciling vnodes on re-add needs the node's current tokens
+    /// - Cost: one extra copy of every token (~8 bytes per vnode)
+    ///
+    /// Must only be mutated through `insert_token()` / `remove_token()` so it
+    /// never drifts from `tokens`.
+    node_tokens: HashMap<NodeId, Vec<Murmur3Token>>,
+
+    /// Topology version, bumped once per successful mutation.
+    ///
+    /// Readers can compare epochs to detect that the ring changed between
+    /// two observations without diffing tokens.
+    epoch: u64,
+
+    /// Scheme mapping `(NodeId, vnode index)` to tokens.
+    ///
+    /// Fixed for the ring's lifetime: switching schemes would move every
+    /// vnode at once.
+    derivation: Arc<dyn TokenDerivation>,
+}
+
+/// Undo journal entry recorded while applying a `RingChange`.
+///
+/// Each entry captures the state needed to reverse one primitive mutation.
+/// Entries are replayed in reverse order on rollback.
+enum Undo {
+    /// A token was inserted at a previously empty position.
+    TokenInserted(Murmur3Token),
+    /// A token was removed (restore it with its previous owner).
+    TokenRemoved(Murmur3Token, NodeId),
+    /// A node was inserted where none existed.
+    NodeInserted(NodeId),
+    /// A node's metadata was replaced or the node was removed (restore it).
+    NodeRestored(Node),
 }
 
 impl RingInner {
@@ -83,11 +144,40 @@ impl RingInner {
     ///
     /// # Performance
     /// - O(1) - No allocations until first node is added
-    fn new() -> Self {
+    fn new(derivation: Arc<dyn TokenDerivation>) -> Self {
         Self {
             tokens: BTreeMap::new(),
             nodes: HashMap::new(),
+            node_tokens: HashMap::new(),
+            epoch: 0,
+            derivation,
+        }
+    }
+
+    /// Rebuild ring state from a validated snapshot.
+    ///
+    /// Tokens are restored exactly as recorded (not re-derived), so moved
+    /// tokens survive the round trip.
+    fn from_state(state: RingState, derivation: Arc<dyn TokenDerivation>) -> Self {
+        let mut inner = Self::new(derivation);
+        for node in state.nodes {
+            inner.nodes.insert(node.id, node);
+        }
+        for (token, node_id) in state.tokens {
+            inner.insert_token(token, node_id);
         }
+        inner.epoch = state.epoch;
+        inner
+    }
+
+    /// Generate the vnode tokens for a node: one per index in [0, vnodes).
+    ///
+    /// Positions come from the ring's `TokenDerivation`, so the same node
+    /// and index always land on the same ring position.
+    fn vnode_tokens(&self, node_id: NodeId, vnodes: usize) -> HashSet<Murmur3Token> {
+        (0..vnodes)
+            .map(|i| self.derivation.derive(node_id, i))
+            .collect()
     }
 
     /// Find the node responsible for a given token (clockwise search).
@@ -139,61 +229,262 @@ impl RingInner {
             .or_else(|| {
                 // Use first_key_value() instead of first() for better performance
                 // (avoids creating a reference to the key)
-                self.tokens
-                    .first_key_value()
-                    .map(|(_, node_id)| *node_id)
+                self.tokens.first_key_value().map(|(_, node_id)| *node_id)
             })
     }
 
-    /// Add a node with virtual nodes (vnodes).
+    /// Find the first node clockwise from a token that `accept` admits.
     ///
     /// # Algorithm
     ///
-    /// For each vnode index i in [0, vnodes):
-    /// 1. Generate a unique vnode key: "node_id:i"
-    /// 2. Hash the key to get a token
-    /// 3. Insert token → node_id mapping
+    /// 1. Walk tokens clockwise starting at the first token >= our token,
+    ///    wrapping around to the start of the ring
+    /// 2. Ask `accept` about each distinct node once (rejected nodes are
+    ///    remembered so their other vnodes are skipped cheaply)
+    /// 3. Stop once every node has been rejected
+    ///
+    /// With an `accept` that always returns `true` this is exactly
+    /// `node_for_token()`.
     ///
     /// # Performance
-    /// - **Time**: O(v * log n) where v = vnodes, n = total tokens
-    ///   - Each BTreeMap insertion is O(log n)
-    ///   - Token generation is O(1) per vnode
-    /// - **Space**: O(v) new entries in BTreeMap
+    /// - **Time**: O(log n + k) where k = tokens walked before acceptance
+    /// - **Space**: O(r) where r = distinct nodes rejected
+    fn node_for_token_where(
+        &self,
+        token: &Murmur3Token,
+        mut accept: impl FnMut(NodeId) -> bool,
+    ) -> Option<NodeId> {
+        let node_total = self.node_tokens.len();
+        let mut rejected: HashSet<NodeId> = HashSet::new();
+
+        let clockwise = self
+            .tokens
+            .range(token..)
+            .chain(self.tokens.range(..*token));
+        for (_, node_id) in clockwise {
+            if rejected.contains(node_id) {
+                continue;
+            }
+            if accept(*node_id) {
+                return Some(*node_id);
+            }
+            rejected.insert(*node_id);
+            if rejected.len() == node_total {
+                break;
+            }
+        }
+        None
+    }
+
+    /// Locate the vnode owning a token and the vnode before it.
+    ///
+    /// # Performance
+    /// - **Time**: O(log n) - two range searches
+    ///
+    /// # Returns
+    /// `(owning token, preceding token, owner, wrapped)`, or `None` if empty
+    fn owning_range(
+        &self,
+        token: &Murmur3Token,
+    ) -> Option<(Murmur3Token, Murmur3Token, NodeId, bool)> {
+        let (owning, owner, wrapped) = match self.tokens.range(token..).next() {
+            Some((owning, owner)) => (*owning, *owner, false),
+            None => {
+                let (first, owner) = self.tokens.first_key_value()?;
+                (*first, *owner, true)
+            }
+        };
+        // The first vnode's range starts at the last vnode (across the top)
+        let previous = self
+            .tokens
+            .range(..owning)
+            .next_back()
+            .or_else(|| self.tokens.last_key_value())
+            .map(|(previous, _)| *previous)?;
+        Some((owning, previous, owner, wrapped))
+    }
+
+    /// Resolve many tokens at once, sorted ascending by token.
+    ///
+    /// # Algorithm
     ///
-    /// # Optimizations
-    /// - Pre-allocate node metadata insertion (HashMap is already efficient)
-    /// - Use string formatting only for vnode key (unavoidable)
-    /// - Token hashing is fast (Murmur3 is optimized)
+    /// Walk the ring and the sorted queries together: each query's owner is
+    /// the first ring token at or after it (wrapping to the first token).
+    /// When there are few queries relative to ring tokens, a binary search
+    /// per query is cheaper than walking every token, so the walk restarts
+    /// from `range()` for each query instead.
+    ///
+    /// # Performance
+    /// - **Time**: O(min(n + m, m log n)) for n tokens and m queries
+    /// - **Space**: O(1) beyond the output
+    ///
+    /// # Arguments
+    /// * `queries` - `(token, key index)` pairs, sorted by token
+    /// * `resolve` - Called with each key index and its owner
+    fn nodes_for_sorted_tokens(
+        &self,
+        queries: &[(Murmur3Token, usize)],
+        mut resolve: impl FnMut(usize, NodeId),
+    ) {
+        let Some((_, &first)) = self.tokens.first_key_value() else {
+            return;
+        };
+
+        // Rough crossover: a merge touches every token, a search ~log n nodes
+        let log_n = usize::BITS - self.tokens.len().leading_zeros();
+        if queries.len().saturating_mul(log_n as usize) < self.tokens.len() {
+            for (token, index) in queries {
+                resolve(*index, self.node_for_token(token).unwrap_or(first));
+            }
+            return;
+        }
+
+        let mut ring = self.tokens.iter().peekable();
+        for (token, index) in queries {
+            while ring
+                .next_if(|(ring_token, _)| *ring_token < token)
+                .is_some()
+            {}
+            let owner = ring.peek().map(|(_, node_id)| **node_id).unwrap_or(first);
+            resolve(*index, owner);
+        }
+    }
+
+    /// Number of nodes that own at least one token (can receive keys).
+    #[inline]
+    fn owning_node_count(&self) -> usize {
+        self.node_tokens.len()
+    }
+
+    /// Insert a token for a node, keeping the per-node index in sync.
+    ///
+    /// # Performance
+    /// - **Time**: O(log n + v) - BTreeMap insert + sorted Vec insert
+    ///
+    /// # Returns
+    /// The previous owner of the token, if any (its index entry is dropped)
+    fn insert_token(&mut self, token: Murmur3Token, node_id: NodeId) -> Option<NodeId> {
+        let previous = self.tokens.insert(token, node_id);
+        if previous == Some(node_id) {
+            return previous;
+        }
+        if let Some(previous_id) = previous {
+            self.unindex_token(&token, &previous_id);
+        }
+        let owned = self.node_tokens.entry(node_id).or_default();
+        if let Err(pos) = owned.binary_search(&token) {
+            owned.insert(pos, token);
+        }
+        previous
+    }
+
+    /// Remove a token, keeping the per-node index in sync.
+    ///
+    /// # Performance
+    /// - **Time**: O(log n + v)
+    ///
+    /// # Returns
+    /// The node that owned the token, or `None` if it wasn't on the ring
+    fn remove_token(&mut self, token: &Murmur3Token) -> Option<NodeId> {
+        let owner = self.tokens.remove(token)?;
+        self.unindex_token(token, &owner);
+        Some(owner)
+    }
+
+    /// Drop a token from a node's index entry (removing empty entries).
+    fn unindex_token(&mut self, token: &Murmur3Token, node_id: &NodeId) {
+        if let Some(owned) = self.node_tokens.get_mut(node_id) {
+            if let Ok(pos) = owned.binary_search(token) {
+                owned.remove(pos);
+            }
+            if owned.is_empty() {
+                self.node_tokens.remove(node_id);
+            }
+        }
+    }
+
+    /// Get the tokens owned by a node (sorted), or an empty slice.
+    ///
+    /// # Performance
+    /// - **Time**: O(1) average case (HashMap lookup)
+    #[inline]
+    fn tokens_of(&self, node_id: &NodeId) -> &[Murmur3Token] {
+        self.node_tokens
+            .get(node_id)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Add a node with virtual nodes (vnodes), reconciling existing ones.
+    ///
+    /// # Algorithm
+    ///
+    /// 1. Compute the desired token set: the ring's `TokenDerivation`
+    ///    assigns a token to each vnode index i in [0, vnodes)
+    /// 2. Remove tokens currently owned by the node (from the per-node index)
+    ///    that are not desired (shrinking the vnode count, or tokens moved via
+    ///    `RingChange::MoveToken`)
+    /// 3. Insert desired tokens that are missing
+    /// 4. Store/update node metadata
+    ///
+    /// For a new node, step 2 is a no-op and this is a plain insertion.
+    ///
+    /// # Performance
+    /// - **Time**: O(v * log n) where v = vnodes, n = total tokens
+    ///   - The node's current tokens come from the per-node index (no scan)
+    ///   - Each BTreeMap insertion/removal is O(log n)
+    /// - **Space**: O(v) for the desired token set
     ///
     /// # Safety
-    /// - If node already exists, metadata is updated (idempotent)
-    /// - Vnodes are added even if node already exists (allows rebalancing)
+    /// - If node already exists, metadata is updated
+    /// - Re-adding with the same vnode count is a no-op for tokens (idempotent)
+    /// - Re-adding with a different vnode count adds or removes the difference
     ///
     /// # Arguments
-    /// * `node` - The node to add (will be cloned for storage)
-    /// * `vnodes` - Number of virtual nodes (typically 128-512)
+    /// * `node` - The node to add
+    /// * `vnodes` - Desired number of virtual nodes (typically 128-512)
     fn add_node(&mut self, node: Node, vnodes: usize) {
+        let node_id = node.id;
+        let desired = self.vnode_tokens(node_id, vnodes);
+
+        // Drop tokens the node should no longer own
+        let stale: Vec<Murmur3Token> = self
+            .tokens_of(&node_id)
+            .iter()
+            .filter(|token| !desired.contains(token))
+            .copied()
+            .collect();
+        for token in &stale {
+            self.remove_token(token);
+        }
+
+        // Insert the missing ones
+        // If token already exists for another node (collision), it's overwritten (shouldn't happen)
+        for token in desired {
+            self.insert_token(token, node_id);
+        }
+
         // Store/update node metadata
-        // HashMap::insert handles both new and existing keys efficiently
-        self.nodes.insert(node.id, node.clone());
-
-        // Generate virtual nodes
-        // We iterate from 0 to vnodes-1, generating a unique token for each
-        // The format "node_id:i" ensures uniqueness across nodes and vnode indices
-        for i in 0..vnodes {
-            // Generate vnode key: "node_id:vnode_index"
-            // Format! is necessary here, but we could optimize with a custom formatter
-            // if this becomes a bottleneck (unlikely for < 1000 vnodes)
-            let vnode_key = format!("{}:{}", node.id, i);
-            
-            // Hash the key to get a token position on the ring
-            // Murmur3Token::from_key() uses Murmur3 hash (fast, good distribution)
-            let token = Murmur3Token::from_key(&vnode_key);
-            
-            // Insert token → node_id mapping
-            // BTreeMap::insert is O(log n) where n = current token count
-            // If token already exists (collision), it's overwritten (shouldn't happen)
-            self.tokens.insert(token, node.id);
+        self.nodes.insert(node_id, node);
+        self.epoch += 1;
+    }
+
+    /// Replace a node's metadata without touching its tokens.
+    ///
+    /// # Performance
+    /// - **Time**: O(1) average case (HashMap lookup + insert)
+    /// - **Space**: O(1)
+    ///
+    /// # Returns
+    /// `true` if the node existed and was updated, `false` otherwise
+    fn update_node(&mut self, node: Node) -> bool {
+        match self.nodes.get_mut(&node.id) {
+            Some(existing) => {
+                *existing = node;
+                self.epoch += 1;
+                true
+            }
+            None => false,
         }
     }
 
@@ -202,21 +493,15 @@ impl RingInner {
     /// # Algorithm
     ///
     /// 1. Check if node exists (fast O(1) lookup)
-    /// 2. Remove all tokens owned by this node using `retain()`
-    /// 3. Remove node metadata
+    /// 2. Take the node's tokens from the per-node index
+    /// 3. Remove each of those tokens from the ring
+    /// 4. Remove node metadata
     ///
     /// # Performance
-    /// - **Time**: O(n) worst case where n = total tokens
-    ///   - `retain()` must check every token
-    ///   - However, it's efficient for sparse removals (only touches matching tokens)
+    /// - **Time**: O(v * log n) where v = node's vnodes, n = total tokens
+    ///   - Only the node's own tokens are touched (no full-ring `retain()`)
     ///   - Node existence check is O(1)
-    /// - **Space**: O(1) - no allocations
-    ///
-    /// # Alternative Approaches Considered
-    /// - **Track vnodes per node**: Would require HashMap<NodeId, Vec<Token>>
-    ///   - Pros: O(v) removal instead of O(n)
-    ///   - Cons: Extra memory, complexity, must maintain consistency
-    ///   - **Decision**: Not worth it - node removal is rare, O(n) is acceptable
+    /// - **Space**: O(1) - the index entry is moved out, not copied
     ///
     /// # Safety
     /// - Returns `false` if node doesn't exist (idempotent)
@@ -234,18 +519,153 @@ impl RingInner {
         }
 
         // Remove all tokens owned by this node
-        // retain() is efficient: it only moves elements that need to be kept
-        // For a node with v vnodes out of n total tokens, this is roughly O(n)
-        // but only touches memory for tokens that need to be removed
-        self.tokens.retain(|_, id| id != node_id);
+        // The index entry is taken wholesale, so each removal is a single
+        // BTreeMap delete (O(log n)) without touching other nodes' tokens
+        for token in self.node_tokens.remove(node_id).unwrap_or_default() {
+            self.tokens.remove(&token);
+        }
 
         // Remove node metadata
         // This is O(1) average case (HashMap removal)
         self.nodes.remove(node_id);
-        
+        self.epoch += 1;
+
         true
     }
 
+    /// Apply a (possibly batched) change, recording undo entries.
+    ///
+    /// # Algorithm
+    ///
+    /// Changes are applied in order directly to the live state. Every
+    /// primitive mutation pushes an `Undo` entry onto `journal` *after* it
+    /// succeeds, so on error the journal describes exactly what must be
+    /// reversed. The caller is responsible for calling `rollback()` on error
+    /// and bumping the epoch on success.
+    ///
+    /// # Validation
+    /// - `AddNode`: generated tokens must not be owned by another node
+    ///   (an existing node is reconciled exactly like `add_node()`)
+    /// - `RemoveNode` / `UpdateNode`: node must exist
+    /// - `MoveToken`: `from` must exist, `to` must be free
+    ///
+    /// # Performance
+    /// - **Time**: O(c * v * log n) where v = vnodes per affected node
+    /// - **Space**: O(c) journal entries
+    fn apply_change(&mut self, change: RingChange, journal: &mut Vec<Undo>) -> Result<()> {
+        match change {
+            RingChange::AddNode { node, vnodes } => {
+                let node_id = node.id;
+                let desired = self.vnode_tokens(node_id, vnodes);
+
+                // Reconcile: drop tokens the node should no longer own
+                let stale: Vec<Murmur3Token> = self
+                    .tokens_of(&node_id)
+                    .iter()
+                    .filter(|token| !desired.contains(token))
+                    .copied()
+                    .collect();
+                for token in stale {
+                    self.remove_token(&token);
+                    journal.push(Undo::TokenRemoved(token, node_id));
+                }
+
+                for token in desired {
+                    match self.tokens.get(&token) {
+                        // Already present for this node (re-add): nothing to do
+                        Some(owner) if *owner == node_id => {}
+                        Some(owner) => {
+                            return Err(Error::InvalidToken(format!(
+                                "token {:016x} for node {} is already owned by node {}",
+                                token.0, node_id, owner
+                            )));
+                        }
+                        None => {
+                            self.insert_token(token, node_id);
+                            journal.push(Undo::TokenInserted(token));
+                        }
+                    }
+                }
+                self.put_node(node, journal);
+            }
+            RingChange::RemoveNode(node_id) => {
+                let node = self.nodes.remove(&node_id).ok_or_else(|| {
+                    Error::InvalidNode(format!("node {} is not in the ring", node_id))
+                })?;
+                for token in self.node_tokens.remove(&node_id).unwrap_or_default() {
+                    self.tokens.remove(&token);
+                    journal.push(Undo::TokenRemoved(token, node_id));
+                }
+                journal.push(Undo::NodeRestored(node));
+            }
+            RingChange::MoveToken { from, to } => {
+                let owner = self.tokens.get(&from).copied().ok_or_else(|| {
+                    Error::InvalidToken(format!("token {:016x} is not on the ring", from.0))
+                })?;
+                if from == to {
+                    return Ok(());
+                }
+                if let Some(existing) = self.tokens.get(&to) {
+                    return Err(Error::InvalidToken(format!(
+                        "token {:016x} is already owned by node {}",
+                        to.0, existing
+                    )));
+                }
+                self.remove_token(&from);
+                journal.push(Undo::TokenRemoved(from, owner));
+                self.insert_token(to, owner);
+                journal.push(Undo::TokenInserted(to));
+            }
+            RingChange::UpdateNode(node) => {
+                if !self.nodes.contains_key(&node.id) {
+                    return Err(Error::InvalidNode(format!(
+                        "node {} is not in the ring",
+                        node.id
+                    )));
+                }
+                self.put_node(node, journal);
+            }
+            RingChange::Batch(changes) => {
+                for change in changes {
+                    self.apply_change(change, journal)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert or replace node metadata, journaling the previous value.
+    fn put_node(&mut self, node: Node, journal: &mut Vec<Undo>) {
+        let node_id = node.id;
+        match self.nodes.insert(node_id, node) {
+            Some(previous) => journal.push(Undo::NodeRestored(previous)),
+            None => journal.push(Undo::NodeInserted(node_id)),
+        }
+    }
+
+    /// Reverse every mutation recorded in `journal` (newest first).
+    ///
+    /// # Performance
+    /// - **Time**: O(c * (log n + v)) where c = journal entries
+    fn rollback(&mut self, journal: Vec<Undo>) {
+        for entry in journal.into_iter().rev() {
+            match entry {
+                Undo::TokenInserted(token) => {
+                    self.remove_token(&token);
+                }
+                Undo::TokenRemoved(token, node_id) => {
+                    self.insert_token(token, node_id);
+                }
+                Undo::NodeInserted(node_id) => {
+                    self.nodes.remove(&node_id);
+                }
+                Undo::NodeRestored(node) => {
+                    self.nodes.insert(node.id, node);
+                }
+            }
+        }
+    }
+
     /// Get node metadata by ID.
     ///
     /// # Performance
@@ -349,17 +769,19 @@ impl RingInner {
 ///
 /// - **Lookup**: O(log n) time, O(1) space, concurrent reads
 /// - **Add node**: O(v * log n) time, O(v) space, exclusive write
-/// - **Remove node**: O(n) time, O(1) space, exclusive write
+/// - **Remove node**: O(v * log n) time, O(1) space, exclusive write
 ///
 /// # Memory Layout
 ///
-/// ```
+/// ```text
 /// HashRing {
-///     partitioner: Arc<Murmur3Partitioner>,  // Shared, immutable
+///     partitioner: Arc<RingPartitioner>,   // Shared, immutable
 ///     inner: Arc<RwLock<RingInner>> {       // Shared, mutable
 ///         tokens: BTreeMap<Token, NodeId>,   // ~24 bytes per entry
 ///         nodes: HashMap<NodeId, Node>,       // ~32 bytes per entry + Node size
+///         node_tokens: HashMap<NodeId, Vec<Token>>, // ~8 bytes per token + ~40 per node
 ///     }
+///     load: Arc<LoadTracker>,               // Shared, lock-free of the ring
 /// }
 /// ```
 ///
@@ -375,6 +797,7 @@ impl RingInner {
 /// // Concurrent lookups are safe
 /// let node_id = ring.lookup(b"my-key");
 /// ```
+#[derive(Clone)]
 pub struct HashRing {
     /// Partitioning strategy (shared, immutable).
     ///
@@ -382,7 +805,7 @@ pub struct HashRing {
     /// - Allows sharing partitioner across multiple ring instances
     /// - Immutable, so no synchronization needed
     /// - Cheap to clone (just increments reference count)
-    partitioner: Arc<Murmur3Partitioner>,
+    partitioner: Arc<RingPartitioner>,
 
     /// Internal ring state (protected by RwLock).
     ///
@@ -391,6 +814,13 @@ pub struct HashRing {
     /// - `RwLock` provides concurrent reads, exclusive writes
     /// - Inner state is not thread-safe, so it MUST be behind RwLock
     inner: Arc<RwLock<RingInner>>,
+
+    /// Per-node in-flight load for bounded-load lookups.
+    ///
+    /// **Why outside `inner`?**
+    /// - Load changes per request; topology changes are rare
+    /// - Acquiring/releasing load must not contend with the ring lock
+    load: Arc<LoadTracker>,
 }
 
 impl HashRing {
@@ -402,31 +832,70 @@ impl HashRing {
     ///
     /// # Defaults
     /// - Partitioner: `Murmur3Partitioner` (Cassandra-compatible)
+    /// - Token derivation: `DefaultTokenDerivation`
     /// - Ring: Empty (no nodes)
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::HashRing;
     /// let ring = HashRing::new();
     /// ```
     pub fn new() -> Self {
-        Self {
-            partitioner: Arc::new(Murmur3Partitioner),
-            inner: Arc::new(RwLock::new(RingInner::new())),
-        }
+        Self::with_partitioner(Arc::new(Murmur3Partitioner))
     }
 
-    /// Create a ring with a custom partitioner (for future extensibility).
+    /// Create a ring with a custom partitioner.
     ///
-    /// # Use Case
-    /// - Testing with different partitioners
-    /// - Future: support for RandomPartitioner, ByteOrderedPartitioner, etc.
+    /// Any partitioner producing 64-bit tokens works (`Murmur3Partitioner`,
+    /// `Xxh3Partitioner`, `Blake3Partitioner`, `SipPartitioner`), or build
+    /// one by name with `config::PartitionerConfig`.
+    ///
+    /// Vnode tokens are independent of the partitioner, so only key
+    /// placement changes; every process sharing a ring must use the same one.
     ///
     /// # Arguments
     /// * `partitioner` - The partitioner to use (wrapped in Arc for sharing)
-    pub fn with_partitioner(partitioner: Arc<Murmur3Partitioner>) -> Self {
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::partitioner::Xxh3Partitioner;
+    /// # use std::sync::Arc;
+    /// let ring = HashRing::with_partitioner(Arc::new(Xxh3Partitioner));
+    /// assert_eq!(ring.partitioner_name(), "Xxh3Partitioner");
+    /// ```
+    pub fn with_partitioner(partitioner: Arc<RingPartitioner>) -> Self {
+        Self::with_partitioner_and_derivation(
+            partitioner,
+            Arc::new(DefaultTokenDerivation::default()),
+        )
+    }
+
+    /// Create a ring with a custom partitioner and vnode token derivation.
+    ///
+    /// Use this to keep an existing ring's vnode positions when its tokens
+    /// were produced by an older scheme (see `token::derivation`).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::partitioner::Murmur3Partitioner;
+    /// # use corelib::token::derivation::LegacyDisplayDerivation;
+    /// # use std::sync::Arc;
+    /// let ring = HashRing::with_partitioner_and_derivation(
+    ///     Arc::new(Murmur3Partitioner),
+    ///     Arc::new(LegacyDisplayDerivation),
+    /// );
+    /// assert_eq!(ring.token_derivation_version(), 0);
+    /// ```
+    pub fn with_partitioner_and_derivation(
+        partitioner: Arc<RingPartitioner>,
+        derivation: Arc<dyn TokenDerivation>,
+    ) -> Self {
         Self {
             partitioner,
-            inner: Arc::new(RwLock::new(RingInner::new())),
+            inner: Arc::new(RwLock::new(RingInner::new(derivation))),
+            load: Arc::new(LoadTracker::new()),
         }
     }
 
@@ -464,6 +933,8 @@ impl HashRing {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # let ring = HashRing::new();
     /// let node_id = ring.lookup(b"my-key");
     /// ```
     #[inline]
@@ -527,27 +998,299 @@ impl HashRing {
     pub fn lookup_node_optimized(&self, key: &[u8]) -> Option<Node> {
         let token = self.partitioner.partition(key);
         let inner = self.inner.read();
-        
+
         // Find node ID
         let node_id = inner.node_for_token(&token)?;
-        
+
         // Get node metadata (same lock, no second acquisition)
         inner.get_node(&node_id).cloned()
     }
 
+    /// Look up many keys under a single read lock.
+    ///
+    /// # Algorithm
+    ///
+    /// 1. Hash every key to a token (no lock held)
+    /// 2. Sort the tokens
+    /// 3. Acquire the read lock once and resolve all tokens in one ordered
+    ///    pass over the ring (falls back to per-token binary search when
+    ///    there are far fewer keys than ring tokens)
+    ///
+    /// Every key sees the same ring state, unlike calling `lookup()` in a
+    /// loop, which may straddle a concurrent topology change.
+    ///
+    /// # Performance
+    /// - **Time**: O(m log m + min(n + m, m log n)) for m keys, n tokens
+    /// - **Space**: O(m)
+    /// - Single read lock acquisition
+    ///
+    /// # Returns
+    /// Owners in the same order as `keys` (`None` for all if the ring is empty)
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{Node, NodeId};
+    /// let ring = HashRing::new();
+    /// ring.add_node(Node::new(NodeId(1), "node1"), 16);
+    /// ring.add_node(Node::new(NodeId(2), "node2"), 16);
+    ///
+    /// let keys: [&[u8]; 3] = [b"a", b"b", b"c"];
+    /// let owners = ring.lookup_many(&keys);
+    /// assert_eq!(owners[1], ring.lookup(b"b"));
+    /// ```
+    pub fn lookup_many(&self, keys: &[&[u8]]) -> Vec<Option<NodeId>> {
+        let queries = self.sorted_tokens(keys);
+        let mut owners = vec![None; keys.len()];
+
+        let inner = self.inner.read();
+        inner.nodes_for_sorted_tokens(&queries, |index, node_id| {
+            owners[index] = Some(node_id);
+        });
+        owners
+    }
+
+    /// Group keys by owning node under a single read lock.
+    ///
+    /// Same algorithm as `lookup_many()`; useful for fanning a multi-get out
+    /// to one request per node.
+    ///
+    /// # Returns
+    /// Node → indices into `keys` (ascending). Empty if the ring is empty.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{Node, NodeId};
+    /// let ring = HashRing::new();
+    /// ring.add_node(Node::new(NodeId(1), "node1"), 16);
+    ///
+    /// let keys: [&[u8]; 2] = [b"a", b"b"];
+    /// let groups = ring.partition_keys(&keys);
+    /// assert_eq!(groups[&NodeId(1)], vec![0, 1]);
+    /// ```
+    pub fn partition_keys(&self, keys: &[&[u8]]) -> HashMap<NodeId, Vec<usize>> {
+        let queries = self.sorted_tokens(keys);
+        let mut groups: HashMap<NodeId, Vec<usize>> = HashMap::new();
+
+        {
+            let inner = self.inner.read();
+            inner.nodes_for_sorted_tokens(&queries, |index, node_id| {
+                groups.entry(node_id).or_default().push(index);
+            });
+        }
+
+        // Queries were visited in token order; restore key order per node
+        for indices in groups.values_mut() {
+            indices.sort_unstable();
+        }
+        groups
+    }
+
+    /// Hash keys and sort `(token, key index)` pairs by token.
+    fn sorted_tokens(&self, keys: &[&[u8]]) -> Vec<(Murmur3Token, usize)> {
+        let mut queries: Vec<(Murmur3Token, usize)> = keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| (self.partitioner.partition(key), index))
+            .collect();
+        queries.sort_unstable();
+        queries
+    }
+
+    /// Explain why a key maps to its node.
+    ///
+    /// Returns the key's token, the owning vnode and the range it covers,
+    /// the owner's metadata and whether the lookup wrapped around. Intended
+    /// for debugging and support tooling; `lookup()` is the fast path.
+    ///
+    /// # Performance
+    /// - **Time**: O(log n) - single read lock acquisition
+    ///
+    /// # Returns
+    /// The explanation, or `None` if the ring is empty
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{Node, NodeId};
+    /// let ring = HashRing::new();
+    /// ring.add_node(Node::new(NodeId(1), "node1"), 8);
+    ///
+    /// let why = ring.explain(b"my-key").unwrap();
+    /// assert_eq!(Some(why.node.id), ring.lookup(b"my-key"));
+    /// assert!(why.key_token <= why.vnode_token || why.wrapped);
+    /// ```
+    pub fn explain(&self, key: &[u8]) -> Option<KeyExplanation> {
+        let key_token = self.partitioner.partition(key);
+        let inner = self.inner.read();
+
+        let (vnode_token, range_start, owner, wrapped) = inner.owning_range(&key_token)?;
+        Some(KeyExplanation {
+            key_token,
+            vnode_token,
+            range_start,
+            node: inner.get_node(&owner)?.clone(),
+            wrapped,
+            partitioner: self.partitioner.name(),
+            epoch: inner.epoch,
+        })
+    }
+
+    /// Find up to `count` distinct nodes for a key, walking clockwise.
+    ///
+    /// # Algorithm
+    ///
+    /// 1. Hash the key and find its primary (same as `lookup()`)
+    /// 2. Keep walking clockwise, collecting each node the first time one of
+    ///    its vnodes is encountered, until `count` nodes are found
+    ///
+    /// # Performance
+    /// - **Time**: O(log n + k) where k = tokens walked (≈ count * n/nodes)
+    /// - **Space**: O(count)
+    /// - Single read lock acquisition
+    ///
+    /// # Returns
+    /// Distinct node IDs, primary first (fewer than `count` if the ring is smaller)
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{Node, NodeId};
+    /// # let ring = HashRing::new();
+    /// ring.add_node(Node::new(NodeId(1), "node1"), 4);
+    /// ring.add_node(Node::new(NodeId(2), "node2"), 4);
+    /// let replicas = ring.replicas(b"my-key", 3);
+    /// assert_eq!(replicas.len(), 2);
+    /// assert_eq!(replicas[0], ring.lookup(b"my-key").unwrap());
+    /// ```
+    pub fn replicas(&self, key: &[u8], count: usize) -> Vec<NodeId> {
+        if count == 0 {
+            return Vec::new();
+        }
+
+        let token = self.partitioner.partition(key);
+        let inner = self.inner.read();
+
+        let mut replicas = Vec::with_capacity(count.min(inner.owning_node_count()));
+        // Reject every node so the walk visits each distinct node once,
+        // stopping early once we have enough
+        inner.node_for_token_where(&token, |node_id| {
+            replicas.push(node_id);
+            replicas.len() >= count
+        });
+        replicas
+    }
+
+    /// Look up a key with bounded loads: skip nodes that are over capacity.
+    ///
+    /// # Algorithm
+    ///
+    /// 1. Compute capacity `ceil((1 + ε) * (L + 1) / n)` where L = total
+    ///    in-flight load and n = nodes owning tokens
+    /// 2. Walk clockwise from the key's token
+    /// 3. Return the first node whose current load is below capacity
+    ///
+    /// Keys whose natural owner has room map exactly as in `lookup()`, so
+    /// most assignments stay stable; only overflow from hot nodes moves.
+    ///
+    /// This only *selects* a node. To charge load for the request, use
+    /// `lookup_bounded_acquire()` (or `acquire_load()` on the result).
+    ///
+    /// # Performance
+    /// - **Time**: O(log n + k) where k = tokens skipped past full nodes
+    /// - **Space**: O(r) where r = full nodes skipped
+    ///
+    /// # Arguments
+    /// * `key` - The key to look up
+    /// * `epsilon` - Allowed imbalance (e.g. 0.25 caps nodes at 125% of
+    ///   average). Smaller values balance better but move more keys.
+    ///   Negative values are treated as 0.
+    ///
+    /// # Returns
+    /// The selected node, or `None` if the ring is empty
+    pub fn lookup_bounded(&self, key: &[u8], epsilon: f64) -> Option<NodeId> {
+        let token = self.partitioner.partition(key);
+        let inner = self.inner.read();
+
+        let nodes = inner.owning_node_count();
+        if nodes == 0 {
+            return None;
+        }
+        let capacity = self.load.capacity(nodes, epsilon);
+        inner.node_for_token_where(&token, |node_id| self.load.load(&node_id) < capacity)
+    }
+
+    /// Look up a key with bounded loads and charge one unit of load to the
+    /// selected node.
+    ///
+    /// Like `lookup_bounded()`, but the capacity check and increment happen
+    /// together per node, so concurrent callers can't both squeeze into a
+    /// node's last free slot.
+    ///
+    /// # Arguments
+    /// * `key` - The key to look up
+    /// * `epsilon` - Allowed imbalance (see `lookup_bounded()`)
+    ///
+    /// # Returns
+    /// A guard that releases the load on drop, or `None` if the ring is empty
+    pub fn lookup_bounded_acquire(&self, key: &[u8], epsilon: f64) -> Option<LoadGuard> {
+        let token = self.partitioner.partition(key);
+        let inner = self.inner.read();
+
+        let nodes = inner.owning_node_count();
+        if nodes == 0 {
+            return None;
+        }
+        let capacity = self.load.capacity(nodes, epsilon);
+        let node_id = inner
+            .node_for_token_where(&token, |node_id| self.load.try_acquire(node_id, capacity))?;
+        Some(LoadGuard::new(Arc::clone(&self.load), node_id))
+    }
+
+    /// Charge one unit of in-flight load to a node, regardless of capacity.
+    ///
+    /// Use this when the node was chosen some other way (e.g. `lookup()` or
+    /// a replica) but the request should still count toward bounded-load
+    /// decisions.
+    ///
+    /// # Returns
+    /// A guard that releases the load on drop
+    pub fn acquire_load(&self, node_id: NodeId) -> LoadGuard {
+        self.load.acquire(node_id);
+        LoadGuard::new(Arc::clone(&self.load), node_id)
+    }
+
+    /// Current in-flight load of a node.
+    ///
+    /// # Performance
+    /// - **Time**: O(1) - no ring lock taken
+    pub fn load(&self, node_id: &NodeId) -> usize {
+        self.load.load(node_id)
+    }
+
+    /// Total in-flight load across all nodes.
+    ///
+    /// # Performance
+    /// - **Time**: O(1) - single atomic read
+    pub fn total_load(&self) -> usize {
+        self.load.total()
+    }
+
     /// Add a node to the ring with the specified number of virtual nodes.
     ///
     /// # Algorithm
     ///
     /// 1. Acquire write lock (exclusive access)
-    /// 2. Store node metadata
-    /// 3. Generate vnodes tokens
-    /// 4. Insert tokens into ring
+    /// 2. Generate the desired vnode tokens
+    /// 3. Remove stale tokens and insert missing ones
+    /// 4. Store node metadata
     ///
     /// # Performance
     /// - **Time**: O(v * log n) where v = vnodes, n = total tokens
     ///   - Lock acquisition: O(1) in uncontended case, may block if readers/writers active
     ///   - Token generation: O(v) - one hash per vnode
+    ///   - Stale token lookup: O(v) - reads the node's entry in the token index
     ///   - Token insertion: O(v * log n) - BTreeMap insertion is O(log n) each
     /// - **Space**: O(v) - new tokens in BTreeMap
     ///
@@ -564,10 +1307,15 @@ impl HashRing {
     ///
     /// # Idempotency
     /// - If node already exists, metadata is updated
-    /// - Vnodes are added even if node exists (allows rebalancing)
+    /// - Vnodes are reconciled: the node ends up with exactly `vnodes` tokens,
+    ///   so re-adding with a smaller count removes the surplus
+    /// - To change only metadata, prefer `update_node()` (no token work)
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{Node, NodeId};
+    /// # let ring = HashRing::new();
     /// ring.add_node(Node::new(NodeId(1), "node1"), 256);
     /// ```
     pub fn add_node(&self, node: Node, vnodes: usize) {
@@ -593,9 +1341,9 @@ impl HashRing {
     /// 4. Remove node metadata
     ///
     /// # Performance
-    /// - **Time**: O(n) worst case where n = total tokens
+    /// - **Time**: O(v * log n) where v = node's vnodes, n = total tokens
     ///   - Lock acquisition: O(1) in uncontended case
-    ///   - Token removal: O(n) - must check every token
+    ///   - Token removal: O(v * log n) - only the node's indexed tokens
     ///   - Node removal: O(1) average case
     /// - **Space**: O(1) - no allocations
     ///
@@ -615,6 +1363,9 @@ impl HashRing {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::NodeId;
+    /// # let ring = HashRing::new();
     /// ring.remove_node(&NodeId(1));
     /// ```
     pub fn remove_node(&self, node_id: &NodeId) -> bool {
@@ -626,6 +1377,179 @@ impl HashRing {
         // Lock is automatically released
     }
 
+    /// Update a node's metadata (name, datacenter, rack) in place.
+    ///
+    /// Tokens are not touched, so no keys move.
+    ///
+    /// # Performance
+    /// - **Time**: O(1) average case
+    /// - **Space**: O(1)
+    ///
+    /// # Arguments
+    /// * `node` - The new metadata; `node.id` selects the node to update
+    ///
+    /// # Returns
+    /// `true` if the node was updated, `false` if it doesn't exist
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{Node, NodeId};
+    /// # let ring = HashRing::new();
+    /// ring.add_node(Node::new(NodeId(1), "node1"), 4);
+    /// let updated = Node::with_topology(NodeId(1), "node1", Some("dc1".into()), None);
+    /// assert!(ring.update_node(updated));
+    /// ```
+    pub fn update_node(&self, node: Node) -> bool {
+        let mut inner = self.inner.write();
+        inner.update_node(node)
+    }
+
+    /// Apply a topology change atomically.
+    ///
+    /// # Algorithm
+    ///
+    /// 1. Acquire write lock (exclusive access)
+    /// 2. Apply each primitive change in order, journaling undo entries
+    /// 3. On validation failure: replay the journal in reverse and return the error
+    /// 4. On success: bump the epoch once for the whole batch
+    ///
+    /// Readers never observe intermediate states: they either see the ring
+    /// before the batch or after it.
+    ///
+    /// # Performance
+    /// - **Time**: O(c * v * log n) where c = primitive changes, v = vnodes per node
+    /// - **Space**: O(c) for the undo journal
+    ///
+    /// # Arguments
+    /// * `change` - A single change or a `RingChange::Batch`
+    ///
+    /// # Returns
+    /// The ring epoch after the change. If the change mutated nothing (e.g.
+    /// an empty batch), the epoch is unchanged.
+    ///
+    /// # Errors
+    /// - `Error::InvalidNode` if a removed/updated node does not exist
+    /// - `Error::InvalidToken` on token collisions or unknown move sources
+    ///
+    /// On error the ring (including its epoch) is left exactly as it was.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::{HashRing, RingChange};
+    /// # use corelib::{Node, NodeId};
+    /// let ring = HashRing::new();
+    /// let result = ring.apply(RingChange::batch([
+    ///     RingChange::add_node(Node::new(NodeId(1), "node1"), 4),
+    ///     RingChange::remove_node(NodeId(99)), // fails validation
+    /// ]));
+    ///
+    /// assert!(result.is_err());
+    /// assert_eq!(ring.node_count(), 0); // rolled back
+    /// ```
+    pub fn apply(&self, change: RingChange) -> Result<u64> {
+        self.apply_with(change, |_| Ok(()))
+    }
+
+    /// Apply a change, keeping it only if `commit` succeeds.
+    ///
+    /// `commit` receives the epoch the change produces. It runs under the
+    /// write lock, after validation and before any reader can observe the
+    /// change; if it fails the change is rolled back and its error
+    /// returned. Changes that mutate nothing skip `commit`.
+    ///
+    /// Used by `RingStore` to make a change durable before it is visible.
+    pub(crate) fn apply_with<F>(&self, change: RingChange, commit: F) -> Result<u64>
+    where
+        F: FnOnce(u64) -> Result<()>,
+    {
+        let mut inner = self.inner.write();
+
+        let mut journal = Vec::with_capacity(change.len());
+        if let Err(err) = inner.apply_change(change, &mut journal) {
+            inner.rollback(journal);
+            return Err(err);
+        }
+        if journal.is_empty() {
+            return Ok(inner.epoch);
+        }
+
+        let epoch = inner.epoch + 1;
+        if let Err(err) = commit(epoch) {
+            inner.rollback(journal);
+            return Err(err);
+        }
+        inner.epoch = epoch;
+        Ok(epoch)
+    }
+
+    /// Get the current ring epoch.
+    ///
+    /// The epoch starts at 0 and is bumped once per successful mutation
+    /// (`add_node`, `remove_node`, `update_node`, or an entire `apply` batch).
+    ///
+    /// # Performance
+    /// - **Time**: O(1)
+    pub fn epoch(&self) -> u64 {
+        self.inner.read().epoch
+    }
+
+    /// Capture a serializable snapshot of the ring.
+    ///
+    /// Nodes are sorted by ID and tokens by value, so equal rings produce
+    /// byte-identical snapshots. In-flight load is not included.
+    ///
+    /// # Performance
+    /// - **Time**: O(n log n + t) - single read lock acquisition
+    /// - **Space**: O(n + t)
+    pub fn snapshot(&self) -> RingState {
+        let inner = self.inner.read();
+        let mut nodes: Vec<Node> = inner.nodes.values().cloned().collect();
+        nodes.sort_by_key(|node| node.id);
+
+        RingState {
+            format_version: RING_STATE_FORMAT_VERSION,
+            partitioner: self.partitioner.name().to_string(),
+            token_derivation: inner.derivation.version(),
+            epoch: inner.epoch,
+            nodes,
+            tokens: inner.tokens(),
+        }
+    }
+
+    /// Rebuild a ring from a snapshot.
+    ///
+    /// # Arguments
+    /// * `state` - The snapshot (validated again here)
+    /// * `partitioner` - Must be the partitioner the snapshot was taken with;
+    ///   keyed partitioners can't be recreated from the name alone
+    ///
+    /// # Errors
+    /// - Any `RingState::validate()` error
+    /// - `Error::Config` if `partitioner` doesn't match `state.partitioner`
+    pub fn from_state(state: RingState, partitioner: Arc<RingPartitioner>) -> Result<Self> {
+        state.validate()?;
+        if partitioner.name() != state.partitioner {
+            return Err(Error::Config(format!(
+                "ring state was written with {} but {} was supplied",
+                state.partitioner,
+                partitioner.name()
+            )));
+        }
+        let derivation = derivation_for_version(state.token_derivation).ok_or_else(|| {
+            Error::Config(format!(
+                "unknown token derivation version {}",
+                state.token_derivation
+            ))
+        })?;
+
+        Ok(Self {
+            partitioner,
+            inner: Arc::new(RwLock::new(RingInner::from_state(state, derivation))),
+            load: Arc::new(LoadTracker::new()),
+        })
+    }
+
     /// Get node metadata by ID.
     ///
     /// # Performance
@@ -667,6 +1591,33 @@ impl HashRing {
         inner.tokens()
     }
 
+    /// Get the tokens owned by a node, sorted by token value.
+    ///
+    /// # Performance
+    /// - **Time**: O(v) where v = the node's vnodes (served from the per-node index)
+    /// - **Space**: O(v) - copies the node's tokens
+    ///
+    /// # Arguments
+    /// * `node_id` - The node to inspect
+    ///
+    /// # Returns
+    /// The node's tokens, or `None` if the node is not in the ring
+    ///
+    /// # Example
+    /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{Node, NodeId};
+    /// # let ring = HashRing::new();
+    /// ring.add_node(Node::new(NodeId(1), "node1"), 4);
+    /// assert_eq!(ring.tokens_of(&NodeId(1)).map(|t| t.len()), Some(4));
+    /// assert_eq!(ring.tokens_of(&NodeId(2)), None);
+    /// ```
+    pub fn tokens_of(&self, node_id: &NodeId) -> Option<Vec<Murmur3Token>> {
+        let inner = self.inner.read();
+        inner.get_node(node_id)?;
+        Some(inner.tokens_of(node_id).to_vec())
+    }
+
     /// Get all nodes in the ring.
     ///
     /// # Performance
@@ -725,6 +1676,13 @@ impl HashRing {
     pub fn partitioner_name(&self) -> &'static str {
         self.partitioner.name()
     }
+
+    /// Version of the scheme deriving vnode tokens (see `token::derivation`).
+    ///
+    /// Persist this with the ring so it can be rebuilt with identical tokens.
+    pub fn token_derivation_version(&self) -> u32 {
+        self.inner.read().derivation.version()
+    }
 }
 
 impl Default for HashRing {
@@ -733,6 +1691,30 @@ impl Default for HashRing {
     }
 }
 
+impl Placement for HashRing {
+    fn locate(&self, key: &[u8]) -> Option<NodeId> {
+        self.lookup(key)
+    }
+
+    fn node_count(&self) -> usize {
+        HashRing::node_count(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "HashRing"
+    }
+}
+
+impl ReplicaSelection for HashRing {
+    fn replicas(&self, key: &[u8], count: usize) -> Vec<NodeId> {
+        HashRing::replicas(self, key, count)
+    }
+
+    fn node(&self, node_id: &NodeId) -> Option<Node> {
+        self.get_node(node_id)
+    }
+}
+
 // ============================================================================
 // Ring Builder (Fluent API)
 // ============================================================================
@@ -743,6 +1725,10 @@ impl Default for HashRing {
 ///
 /// Uses the builder pattern to allow fluent construction:
 /// ```rust
+/// # use corelib::ring::RingBuilder;
+/// # use corelib::{Node, NodeId};
+/// # let node1 = Node::new(NodeId(1), "node1");
+/// # let node2 = Node::new(NodeId(2), "node2");
 /// let ring = RingBuilder::new()
 ///     .with_vnodes(512)
 ///     .add_node(node1)
@@ -779,6 +1765,7 @@ impl RingBuilder {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::RingBuilder;
     /// let builder = RingBuilder::new();
     /// ```
     pub fn new() -> Self {
@@ -803,6 +1790,8 @@ impl RingBuilder {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::RingBuilder;
+    /// # let builder = RingBuilder::new();
     /// builder.with_vnodes(512);
     /// ```
     pub fn with_vnodes(mut self, vnodes: usize) -> Self {
@@ -824,9 +1813,12 @@ impl RingBuilder {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::RingBuilder;
+    /// # use corelib::{Node, NodeId};
+    /// # let builder = RingBuilder::new();
     /// builder.add_node(Node::new(NodeId(1), "node1"));
     /// ```
-    pub fn add_node(mut self, node: Node) -> Self {
+    pub fn add_node(self, node: Node) -> Self {
         // Add node with default vnodes
         // This acquires a write lock, so it's not free
         // But it's necessary to build the ring incrementally
@@ -849,9 +1841,12 @@ impl RingBuilder {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::RingBuilder;
+    /// # use corelib::{Node, NodeId};
+    /// # let builder = RingBuilder::new();
     /// builder.add_node_with_vnodes(Node::new(NodeId(1), "node1"), 512);
     /// ```
-    pub fn add_node_with_vnodes(mut self, node: Node, vnodes: usize) -> Self {
+    pub fn add_node_with_vnodes(self, node: Node, vnodes: usize) -> Self {
         self.ring.add_node(node, vnodes);
         self
     }
@@ -867,6 +1862,8 @@ impl RingBuilder {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::RingBuilder;
+    /// # let builder = RingBuilder::new();
     /// let ring = builder.build();
     /// ```
     pub fn build(self) -> HashRing {
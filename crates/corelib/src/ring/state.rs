@@ -0,0 +1,214 @@
+//! Serializable snapshots of a `HashRing`.
+//!
+//! A `RingState` captures everything needed to rebuild a ring with
+//! identical key placement: partitioner, vnode token derivation scheme,
+//! node metadata, every token and the epoch. It can be exported as JSON
+//! (human-readable, for debugging and tooling) or bincode (compact, for
+//! storage and the wire).
+//!
+//! # Validation
+//!
+//! Snapshots come from disk or from peers, so `validate()` (run by every
+//! decoder and by `HashRing::from_state()`) rejects states that would
+//! break ring invariants:
+//!
+//! - Unsupported format version or unknown token derivation
+//! - Duplicate nodes or duplicate tokens
+//! - Tokens owned by nodes missing from `nodes`
+//!
+//! # Example
+//!
+//! ```rust
+//! use corelib::ring::{HashRing, RingState};
+//! use corelib::partitioner::Murmur3Partitioner;
+//! use corelib::{Node, NodeId};
+//! use std::sync::Arc;
+//!
+//! let ring = HashRing::new();
+//! ring.add_node(Node::new(NodeId(1), "node1"), 8);
+//!
+//! let json = ring.snapshot().to_json().unwrap();
+//! let state = RingState::from_json(&json).unwrap();
+//! let restored = HashRing::from_state(state, Arc::new(Murmur3Partitioner)).unwrap();
+//!
+//! assert_eq!(restored.tokens(), ring.tokens());
+//! assert_eq!(restored.epoch(), ring.epoch());
+//! ```
+
+use crate::error::{Error, Result};
+use crate::node::{Node, NodeId};
+use crate::token::derivation::derivation_for_version;
+use crate::token::murmur3::Murmur3Token;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Current `RingState` format version.
+///
+/// Bump when the snapshot layout changes; decoders reject newer versions
+/// instead of misreading them.
+pub const RING_STATE_FORMAT_VERSION: u32 = 1;
+
+/// Point-in-time snapshot of a `HashRing`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RingState {
+    /// Snapshot layout version (`RING_STATE_FORMAT_VERSION` when written).
+    pub format_version: u32,
+    /// Name of the partitioner keys were placed with (e.g. "Murmur3Partitioner").
+    pub partitioner: String,
+    /// Version of the vnode token derivation scheme.
+    pub token_derivation: u32,
+    /// Ring epoch at the time of the snapshot.
+    pub epoch: u64,
+    /// All nodes with metadata, sorted by `NodeId`.
+    pub nodes: Vec<Node>,
+    /// All tokens with their owners, sorted by token.
+    pub tokens: Vec<(Murmur3Token, NodeId)>,
+}
+
+impl RingState {
+    /// Check that the snapshot describes a valid ring.
+    ///
+    /// # Performance
+    /// - **Time**: O(n + t) for n nodes and t tokens
+    ///
+    /// # Errors
+    /// - `Error::Serialization` for an unsupported format version
+    /// - `Error::Config` for an unknown token derivation version
+    /// - `Error::InvalidNode` for duplicate nodes or unknown token owners
+    /// - `Error::InvalidToken` for duplicate tokens
+    pub fn validate(&self) -> Result<()> {
+        if self.format_version != RING_STATE_FORMAT_VERSION {
+            return Err(Error::Serialization(format!(
+                "unsupported ring state format version {} (expected {})",
+                self.format_version, RING_STATE_FORMAT_VERSION
+            )));
+        }
+        if derivation_for_version(self.token_derivation).is_none() {
+            return Err(Error::Config(format!(
+                "unknown token derivation version {}",
+                self.token_derivation
+            )));
+        }
+
+        let mut node_ids = HashSet::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            if !node_ids.insert(node.id) {
+                return Err(Error::InvalidNode(format!("duplicate node {}", node.id)));
+            }
+        }
+
+        let mut tokens = HashSet::with_capacity(self.tokens.len());
+        for (token, owner) in &self.tokens {
+            if !tokens.insert(*token) {
+                return Err(Error::InvalidToken(format!("duplicate token {}", token.0)));
+            }
+            if !node_ids.contains(owner) {
+                return Err(Error::InvalidNode(format!(
+                    "token {} is owned by unknown node {}",
+                    token.0, owner
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Encode as JSON.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    /// Encode as indented JSON (for humans).
+    pub fn to_json_pretty(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    /// Decode from JSON and validate.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let state: Self =
+            serde_json::from_str(json).map_err(|e| Error::Serialization(e.to_string()))?;
+        state.validate()?;
+        Ok(state)
+    }
+
+    /// Encode as bincode.
+    pub fn to_bincode(&self) -> Result<Vec<u8>> {
+        bincode::serialize(self).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    /// Decode from bincode and validate.
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self> {
+        let state: Self =
+            bincode::deserialize(bytes).map_err(|e| Error::Serialization(e.to_string()))?;
+        state.validate()?;
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> RingState {
+        RingState {
+            format_version: RING_STATE_FORMAT_VERSION,
+            partitioner: "Murmur3Partitioner".to_string(),
+            token_derivation: 1,
+            epoch: 3,
+            nodes: vec![
+                Node::new(NodeId(1), "node1"),
+                Node::with_topology(NodeId(u128::MAX), "node2", Some("dc1".into()), None),
+            ],
+            tokens: vec![
+                (Murmur3Token(10), NodeId(1)),
+                (Murmur3Token(20), NodeId(u128::MAX)),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let state = state();
+        assert_eq!(
+            RingState::from_json(&state.to_json().unwrap()).unwrap(),
+            state
+        );
+        assert_eq!(
+            RingState::from_bincode(&state.to_bincode().unwrap()).unwrap(),
+            state
+        );
+    }
+
+    #[test]
+    fn test_validation_rejects_bad_states() {
+        let mut duplicate_token = state();
+        duplicate_token.tokens.push((Murmur3Token(10), NodeId(1)));
+        assert!(matches!(
+            duplicate_token.validate(),
+            Err(Error::InvalidToken(_))
+        ));
+
+        let mut unknown_owner = state();
+        unknown_owner.tokens.push((Murmur3Token(30), NodeId(9)));
+        assert!(matches!(
+            unknown_owner.validate(),
+            Err(Error::InvalidNode(_))
+        ));
+
+        let mut duplicate_node = state();
+        duplicate_node.nodes.push(Node::new(NodeId(1), "again"));
+        assert!(matches!(
+            duplicate_node.validate(),
+            Err(Error::InvalidNode(_))
+        ));
+
+        let mut future = state();
+        future.format_version += 1;
+        assert!(RingState::from_json(&future.to_json().unwrap()).is_err());
+
+        let mut derivation = state();
+        derivation.token_derivation = 99;
+        assert!(matches!(derivation.validate(), Err(Error::Config(_))));
+
+        assert!(RingState::from_bincode(&[1, 2, 3]).is_err());
+    }
+}
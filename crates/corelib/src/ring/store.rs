@@ -0,0 +1,573 @@
+//! Crash-safe on-disk persistence for a `HashRing`.
+//!
+//! # Layout
+//!
+//! ```text
+//! <dir>/ring.snapshot   "RSN1" ‖ crc32c(u32 LE) ‖ bincode(RingState)
+//! <dir>/ring.wal        record*
+//!
+//! record = len(u32 LE) ‖ crc32c(payload)(u32 LE) ‖ payload
+//! payload = bincode(WalRecord { epoch, change })
+//! ```
+//!
+//! # Write Path
+//!
+//! `RingStore::apply()` validates a `RingChange` against the in-memory ring,
+//! appends it to the write-ahead log and `fsync`s, and only then lets ring
+//! readers see it. A change is durable once `apply()` returns `Ok`; if
+//! logging fails the ring is left unchanged and the partial record is cut
+//! off the log, so later appends never land behind torn bytes. If even
+//! that fails the store is poisoned and refuses further changes until it
+//! is reopened (recovery truncates the torn tail).
+//!
+//! Every `snapshot_every` records the log is compacted: the full ring is
+//! written to `ring.snapshot.tmp`, fsynced, atomically renamed over
+//! `ring.snapshot`, and only then is the log truncated.
+//!
+//! # Recovery
+//!
+//! `RingStore::open()` loads the snapshot (if any) and replays the log:
+//!
+//! - Records at or below the snapshot epoch are skipped (a crash between
+//!   snapshot rename and log truncation leaves them behind)
+//! - A torn or corrupt record ends the log: it and everything after it is
+//!   truncated, since a crash mid-append can only damage the tail
+//! - A corrupt snapshot is an error, never silently ignored: it is the only
+//!   copy of everything compacted out of the log
+//!
+//! # Concurrency
+//!
+//! Writers are serialized by the store; readers use `ring()` as usual. All
+//! mutations must go through the store: changes made directly on the ring
+//! are not logged and are lost on restart.
+
+use crate::error::{Error, Result};
+use crate::partitioner::RingPartitioner;
+use crate::ring::change::RingChange;
+use crate::ring::ring::HashRing;
+use crate::ring::state::RingState;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Default number of log records between snapshots.
+pub const DEFAULT_SNAPSHOT_EVERY: usize = 1024;
+
+const SNAPSHOT_FILE: &str = "ring.snapshot";
+const SNAPSHOT_TMP_FILE: &str = "ring.snapshot.tmp";
+const WAL_FILE: &str = "ring.wal";
+const SNAPSHOT_MAGIC: &[u8; 4] = b"RSN1";
+/// Length + checksum prefix of every log record.
+const RECORD_HEADER_LEN: usize = 8;
+/// Upper bound on a single record; larger lengths mean a corrupt header.
+const MAX_RECORD_LEN: usize = 64 * 1024 * 1024;
+
+fn storage_error(context: &str, path: &Path, err: std::io::Error) -> Error {
+    Error::Storage(format!("{} {}: {}", context, path.display(), err))
+}
+
+/// One logged mutation: the change and the epoch it produced.
+#[derive(Debug, Serialize, Deserialize)]
+struct WalRecord {
+    epoch: u64,
+    change: RingChange,
+}
+
+/// Open log file and the number of records since the last snapshot.
+struct Wal {
+    file: File,
+    records: usize,
+    /// Length of the log up to the last complete record.
+    len: u64,
+    /// Set when a failed append could not be cut off the log.
+    poisoned: bool,
+    /// Test hook: the next append writes this many bytes, then fails.
+    #[cfg(test)]
+    fail_after: Option<usize>,
+}
+
+impl Wal {
+    /// Append a record and `fsync` it.
+    ///
+    /// On failure the log is truncated back to its last complete record;
+    /// if that fails too the log is poisoned.
+    fn append(&mut self, record: &[u8]) -> io::Result<()> {
+        let result = self.write_record(record);
+        match &result {
+            Ok(()) => self.len += record.len() as u64,
+            Err(_) => {
+                let len = self.len;
+                if self
+                    .file
+                    .set_len(len)
+                    .and_then(|_| self.file.sync_data())
+                    .is_err()
+                {
+                    self.poisoned = true;
+                }
+            }
+        }
+        result
+    }
+
+    fn write_record(&mut self, record: &[u8]) -> io::Result<()> {
+        #[cfg(test)]
+        if let Some(written) = self.fail_after.take() {
+            self.file.write_all(&record[..written])?;
+            return Err(io::Error::other("injected failure"));
+        }
+        self.file.write_all(record)?;
+        self.file.sync_data()
+    }
+}
+
+/// Summary of what `RingStore::open()` found on disk.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Recovery {
+    /// Epoch of the loaded snapshot, if one existed.
+    pub snapshot_epoch: Option<u64>,
+    /// Log records applied on top of the snapshot.
+    pub replayed: usize,
+    /// Log records skipped because the snapshot already contained them.
+    pub skipped: usize,
+    /// Bytes of torn or corrupt log tail that were truncated.
+    pub truncated_bytes: u64,
+}
+
+/// Durable `HashRing`: snapshot plus write-ahead log in a local directory.
+///
+/// # Example
+///
+/// ```rust
+/// use corelib::partitioner::Murmur3Partitioner;
+/// use corelib::ring::{RingChange, RingStore};
+/// use corelib::{Node, NodeId};
+/// use std::sync::Arc;
+///
+/// # let dir = tempfile::tempdir().unwrap();
+/// let store = RingStore::open(dir.path(), Arc::new(Murmur3Partitioner)).unwrap();
+/// store
+///     .apply(RingChange::add_node(Node::new(NodeId(1), "node1"), 8))
+///     .unwrap();
+/// drop(store);
+///
+/// // After a restart the ring comes back without contacting peers
+/// let store = RingStore::open(dir.path(), Arc::new(Murmur3Partitioner)).unwrap();
+/// assert_eq!(store.ring().node_count(), 1);
+/// ```
+pub struct RingStore {
+    dir: PathBuf,
+    ring: HashRing,
+    wal: Mutex<Wal>,
+    snapshot_every: usize,
+    recovery: Recovery,
+}
+
+impl RingStore {
+    /// Open (or create) a store in `dir` and recover its ring.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory holding the snapshot and log (created if missing)
+    /// * `partitioner` - Must match the partitioner the ring was saved with
+    ///
+    /// # Errors
+    /// - `Error::Storage` on I/O failure, a corrupt snapshot, or a log
+    ///   record that no longer applies to the recovered ring
+    /// - Any `HashRing::from_state()` error for the snapshot
+    pub fn open(dir: impl AsRef<Path>, partitioner: Arc<RingPartitioner>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| storage_error("creating", &dir, e))?;
+
+        // A leftover temp file is an interrupted compaction; the old
+        // snapshot plus the log are still authoritative
+        let tmp = dir.join(SNAPSHOT_TMP_FILE);
+        if tmp.exists() {
+            fs::remove_file(&tmp).map_err(|e| storage_error("removing", &tmp, e))?;
+        }
+
+        let mut recovery = Recovery::default();
+        let ring = match read_snapshot(&dir.join(SNAPSHOT_FILE))? {
+            Some(state) => {
+                recovery.snapshot_epoch = Some(state.epoch);
+                HashRing::from_state(state, partitioner)?
+            }
+            None => HashRing::with_partitioner(partitioner),
+        };
+
+        let wal_path = dir.join(WAL_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&wal_path)
+            .map_err(|e| storage_error("opening", &wal_path, e))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .map_err(|e| storage_error("reading", &wal_path, e))?;
+
+        let (records, valid_len) = decode_records(&bytes);
+        if valid_len < bytes.len() {
+            recovery.truncated_bytes = (bytes.len() - valid_len) as u64;
+            file.set_len(valid_len as u64)
+                .and_then(|_| file.sync_all())
+                .map_err(|e| storage_error("truncating", &wal_path, e))?;
+        }
+
+        for record in &records {
+            if record.epoch <= ring.epoch() {
+                recovery.skipped += 1;
+                continue;
+            }
+            let epoch = ring.apply(record.change.clone()).map_err(|e| {
+                Error::Storage(format!(
+                    "log record for epoch {} no longer applies: {}",
+                    record.epoch, e
+                ))
+            })?;
+            if epoch != record.epoch {
+                return Err(Error::Storage(format!(
+                    "log replay diverged: record epoch {} produced epoch {}",
+                    record.epoch, epoch
+                )));
+            }
+            recovery.replayed += 1;
+        }
+
+        Ok(Self {
+            dir,
+            ring,
+            wal: Mutex::new(Wal {
+                file,
+                records: records.len(),
+                len: valid_len as u64,
+                poisoned: false,
+                #[cfg(test)]
+                fail_after: None,
+            }),
+            snapshot_every: DEFAULT_SNAPSHOT_EVERY,
+            recovery,
+        })
+    }
+
+    /// Compact after `records` log records (clamped to at least 1).
+    pub fn with_snapshot_every(mut self, records: usize) -> Self {
+        self.snapshot_every = records.max(1);
+        self
+    }
+
+    /// The recovered, live ring (clone it freely for lookups).
+    pub fn ring(&self) -> &HashRing {
+        &self.ring
+    }
+
+    /// What `open()` found on disk.
+    pub fn recovery(&self) -> Recovery {
+        self.recovery
+    }
+
+    /// Directory the store persists to.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of log records since the last snapshot.
+    pub fn wal_len(&self) -> usize {
+        self.wal.lock().records
+    }
+
+    /// Durably log a change, then apply it to the ring.
+    ///
+    /// Changes that fail validation are not logged. Changes that leave the
+    /// ring untouched (empty batches) are not logged either. Readers see
+    /// the change only once it is logged; ring lookups wait for the
+    /// `fsync`.
+    ///
+    /// # Performance
+    /// - One `fsync` per call, plus a snapshot every `snapshot_every` calls
+    ///
+    /// # Returns
+    /// The ring epoch after the change
+    ///
+    /// # Errors
+    /// - Any `HashRing::apply()` error (nothing is logged)
+    /// - `Error::Storage` if logging fails or the store is poisoned. The
+    ///   ring is left unchanged. A failed compaction is not reported here:
+    ///   the change is already durable, and compaction is retried on the
+    ///   next call (or reported by `compact()`).
+    pub fn apply(&self, change: RingChange) -> Result<u64> {
+        let mut wal = self.wal.lock();
+        let path = self.dir.join(WAL_FILE);
+        if wal.poisoned {
+            return Err(Error::Storage(format!(
+                "{} has a torn record after a failed write; reopen the store",
+                path.display()
+            )));
+        }
+
+        let logged = change.clone();
+        let epoch = self.ring.apply_with(change, |epoch| {
+            let payload = bincode::serialize(&WalRecord {
+                epoch,
+                change: logged,
+            })
+            .map_err(|e| Error::Serialization(e.to_string()))?;
+            let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+            record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            record.extend_from_slice(&crc32c::crc32c(&payload).to_le_bytes());
+            record.extend_from_slice(&payload);
+
+            wal.append(&record)
+                .map_err(|e| storage_error("appending to", &path, e))?;
+            wal.records += 1;
+            Ok(())
+        })?;
+
+        if wal.records >= self.snapshot_every {
+            // Best effort: the change is durable in the log either way
+            let _ = self.compact_locked(&mut wal);
+        }
+        Ok(epoch)
+    }
+
+    /// Write a snapshot of the current ring and truncate the log.
+    ///
+    /// # Errors
+    /// `Error::Storage` on I/O failure (the previous snapshot and log stay
+    /// valid, so recovery is unaffected)
+    pub fn compact(&self) -> Result<()> {
+        let mut wal = self.wal.lock();
+        self.compact_locked(&mut wal)
+    }
+
+    fn compact_locked(&self, wal: &mut Wal) -> Result<()> {
+        let payload = self.ring.snapshot().to_bincode()?;
+
+        let tmp = self.dir.join(SNAPSHOT_TMP_FILE);
+        let mut file = File::create(&tmp).map_err(|e| storage_error("creating", &tmp, e))?;
+        file.write_all(SNAPSHOT_MAGIC)
+            .and_then(|_| file.write_all(&crc32c::crc32c(&payload).to_le_bytes()))
+            .and_then(|_| file.write_all(&payload))
+            .and_then(|_| file.sync_all())
+            .map_err(|e| storage_error("writing", &tmp, e))?;
+
+        let snapshot = self.dir.join(SNAPSHOT_FILE);
+        fs::rename(&tmp, &snapshot).map_err(|e| storage_error("renaming", &tmp, e))?;
+        // Persist the rename itself before dropping the log
+        File::open(&self.dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(|e| storage_error("syncing", &self.dir, e))?;
+
+        let wal_path = self.dir.join(WAL_FILE);
+        wal.file
+            .set_len(0)
+            .and_then(|_| wal.file.sync_all())
+            .map_err(|e| storage_error("truncating", &wal_path, e))?;
+        wal.records = 0;
+        wal.len = 0;
+        Ok(())
+    }
+}
+
+/// Read and verify the snapshot file, if present.
+fn read_snapshot(path: &Path) -> Result<Option<RingState>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(storage_error("reading", path, e)),
+    };
+
+    let corrupt =
+        |reason: &str| Error::Storage(format!("corrupt snapshot {}: {}", path.display(), reason));
+    if bytes.len() < 8 || &bytes[..4] != SNAPSHOT_MAGIC {
+        return Err(corrupt("bad header"));
+    }
+    let checksum = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    let payload = &bytes[8..];
+    if crc32c::crc32c(payload) != checksum {
+        return Err(corrupt("checksum mismatch"));
+    }
+    RingState::from_bincode(payload).map(Some)
+}
+
+/// Decode log records up to the first torn or corrupt one.
+///
+/// # Returns
+/// The valid records and the byte length they occupy
+fn decode_records(bytes: &[u8]) -> (Vec<WalRecord>, usize) {
+    let mut records = Vec::new();
+    let mut offset = 0;
+
+    while bytes.len() - offset >= RECORD_HEADER_LEN {
+        let header = &bytes[offset..offset + RECORD_HEADER_LEN];
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+
+        let start = offset + RECORD_HEADER_LEN;
+        if len > MAX_RECORD_LEN || bytes.len() - start < len {
+            break;
+        }
+        let payload = &bytes[start..start + len];
+        if crc32c::crc32c(payload) != checksum {
+            break;
+        }
+        match bincode::deserialize::<WalRecord>(payload) {
+            Ok(record) => records.push(record),
+            Err(_) => break,
+        }
+        offset = start + len;
+    }
+    (records, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{Node, NodeId};
+    use crate::partitioner::Murmur3Partitioner;
+
+    fn open(dir: &Path) -> RingStore {
+        RingStore::open(dir, Arc::new(Murmur3Partitioner)).unwrap()
+    }
+
+    fn add(store: &RingStore, id: u128) -> u64 {
+        store
+            .apply(RingChange::add_node(
+                Node::new(NodeId(id), format!("node{}", id)),
+                8,
+            ))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_replay_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(dir.path());
+        add(&store, 1);
+        add(&store, 2);
+        store.apply(RingChange::remove_node(NodeId(1))).unwrap();
+        // Rejected and no-op changes are not logged
+        assert!(store.apply(RingChange::remove_node(NodeId(9))).is_err());
+        store.apply(RingChange::batch([])).unwrap();
+        let (tokens, epoch) = (store.ring().tokens(), store.ring().epoch());
+        drop(store);
+
+        let store = open(dir.path());
+        assert_eq!(store.ring().tokens(), tokens);
+        assert_eq!(store.ring().epoch(), epoch);
+        assert_eq!(store.recovery().replayed, 3);
+        assert_eq!(store.wal_len(), 3);
+    }
+
+    #[test]
+    fn test_compaction_and_stale_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(dir.path()).with_snapshot_every(2);
+        add(&store, 1);
+        add(&store, 2);
+        assert_eq!(store.wal_len(), 0, "compacted after 2 records");
+        add(&store, 3);
+        let wal = fs::read(dir.path().join(WAL_FILE)).unwrap();
+        let tokens = store.ring().tokens();
+        store.compact().unwrap();
+        drop(store);
+
+        // Simulate a crash between snapshot rename and log truncation
+        fs::write(dir.path().join(WAL_FILE), wal).unwrap();
+        let store = open(dir.path());
+        assert_eq!(store.ring().tokens(), tokens);
+        assert_eq!(store.recovery().snapshot_epoch, Some(3));
+        assert_eq!(store.recovery().skipped, 1);
+    }
+
+    #[test]
+    fn test_torn_tail_is_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(dir.path());
+        add(&store, 1);
+        add(&store, 2);
+        drop(store);
+
+        // Half-written third record
+        let wal_path = dir.path().join(WAL_FILE);
+        let mut file = OpenOptions::new().append(true).open(&wal_path).unwrap();
+        file.write_all(&[200, 0, 0, 0, 1, 2, 3, 4, 5]).unwrap();
+        drop(file);
+
+        let store = open(dir.path());
+        assert_eq!(store.ring().node_count(), 2);
+        assert_eq!(store.recovery().truncated_bytes, 9);
+        // The store keeps appending cleanly after truncation
+        add(&store, 3);
+        drop(store);
+        assert_eq!(open(dir.path()).ring().node_count(), 3);
+    }
+
+    #[test]
+    fn test_failed_append_leaves_ring_and_log_intact() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(dir.path());
+        add(&store, 1);
+        let tokens = store.ring().tokens();
+
+        // Half the record reaches the file before the write fails
+        store.wal.lock().fail_after = Some(10);
+        let result = store.apply(RingChange::add_node(Node::new(NodeId(2), "node2"), 8));
+        assert!(matches!(result, Err(Error::Storage(_))));
+        assert_eq!(store.ring().tokens(), tokens);
+        assert_eq!(store.ring().epoch(), 1);
+
+        // The torn bytes were cut off, so later records are not lost
+        add(&store, 3);
+        drop(store);
+        let store = open(dir.path());
+        assert_eq!(store.recovery().truncated_bytes, 0);
+        assert_eq!(store.recovery().replayed, 2);
+        assert!(store.ring().get_node(&NodeId(2)).is_none());
+        assert!(store.ring().get_node(&NodeId(3)).is_some());
+    }
+
+    #[test]
+    fn test_unrecoverable_append_poisons_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(dir.path());
+        add(&store, 1);
+
+        // A read-only handle: neither the write nor the truncation works
+        store.wal.lock().file = File::open(dir.path().join(WAL_FILE)).unwrap();
+        for id in [2, 3] {
+            let result = store.apply(RingChange::add_node(
+                Node::new(NodeId(id), format!("node{}", id)),
+                8,
+            ));
+            assert!(matches!(result, Err(Error::Storage(_))));
+        }
+        assert!(store.wal.lock().poisoned);
+        assert_eq!(store.ring().node_count(), 1);
+        drop(store);
+
+        let store = open(dir.path());
+        assert_eq!(store.ring().node_count(), 1);
+        add(&store, 2);
+    }
+
+    #[test]
+    fn test_corrupt_snapshot_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = open(dir.path());
+        add(&store, 1);
+        store.compact().unwrap();
+        drop(store);
+
+        let path = dir.path().join(SNAPSHOT_FILE);
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        let result = RingStore::open(dir.path(), Arc::new(Murmur3Partitioner));
+        assert!(matches!(result, Err(Error::Storage(_))));
+    }
+}
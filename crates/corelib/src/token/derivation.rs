@@ -0,0 +1,156 @@
+//! Stable derivation of vnode tokens from `(NodeId, index)`.
+//!
+//! # Why a Versioned Scheme?
+//!
+//! Vnode positions must never change for a given node and index, or every
+//! key on a production ring moves at once. Deriving tokens by hashing a
+//! formatted string (`"node_id:index"`) ties positions to `NodeId`'s
+//! `Display` format and to whatever the hash of that string happens to be.
+//!
+//! A `TokenDerivation` pins the exact byte layout and hash, and carries a
+//! `version()` so persisted rings can record which scheme produced their
+//! tokens. A new scheme gets a new version; existing versions never change.
+//!
+//! # Versions
+//!
+//! | Version | Type | Input |
+//! |---------|------|-------|
+//! | 0 | `LegacyDisplayDerivation` | `format!("{}:{}", node_id, index)` (pre-versioning rings) |
+//! | 1 | `SipDerivationV1` | tag ‖ node_id (LE u128) ‖ index (LE u64), SipHash-1-3 zero key |
+//!
+//! Version 1 is the default (`DefaultTokenDerivation`).
+
+use crate::node::NodeId;
+use crate::token::murmur3::Murmur3Token;
+use siphasher::sip::SipHasher13;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+/// Derives the ring position of a node's `index`-th vnode.
+///
+/// Implementations must be pure: the same `(node_id, index)` always yields
+/// the same token, on every platform and in every release.
+pub trait TokenDerivation: Send + Sync + 'static {
+    /// Scheme version, recorded alongside persisted rings.
+    fn version(&self) -> u32;
+
+    /// Human-readable scheme name.
+    fn name(&self) -> &'static str;
+
+    /// Token of the vnode at `index` for `node_id`.
+    fn derive(&self, node_id: NodeId, index: usize) -> Murmur3Token;
+}
+
+/// Version 0: hash of the formatted string `"node_id:index"`.
+///
+/// Reproduces the tokens of rings built before derivation was versioned.
+/// Allocates per token; only use it to keep such rings stable.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LegacyDisplayDerivation;
+
+impl TokenDerivation for LegacyDisplayDerivation {
+    fn version(&self) -> u32 {
+        0
+    }
+
+    fn name(&self) -> &'static str {
+        "legacy-display"
+    }
+
+    fn derive(&self, node_id: NodeId, index: usize) -> Murmur3Token {
+        // Frozen: NodeId displayed as 32 lowercase hex digits
+        Murmur3Token::from_key(&format!("{:032x}:{}", node_id.0, index))
+    }
+}
+
+/// Version 1: SipHash-1-3 (zero key) over fixed little-endian fields.
+///
+/// # Algorithm
+///
+/// ```text
+/// token = SipHash13_{k=0}( "vnode-v1" ‖ node_id as u128 LE ‖ index as u64 LE )
+/// ```
+///
+/// # Performance
+/// - **Time**: O(1) - 40 bytes hashed, no allocation or formatting
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SipDerivationV1;
+
+impl SipDerivationV1 {
+    /// Domain-separation tag (distinct from key hashing).
+    const TAG: &'static [u8; 8] = b"vnode-v1";
+}
+
+impl TokenDerivation for SipDerivationV1 {
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "sip13-v1"
+    }
+
+    fn derive(&self, node_id: NodeId, index: usize) -> Murmur3Token {
+        let mut hasher = SipHasher13::new_with_keys(0, 0);
+        hasher.write(Self::TAG);
+        hasher.write(&node_id.0.to_le_bytes());
+        hasher.write(&(index as u64).to_le_bytes());
+        Murmur3Token(hasher.finish())
+    }
+}
+
+/// The scheme used by `HashRing` and `VirtualNode::from_index()` by default.
+pub type DefaultTokenDerivation = SipDerivationV1;
+
+/// Look up a derivation scheme by version (e.g. when restoring a ring).
+///
+/// # Returns
+/// The scheme, or `None` if the version is unknown to this release
+///
+/// # Example
+/// ```rust
+/// # use corelib::token::derivation::derivation_for_version;
+/// assert_eq!(derivation_for_version(1).unwrap().name(), "sip13-v1");
+/// assert!(derivation_for_version(99).is_none());
+/// ```
+pub fn derivation_for_version(version: u32) -> Option<Arc<dyn TokenDerivation>> {
+    match version {
+        0 => Some(Arc::new(LegacyDisplayDerivation)),
+        1 => Some(Arc::new(SipDerivationV1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_v1_tokens_are_pinned() {
+        // Golden values: if these change, every ring in production reshuffles
+        let v1 = SipDerivationV1;
+        assert_eq!(v1.derive(NodeId(1), 0).0, 14_990_208_366_932_261_187);
+        assert_eq!(v1.derive(NodeId(1), 1).0, 2_459_945_965_818_515_871);
+        assert_eq!(
+            v1.derive(NodeId(u128::MAX), 255).0,
+            4_528_167_482_694_834_944
+        );
+    }
+
+    #[test]
+    fn test_legacy_matches_display_format() {
+        let node_id = NodeId(0xabc);
+        assert_eq!(
+            LegacyDisplayDerivation.derive(node_id, 7),
+            Murmur3Token::from_key(&format!("{}:{}", node_id, 7))
+        );
+    }
+
+    #[test]
+    fn test_versions_are_distinct() {
+        let v0 = derivation_for_version(0).unwrap();
+        let v1 = derivation_for_version(1).unwrap();
+        assert_eq!((v0.version(), v1.version()), (0, 1));
+        assert_ne!(v0.derive(NodeId(1), 0), v1.derive(NodeId(1), 0));
+    }
+}
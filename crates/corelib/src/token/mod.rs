@@ -4,9 +4,11 @@
 //! hashable, and thread-safe.
 
 pub mod byte_ordered;
+pub mod derivation;
 pub mod extended;
 pub mod murmur3;
 pub mod random;
 pub mod traits;
 
+pub use derivation::{DefaultTokenDerivation, TokenDerivation};
 pub use traits::{ByteComparableVersion, Token, TokenError};
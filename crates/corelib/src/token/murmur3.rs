@@ -1,11 +1,12 @@
 //! Murmur3 hash token implementation (Cassandra-compatible).
 
 use crate::token::traits::Token;
+use serde::{Deserialize, Serialize};
 use siphasher::sip::SipHasher13;
 use std::hash::{Hash, Hasher};
 
 /// Murmur3 token using u64 representation.
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct Murmur3Token(pub u64);
 
 impl Token for Murmur3Token {
@@ -13,7 +13,7 @@
 //! - **Operations**: Understand which keys map to which nodes
 //! - **Rebalancing**: Identify nodes that need rebalancing
 
-use crate::node::{Node, NodeId};
+use crate::node::NodeId;
 use crate::ring::HashRing;
 use crate::token::murmur3::Murmur3Token;
 use std::collections::HashMap;
@@ -71,6 +71,9 @@ impl Topology {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{NodeId, Topology};
+    /// # let ring = HashRing::new();
     /// let topology = Topology::new(ring);
     /// let ownership = topology.ownership();
     /// // ownership[NodeId(1)] = [Token(100), Token(200), ...]
@@ -80,7 +83,7 @@ impl Topology {
         let mut ownership: HashMap<NodeId, Vec<Murmur3Token>> = HashMap::new();
 
         for (token, node_id) in tokens {
-            ownership.entry(node_id).or_insert_with(Vec::new).push(token);
+            ownership.entry(node_id).or_default().push(token);
         }
 
         // Sort tokens for each node (useful for range queries)
@@ -107,6 +110,10 @@ impl Topology {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{NodeId, Topology};
+    /// # let ring = HashRing::new();
+    /// # let topology = Topology::new(ring);
     /// let percentages = topology.ownership_percentages();
     /// // percentages[NodeId(1)] = 33.33 (if node1 owns 1/3 of tokens)
     /// ```
@@ -131,7 +138,7 @@ impl Topology {
     ///
     /// # Format
     ///
-    /// ```
+    /// ```text
     /// Ring Description:
     ///   Nodes: 3
     ///   Total Tokens: 768
@@ -211,6 +218,10 @@ impl Topology {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::ring::HashRing;
+    /// # use corelib::{NodeId, Topology};
+    /// # let ring = HashRing::new();
+    /// # let topology = Topology::new(ring);
     /// let replicas = topology.replicas_for_key(b"my-key", 3);
     /// // Returns [NodeId(1), NodeId(2), NodeId(3)]
     /// ```
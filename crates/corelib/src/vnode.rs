@@ -25,6 +25,7 @@
 //! More vnodes = better distribution but more memory and slightly slower operations.
 
 use crate::node::NodeId;
+use crate::token::derivation::{DefaultTokenDerivation, TokenDerivation};
 use crate::token::murmur3::Murmur3Token;
 use crate::token::Token;
 
@@ -41,7 +42,7 @@ use crate::token::Token;
 ///
 /// # Memory Layout
 ///
-/// ```
+/// ```text
 /// VirtualNode {
 ///     token: Murmur3Token(u64),  // 8 bytes
 ///     node_id: NodeId(u128),     // 16 bytes
@@ -64,7 +65,7 @@ use crate::token::Token;
 pub struct VirtualNode {
     /// Token position on the ring.
     ///
-    /// This is the hash of a unique identifier like "node_id:vnode_index".
+    /// Derived from `(node_id, vnode_index)` by a `TokenDerivation`.
     /// The token determines where this vnode sits on the ring and which
     /// keys it's responsible for.
     pub token: Murmur3Token,
@@ -90,6 +91,8 @@ impl VirtualNode {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::{NodeId, VirtualNode};
+    /// # use corelib::token::murmur3::Murmur3Token;
     /// let vnode = VirtualNode::new(
     ///     Murmur3Token::from_key("node1:0"),
     ///     NodeId(1)
@@ -102,20 +105,13 @@ impl VirtualNode {
 
     /// Create a virtual node from a node ID and vnode index.
     ///
-    /// This is a convenience method that generates the token automatically
-    /// by hashing "node_id:vnode_index".
-    ///
-    /// # Algorithm
-    ///
-    /// 1. Format string: "node_id:vnode_index"
-    /// 2. Hash the string to get a token
-    /// 3. Create VirtualNode with token and node_id
+    /// The token comes from `DefaultTokenDerivation`, the same scheme
+    /// `HashRing` uses by default, so this vnode sits exactly where the ring
+    /// would place it.
     ///
     /// # Performance
-    /// - **Time**: O(k) where k = length of formatted string (~20-30 bytes)
-    ///   - Formatting: O(k)
-    ///   - Hashing: O(k) (Murmur3)
-    /// - **Space**: O(k) temporary for formatted string
+    /// - **Time**: O(1) - fixed-size hash input
+    /// - **Space**: O(1) - no allocation or string formatting
     ///
     /// # Arguments
     /// * `node_id` - The physical node ID
@@ -126,6 +122,7 @@ impl VirtualNode {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::{NodeId, VirtualNode};
     /// // Create vnode #0 for node 1
     /// let vnode0 = VirtualNode::from_index(NodeId(1), 0);
     ///
@@ -133,14 +130,21 @@ impl VirtualNode {
     /// let vnode1 = VirtualNode::from_index(NodeId(1), 1);
     /// ```
     pub fn from_index(node_id: NodeId, vnode_index: usize) -> Self {
-        // Generate unique key for this vnode
-        // Format: "node_id:vnode_index" ensures uniqueness
-        let vnode_key = format!("{}:{}", node_id, vnode_index);
-        
-        // Hash to get token position
-        let token = Murmur3Token::from_key(&vnode_key);
-        
-        Self::new(token, node_id)
+        Self::from_index_with(&DefaultTokenDerivation::default(), node_id, vnode_index)
+    }
+
+    /// Create a virtual node using a specific token derivation scheme.
+    ///
+    /// # Arguments
+    /// * `derivation` - Scheme mapping `(node_id, index)` to a token
+    /// * `node_id` - The physical node ID
+    /// * `vnode_index` - The index of this vnode
+    pub fn from_index_with(
+        derivation: &dyn TokenDerivation,
+        node_id: NodeId,
+        vnode_index: usize,
+    ) -> Self {
+        Self::new(derivation.derive(node_id, vnode_index), node_id)
     }
 
     /// Get the token position.
@@ -185,6 +189,7 @@ impl VirtualNode {
     ///
     /// # Example
     /// ```rust
+    /// # use corelib::{NodeId, VirtualNode};
     /// let vnode1 = VirtualNode::from_index(NodeId(1), 0);
     /// let vnode2 = VirtualNode::from_index(NodeId(2), 0);
     /// let distance = vnode1.distance_to(&vnode2);
@@ -9,7 +9,7 @@
 //! 5. **Thread safety**: Concurrent access (if we add those tests)
 
 use corelib::node::{Node, NodeId};
-use corelib::ring::HashRing;
+use corelib::ring::{HashRing, RingChange};
 
 // ============================================================================
 // Basic Functionality Tests
@@ -195,7 +195,7 @@ fn test_single_node() {
     ring.add_node(Node::new(NodeId(1), "node1"), 4);
     
     // All keys should map to the single node
-    for key in [b"key1", b"key2", b"key3", b"very-long-key-name"] {
+    for key in [&b"key1"[..], b"key2", b"key3", b"very-long-key-name"] {
         let node_id = ring.lookup(key);
         assert_eq!(node_id, Some(NodeId(1)), "All keys should map to single node");
     }
@@ -228,13 +228,380 @@ fn test_idempotent_add() {
     let node = Node::new(NodeId(1), "node1");
     ring.add_node(node.clone(), 4);
     assert_eq!(ring.token_count(), 4);
+    let tokens_before = ring.tokens();
     
-    // Add same node again (should add more vnodes, not replace)
+    // Add same node again (should reconcile, not append)
     ring.add_node(node, 4);
-    assert_eq!(ring.token_count(), 8); // Should have 8 vnodes now (4 + 4)
+    assert_eq!(ring.token_count(), 4); // Same 4 vnodes
+    assert_eq!(ring.tokens(), tokens_before);
     assert_eq!(ring.node_count(), 1); // Still one node
 }
 
+#[test]
+fn test_add_existing_node_reconciles_vnodes() {
+    // Re-adding with a different vnode count adds or removes the difference
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 8);
+    ring.add_node(Node::new(NodeId(2), "node2"), 4);
+    let full = ring.tokens();
+
+    // Shrink: stale tokens must be removed
+    ring.add_node(Node::new(NodeId(1), "node1"), 2);
+    assert_eq!(ring.token_count(), 6); // 2 + 4
+    let shrunk = ring.tokens();
+    assert!(shrunk.iter().all(|entry| full.contains(entry)));
+
+    // Grow back: the original positions are restored
+    ring.add_node(Node::new(NodeId(1), "node1"), 8);
+    assert_eq!(ring.tokens(), full);
+}
+
+#[test]
+fn test_update_node_metadata() {
+    // Updating metadata keeps tokens and bumps the epoch
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 4);
+    let tokens_before = ring.tokens();
+    let epoch_before = ring.epoch();
+
+    let updated = Node::with_topology(
+        NodeId(1),
+        "host-1.example",
+        Some("dc1".to_string()),
+        Some("rack2".to_string()),
+    );
+    assert!(ring.update_node(updated.clone()));
+
+    assert_eq!(ring.get_node(&NodeId(1)), Some(updated));
+    assert_eq!(ring.tokens(), tokens_before);
+    assert_eq!(ring.epoch(), epoch_before + 1);
+
+    // Unknown nodes are not inserted
+    assert!(!ring.update_node(Node::new(NodeId(2), "node2")));
+    assert_eq!(ring.node_count(), 1);
+}
+
+// ============================================================================
+// Batch Change Tests
+// ============================================================================
+
+#[test]
+fn test_apply_batch_single_epoch_bump() {
+    // A batch of several changes should bump the epoch exactly once
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 4);
+    let before = ring.epoch();
+
+    let epoch = ring
+        .apply(RingChange::batch([
+            RingChange::add_node(Node::new(NodeId(2), "node2"), 4),
+            RingChange::add_node(Node::new(NodeId(3), "node3"), 4),
+            RingChange::remove_node(NodeId(1)),
+        ]))
+        .expect("batch should apply");
+
+    assert_eq!(epoch, before + 1, "Batch should bump epoch once");
+    assert_eq!(ring.epoch(), epoch);
+    assert_eq!(ring.node_count(), 2);
+    assert_eq!(ring.token_count(), 8);
+    assert!(ring.get_node(&NodeId(1)).is_none());
+}
+
+#[test]
+fn test_apply_batch_rollback_on_failure() {
+    // A failing step must undo every earlier step in the batch
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 4);
+    let tokens_before = ring.tokens();
+    let epoch_before = ring.epoch();
+
+    let result = ring.apply(RingChange::batch([
+        RingChange::add_node(Node::new(NodeId(2), "node2"), 4),
+        RingChange::update_node(Node::new(NodeId(1), "renamed")),
+        RingChange::remove_node(NodeId(1)),
+        RingChange::remove_node(NodeId(999)), // Does not exist
+    ]));
+
+    assert!(result.is_err(), "Batch should fail validation");
+    assert_eq!(ring.tokens(), tokens_before, "Tokens should be restored");
+    assert_eq!(ring.epoch(), epoch_before, "Epoch should not change");
+    assert_eq!(ring.node_count(), 1);
+    assert_eq!(ring.get_node(&NodeId(1)).unwrap().name, "node1");
+}
+
+#[test]
+fn test_apply_move_token() {
+    // Moving a token keeps its owner and rejects occupied targets
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 2);
+    ring.add_node(Node::new(NodeId(2), "node2"), 2);
+
+    let tokens = ring.tokens();
+    let (from, owner) = tokens[0];
+    let (occupied, _) = tokens[1];
+
+    assert!(ring.apply(RingChange::move_token(from, occupied)).is_err());
+
+    let to = corelib::token::murmur3::Murmur3Token(from.0.wrapping_add(1));
+    ring.apply(RingChange::move_token(from, to)).expect("move should apply");
+
+    let moved = ring.tokens();
+    assert_eq!(moved.len(), 4);
+    assert!(moved.contains(&(to, owner)), "Token should keep its owner");
+    assert!(!moved.iter().any(|(t, _)| *t == from), "Old position should be free");
+}
+
+#[test]
+fn test_apply_update_node_metadata() {
+    // Updating metadata must not touch tokens
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 4);
+    let tokens_before = ring.tokens();
+
+    ring.apply(RingChange::update_node(Node::with_topology(
+        NodeId(1),
+        "node1",
+        Some("dc1".to_string()),
+        Some("rack1".to_string()),
+    )))
+    .expect("update should apply");
+
+    let node = ring.get_node(&NodeId(1)).unwrap();
+    assert_eq!(node.datacenter.as_deref(), Some("dc1"));
+    assert_eq!(node.rack.as_deref(), Some("rack1"));
+    assert_eq!(ring.tokens(), tokens_before);
+
+    // Updating an unknown node is rejected
+    assert!(ring
+        .apply(RingChange::update_node(Node::new(NodeId(2), "node2")))
+        .is_err());
+}
+
+// ============================================================================
+// Bounded-Load Tests
+// ============================================================================
+
+#[test]
+fn test_lookup_bounded_matches_lookup_when_idle() {
+    // With no load, bounded lookup must agree with plain lookup
+    let ring = HashRing::new();
+    for i in 1..=4 {
+        ring.add_node(Node::new(NodeId(i), format!("node{}", i)), 16);
+    }
+
+    for i in 0..100 {
+        let key = format!("key-{}", i);
+        assert_eq!(ring.lookup_bounded(key.as_bytes(), 0.25), ring.lookup(key.as_bytes()));
+    }
+    assert_eq!(HashRing::new().lookup_bounded(b"key", 0.25), None);
+}
+
+#[test]
+fn test_lookup_bounded_caps_load() {
+    // Hammering one key must spread load so no node exceeds the bound
+    let ring = HashRing::new();
+    for i in 1..=4 {
+        ring.add_node(Node::new(NodeId(i), format!("node{}", i)), 16);
+    }
+
+    let epsilon = 0.25;
+    let guards: Vec<_> = (0..100)
+        .map(|_| ring.lookup_bounded_acquire(b"hot-key", epsilon).unwrap())
+        .collect();
+
+    assert_eq!(ring.total_load(), 100);
+    let cap = ((1.0 + epsilon) * 100.0 / 4.0_f64).ceil() as usize;
+    for i in 1..=4 {
+        assert!(ring.load(&NodeId(i)) <= cap, "Node {} over capacity", i);
+    }
+
+    // The natural owner is filled first
+    let owner = ring.lookup(b"hot-key").unwrap();
+    assert_eq!(guards[0].node_id(), owner);
+
+    drop(guards);
+    assert_eq!(ring.total_load(), 0, "Guards should release on drop");
+    assert_eq!(ring.lookup_bounded(b"hot-key", epsilon), Some(owner));
+}
+
+#[test]
+fn test_acquire_load_guard() {
+    // Manually acquired load steers bounded lookups away from a full node
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 8);
+    ring.add_node(Node::new(NodeId(2), "node2"), 8);
+
+    let owner = ring.lookup(b"key").unwrap();
+    let other = if owner == NodeId(1) { NodeId(2) } else { NodeId(1) };
+
+    let guards: Vec<_> = (0..10).map(|_| ring.acquire_load(owner)).collect();
+    assert_eq!(ring.load(&owner), 10);
+    assert_eq!(ring.lookup_bounded(b"key", 0.1), Some(other));
+
+    drop(guards);
+    assert_eq!(ring.load(&owner), 0);
+}
+
+// ============================================================================
+// Snapshot Tests
+// ============================================================================
+
+#[test]
+fn test_snapshot_round_trip_preserves_placement() {
+    use corelib::partitioner::{Murmur3Partitioner, Xxh3Partitioner};
+    use corelib::ring::RingState;
+    use std::sync::Arc;
+
+    let ring = HashRing::new();
+    ring.add_node(Node::with_topology(NodeId(1), "node1", Some("dc1".into()), Some("r1".into())), 16);
+    ring.add_node(Node::new(NodeId(2), "node2"), 16);
+    // Moved tokens are not re-derivable; the snapshot must carry them as-is
+    let from = ring.tokens_of(&NodeId(2)).unwrap()[0];
+    let to = corelib::token::murmur3::Murmur3Token(from.0.wrapping_add(1));
+    ring.apply(RingChange::move_token(from, to)).unwrap();
+
+    let bytes = ring.snapshot().to_bincode().unwrap();
+    let restored =
+        HashRing::from_state(RingState::from_bincode(&bytes).unwrap(), Arc::new(Murmur3Partitioner))
+            .unwrap();
+
+    assert_eq!(restored.tokens(), ring.tokens());
+    assert_eq!(restored.epoch(), ring.epoch());
+    assert_eq!(restored.get_node(&NodeId(1)), ring.get_node(&NodeId(1)));
+    for i in 0..100 {
+        let key = format!("key-{}", i);
+        assert_eq!(restored.lookup(key.as_bytes()), ring.lookup(key.as_bytes()));
+    }
+
+    // Keys would land elsewhere under a different partitioner
+    assert!(HashRing::from_state(ring.snapshot(), Arc::new(Xxh3Partitioner)).is_err());
+}
+
+// ============================================================================
+// Batch Lookup Tests
+// ============================================================================
+
+#[test]
+fn test_lookup_many_matches_lookup() {
+    // Both the merge pass (many keys) and the search path (few keys)
+    // must agree with single-key lookups
+    let ring = HashRing::new();
+    for i in 1..=5 {
+        ring.add_node(Node::new(NodeId(i), format!("node{}", i)), 32);
+    }
+
+    let owned: Vec<Vec<u8>> = (0..1_000).map(|i| format!("key-{}", i).into_bytes()).collect();
+    let keys: Vec<&[u8]> = owned.iter().map(Vec::as_slice).collect();
+
+    for batch in [&keys[..], &keys[..3]] {
+        let owners = ring.lookup_many(batch);
+        assert_eq!(owners.len(), batch.len());
+        for (key, owner) in batch.iter().zip(&owners) {
+            assert_eq!(*owner, ring.lookup(key));
+        }
+    }
+
+    assert_eq!(HashRing::new().lookup_many(&keys[..2]), vec![None, None]);
+    assert!(ring.lookup_many(&[]).is_empty());
+}
+
+#[test]
+fn test_partition_keys_groups_by_owner() {
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 16);
+    ring.add_node(Node::new(NodeId(2), "node2"), 16);
+
+    let owned: Vec<Vec<u8>> = (0..200).map(|i| format!("key-{}", i).into_bytes()).collect();
+    let keys: Vec<&[u8]> = owned.iter().map(Vec::as_slice).collect();
+
+    let groups = ring.partition_keys(&keys);
+    assert_eq!(groups.values().map(Vec::len).sum::<usize>(), keys.len());
+    for (node_id, indices) in &groups {
+        assert!(indices.windows(2).all(|w| w[0] < w[1]), "indices sorted");
+        for &index in indices {
+            assert_eq!(ring.lookup(keys[index]), Some(*node_id));
+        }
+    }
+
+    assert!(HashRing::new().partition_keys(&keys).is_empty());
+}
+
+#[test]
+fn test_explain_matches_lookup_and_ranges() {
+    let ring = HashRing::new();
+    ring.add_node(
+        Node::with_topology(NodeId(1), "node1", Some("dc1".into()), None),
+        8,
+    );
+    ring.add_node(Node::new(NodeId(2), "node2"), 8);
+    let tokens: Vec<_> = ring.tokens().into_iter().map(|(token, _)| token).collect();
+
+    for i in 0..500 {
+        let key = format!("key-{}", i);
+        let why = ring.explain(key.as_bytes()).unwrap();
+        assert_eq!(Some(why.node.id), ring.lookup(key.as_bytes()));
+        assert_eq!(why.epoch, ring.epoch());
+
+        let position = tokens.binary_search(&why.vnode_token).unwrap();
+        let previous = tokens[(position + tokens.len() - 1) % tokens.len()];
+        assert_eq!(why.range_start, previous);
+        if why.wrapped {
+            assert_eq!(position, 0);
+            assert!(why.key_token > *tokens.last().unwrap());
+        } else {
+            assert!(why.key_token <= why.vnode_token);
+            assert!(position == 0 || why.key_token > why.range_start);
+        }
+    }
+
+    assert!(HashRing::new().explain(b"key").is_none());
+}
+
+#[test]
+fn test_explain_wraparound() {
+    // A lone vnode at token 0 owns the whole ring; every other key wraps
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 1);
+    let (from, _) = ring.tokens()[0];
+    let zero = corelib::token::murmur3::Murmur3Token(0);
+    ring.apply(RingChange::move_token(from, zero)).unwrap();
+
+    let why = ring.explain(b"key").unwrap();
+    assert!(why.wrapped);
+    assert_eq!((why.vnode_token, why.range_start), (zero, zero));
+    assert_eq!(why.node.name, "node1");
+    assert!(why.to_string().contains("wrapped around"));
+}
+
+// ============================================================================
+// Placement Trait Tests
+// ============================================================================
+
+#[test]
+fn test_placement_trait_objects() {
+    // HashRing and JumpHash are interchangeable behind the Placement trait
+    use corelib::placement::{JumpHash, Placement};
+
+    let nodes: Vec<Node> = (1..=3).map(|i| Node::new(NodeId(i), format!("node{}", i))).collect();
+
+    let ring = HashRing::new();
+    for node in &nodes {
+        ring.add_node(node.clone(), 16);
+    }
+    let jump = JumpHash::new(nodes.clone());
+
+    let placements: Vec<Box<dyn Placement>> = vec![Box::new(ring), Box::new(jump)];
+    for placement in &placements {
+        assert_eq!(placement.node_count(), 3, "{}", placement.name());
+        for i in 0..50 {
+            let key = format!("key-{}", i);
+            let node_id = placement.locate(key.as_bytes()).unwrap();
+            assert!(nodes.iter().any(|n| n.id == node_id));
+            assert_eq!(placement.locate(key.as_bytes()), Some(node_id));
+        }
+    }
+}
+
 // ============================================================================
 // Utility Tests
 // ============================================================================
@@ -271,6 +638,71 @@ fn test_get_all_tokens() {
     }
 }
 
+#[test]
+fn test_vnode_tokens_match_virtual_node() {
+    // The ring and VirtualNode::from_index must share one derivation scheme
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(42), "node42"), 16);
+
+    let mut expected: Vec<_> = (0..16)
+        .map(|i| corelib::VirtualNode::from_index(NodeId(42), i).token)
+        .collect();
+    expected.sort();
+    assert_eq!(ring.tokens_of(&NodeId(42)), Some(expected));
+    assert_eq!(ring.token_derivation_version(), 1);
+}
+
+#[test]
+fn test_tokens_of() {
+    // The per-node index must match the ring after every kind of mutation
+    let ring = HashRing::new();
+    ring.add_node(Node::new(NodeId(1), "node1"), 8);
+    ring.add_node(Node::new(NodeId(2), "node2"), 8);
+
+    let assert_index_matches = |ring: &HashRing| {
+        for node in ring.nodes() {
+            let mut expected: Vec<_> = ring
+                .tokens()
+                .into_iter()
+                .filter(|(_, id)| *id == node.id)
+                .map(|(t, _)| t)
+                .collect();
+            expected.sort();
+            assert_eq!(ring.tokens_of(&node.id), Some(expected));
+        }
+    };
+    assert_index_matches(&ring);
+    assert_eq!(ring.tokens_of(&NodeId(1)).unwrap().len(), 8);
+
+    // Shrink via reconcile
+    ring.add_node(Node::new(NodeId(1), "node1"), 3);
+    assert_index_matches(&ring);
+    assert_eq!(ring.tokens_of(&NodeId(1)).unwrap().len(), 3);
+
+    // Move a token
+    let from = ring.tokens_of(&NodeId(2)).unwrap()[0];
+    let to = corelib::token::murmur3::Murmur3Token(from.0.wrapping_add(1));
+    ring.apply(RingChange::move_token(from, to)).unwrap();
+    assert_index_matches(&ring);
+
+    // Failed batch rolls the index back too
+    let before = ring.tokens_of(&NodeId(2));
+    assert!(ring
+        .apply(RingChange::batch([
+            RingChange::remove_node(NodeId(2)),
+            RingChange::remove_node(NodeId(999)),
+        ]))
+        .is_err());
+    assert_eq!(ring.tokens_of(&NodeId(2)), before);
+    assert_index_matches(&ring);
+
+    // Removal clears the index entry
+    assert!(ring.remove_node(&NodeId(1)));
+    assert_eq!(ring.tokens_of(&NodeId(1)), None);
+    assert_eq!(ring.token_count(), 8);
+    assert_index_matches(&ring);
+}
+
 #[test]
 fn test_partitioner_name() {
     // Test getting partitioner name
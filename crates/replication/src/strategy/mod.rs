@@ -13,6 +13,8 @@ pub mod simple;
 pub use network_topology::NetworkTopologyStrategy;
 pub use simple::SimpleStrategy;
 
+use corelib::placement::ReplicaSelection;
+
 /// Trait for replication strategies.
 ///
 /// A replication strategy determines:
@@ -34,7 +36,8 @@ pub trait ReplicationStrategy: Send + Sync + 'static {
     /// Find replica nodes for a given key.
     ///
     /// # Arguments
-    /// * `ring` - The hash ring to query
+    /// * `ring` - The placement to query: a `HashRing`, `RendezvousRing`, or
+    ///   any other `ReplicaSelection` implementation
     /// * `key` - The key to find replicas for
     ///
     /// # Returns
@@ -42,7 +45,7 @@ pub trait ReplicationStrategy: Send + Sync + 'static {
     ///
     /// # Performance
     /// Should be O(r * log n) where r = replica count, n = tokens
-    fn replicas_for_key(&self, ring: &corelib::ring::HashRing, key: &[u8]) -> Vec<corelib::node::NodeId>;
+    fn replicas_for_key(&self, ring: &dyn ReplicaSelection, key: &[u8]) -> Vec<corelib::node::NodeId>;
 
     /// Get the strategy name (for logging/debugging).
     ///
@@ -13,11 +13,14 @@
 //! 2. Continue clockwise to find N-1 more unique nodes
 //! 3. Return list of node IDs (primary first)
 //!
+//! The walk is delegated to `ReplicaSelection::replicas()`, so the same
+//! strategy also runs on non-ring placements (e.g. `RendezvousRing`, where
+//! "clockwise" becomes "next-highest score").
+//!
 //! # Performance
 //!
-//! - **Time**: O(r * log n) where r = replica count, n = tokens
-//!   - Each node lookup is O(log n)
-//!   - We do r lookups
+//! - **Time**: O(log n + k) on a `HashRing` where k = tokens walked
+//!   - Single lookup, then a clockwise walk skipping seen nodes
 //! - **Space**: O(r) - returns Vec of node IDs
 //!
 //! # Limitations
@@ -28,7 +31,7 @@
 
 use crate::strategy::ReplicationStrategy;
 use corelib::node::NodeId;
-use corelib::ring::HashRing;
+use corelib::placement::ReplicaSelection;
 
 /// Simple replication strategy: N replicas placed sequentially around the ring.
 ///
@@ -91,61 +94,10 @@ impl ReplicationStrategy for SimpleStrategy {
         self.replication_factor
     }
 
-    fn replicas_for_key(&self, ring: &HashRing, key: &[u8]) -> Vec<NodeId> {
-        if self.replication_factor == 0 {
-            return Vec::new();
-        }
-
-        // Find the primary node (first replica)
-        let primary = match ring.lookup(key) {
-            Some(node_id) => node_id,
-            None => return Vec::new(), // Empty ring
-        };
-
-        // Get all tokens sorted by position
-        let mut tokens = ring.tokens();
-        if tokens.is_empty() {
-            return Vec::new();
-        }
-
-        // Sort tokens by position
-        tokens.sort_by_key(|(token, _)| *token);
-
-        // Find the starting position (first token that maps to primary node)
-        // We need to find where the primary node's tokens start
-        let start_idx = tokens
-            .iter()
-            .position(|(_, node_id)| *node_id == primary)
-            .unwrap_or(0);
-
-        // Collect replicas (wrapping around if needed)
-        let mut replicas = Vec::with_capacity(self.replication_factor);
-        let mut seen_nodes = std::collections::HashSet::new();
-
-        // Add primary first
-        replicas.push(primary);
-        seen_nodes.insert(primary);
-
-        // Continue clockwise to find more replicas
-        for i in 1..tokens.len() {
-            let idx = (start_idx + i) % tokens.len();
-            let (_, node_id) = tokens[idx];
-
-            // Skip if we've already seen this node
-            if seen_nodes.contains(&node_id) {
-                continue;
-            }
-
-            replicas.push(node_id);
-            seen_nodes.insert(node_id);
-
-            // Stop when we have enough replicas
-            if replicas.len() >= self.replication_factor {
-                break;
-            }
-        }
-
-        replicas
+    fn replicas_for_key(&self, ring: &dyn ReplicaSelection, key: &[u8]) -> Vec<NodeId> {
+        // The placement already walks distinct nodes in preference order
+        // (clockwise for HashRing, by score for RendezvousRing)
+        ring.replicas(key, self.replication_factor)
     }
 
     fn name(&self) -> &'static str {
@@ -157,6 +109,8 @@ impl ReplicationStrategy for SimpleStrategy {
 mod tests {
     use super::*;
     use corelib::node::Node;
+    use corelib::placement::RendezvousRing;
+    use corelib::ring::HashRing;
 
     #[test]
     fn test_simple_strategy_replication_factor() {
@@ -179,4 +133,31 @@ mod tests {
         let unique: std::collections::HashSet<_> = replicas.iter().collect();
         assert_eq!(unique.len(), 3);
     }
+
+    #[test]
+    fn test_simple_strategy_primary_first() {
+        let ring = HashRing::new();
+        for i in 1..=5 {
+            ring.add_node(Node::new(NodeId(i), format!("node{}", i)), 8);
+        }
+
+        let strategy = SimpleStrategy::new(3);
+        for i in 0..50 {
+            let key = format!("key-{}", i);
+            let replicas = strategy.replicas_for_key(&ring, key.as_bytes());
+            assert_eq!(replicas[0], ring.lookup(key.as_bytes()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_simple_strategy_on_rendezvous() {
+        let ring = RendezvousRing::new();
+        for i in 1..=5 {
+            ring.add_node(Node::new(NodeId(i), format!("node{}", i)));
+        }
+
+        let strategy = SimpleStrategy::new(3);
+        let replicas = strategy.replicas_for_key(&ring, b"object-1");
+        assert_eq!(replicas, ring.top_k(b"object-1", 3));
+    }
 }
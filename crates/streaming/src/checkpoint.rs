@@ -0,0 +1,310 @@
+//! Session checkpoints for resuming interrupted streams.
+//!
+//! A receiver records how far each session got: which ranges are complete
+//! and how many bytes of each file it has written. When the sender retries
+//! with the same `SessionId`, the receiver answers `StreamInit` with a
+//! `Resume` carrying that checkpoint, and the sender skips complete ranges
+//! and files and continues partial files from the recorded offset.
+//!
+//! ```text
+//! attempt 1:  range A [done]  range B [file 1 done, file 2 @ 40 MiB]  -- failure
+//! attempt 2:  range A skip    range B [file 1 skip, file 2 from 40 MiB] ...
+//! ```
+//!
+//! Checkpoints are kept in a `CheckpointStore`: `MemoryCheckpointStore`
+//! survives failed sessions, `FileCheckpointStore` also survives restarts
+//! of the receiving process.
+
+use crate::error::{Result, StreamingError};
+use crate::protocol::{SessionId, TokenRange};
+use corelib::node::NodeId;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// How far one file was received.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileCheckpoint {
+    /// File name within its range.
+    pub name: String,
+    /// Size announced by the sender.
+    pub size: u64,
+    /// Bytes written so far, from offset 0.
+    pub received: u64,
+}
+
+/// How far one range was received.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeCheckpoint {
+    /// The range.
+    pub range: TokenRange,
+    /// True once the sender moved past the range.
+    pub complete: bool,
+    /// Files of the range seen so far.
+    pub files: Vec<FileCheckpoint>,
+}
+
+/// Progress of one session, as recorded by its receiver.
+///
+/// # Example
+/// ```rust
+/// # use streaming::checkpoint::SessionCheckpoint;
+/// # use streaming::protocol::{SessionId, TokenRange};
+/// # use corelib::token::murmur3::Murmur3Token;
+/// # use corelib::NodeId;
+/// let range = TokenRange::new(Murmur3Token(0), Murmur3Token(100));
+/// let mut checkpoint = SessionCheckpoint::new(SessionId(1), NodeId(1));
+/// checkpoint.record_file(range, "data-1", 1000, 400);
+///
+/// assert_eq!(checkpoint.resume_offset(range, "data-1", 1000), Some(400));
+/// // A file whose size changed starts over
+/// assert_eq!(checkpoint.resume_offset(range, "data-1", 2000), None);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionCheckpoint {
+    /// Session the checkpoint belongs to.
+    pub session_id: SessionId,
+    /// Node sending the session.
+    pub peer: NodeId,
+    /// Ranges seen so far, in streaming order.
+    pub ranges: Vec<RangeCheckpoint>,
+}
+
+impl SessionCheckpoint {
+    /// An empty checkpoint: nothing received yet.
+    pub fn new(session_id: SessionId, peer: NodeId) -> Self {
+        Self {
+            session_id,
+            peer,
+            ranges: Vec::new(),
+        }
+    }
+
+    fn range(&self, range: TokenRange) -> Option<&RangeCheckpoint> {
+        self.ranges.iter().find(|r| r.range == range)
+    }
+
+    fn range_mut(&mut self, range: TokenRange) -> &mut RangeCheckpoint {
+        let index = match self.ranges.iter().position(|r| r.range == range) {
+            Some(index) => index,
+            None => {
+                self.ranges.push(RangeCheckpoint {
+                    range,
+                    complete: false,
+                    files: Vec::new(),
+                });
+                self.ranges.len() - 1
+            }
+        };
+        &mut self.ranges[index]
+    }
+
+    /// True if every file of `range` was received.
+    pub fn is_range_complete(&self, range: TokenRange) -> bool {
+        self.range(range).is_some_and(|r| r.complete)
+    }
+
+    /// Bytes of a file already received.
+    ///
+    /// # Returns
+    /// The offset to continue from, or `None` if the file was never seen
+    /// or its size no longer matches (it must be streamed from the start)
+    pub fn resume_offset(&self, range: TokenRange, name: &str, size: u64) -> Option<u64> {
+        self.range(range)?
+            .files
+            .iter()
+            .find(|f| f.name == name && f.size == size)
+            .map(|f| f.received)
+    }
+
+    /// Record that `received` bytes of a file have been written.
+    pub fn record_file(&mut self, range: TokenRange, name: &str, size: u64, received: u64) {
+        let files = &mut self.range_mut(range).files;
+        match files.iter_mut().find(|f| f.name == name) {
+            Some(file) => {
+                file.size = size;
+                file.received = received;
+            }
+            None => files.push(FileCheckpoint {
+                name: name.to_string(),
+                size,
+                received,
+            }),
+        }
+    }
+
+    /// Record that every file of `range` was received.
+    pub fn complete_range(&mut self, range: TokenRange) {
+        self.range_mut(range).complete = true;
+    }
+
+    /// Total bytes received across all files.
+    pub fn received_bytes(&self) -> u64 {
+        self.ranges
+            .iter()
+            .flat_map(|r| &r.files)
+            .map(|f| f.received)
+            .sum()
+    }
+}
+
+/// Where receivers keep session checkpoints.
+///
+/// Keyed by sending node and session, since session ids are only unique
+/// per pair of nodes.
+pub trait CheckpointStore: Send + Sync {
+    /// The checkpoint of a session, if one was saved.
+    fn load(&self, peer: NodeId, session_id: SessionId) -> Result<Option<SessionCheckpoint>>;
+
+    /// Save (replace) a checkpoint.
+    fn save(&self, checkpoint: &SessionCheckpoint) -> Result<()>;
+
+    /// Forget a session, once it completed.
+    fn remove(&self, peer: NodeId, session_id: SessionId) -> Result<()>;
+}
+
+/// Checkpoints held in memory.
+///
+/// Lets a receiver resume sessions that failed, but not across restarts.
+#[derive(Debug, Default)]
+pub struct MemoryCheckpointStore {
+    checkpoints: Mutex<HashMap<(NodeId, SessionId), SessionCheckpoint>>,
+}
+
+impl MemoryCheckpointStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of sessions with a checkpoint.
+    pub fn len(&self) -> usize {
+        self.checkpoints.lock().len()
+    }
+
+    /// True if no session has a checkpoint.
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.lock().is_empty()
+    }
+}
+
+impl CheckpointStore for MemoryCheckpointStore {
+    fn load(&self, peer: NodeId, session_id: SessionId) -> Result<Option<SessionCheckpoint>> {
+        Ok(self.checkpoints.lock().get(&(peer, session_id)).cloned())
+    }
+
+    fn save(&self, checkpoint: &SessionCheckpoint) -> Result<()> {
+        self.checkpoints
+            .lock()
+            .insert((checkpoint.peer, checkpoint.session_id), checkpoint.clone());
+        Ok(())
+    }
+
+    fn remove(&self, peer: NodeId, session_id: SessionId) -> Result<()> {
+        self.checkpoints.lock().remove(&(peer, session_id));
+        Ok(())
+    }
+}
+
+/// Checkpoints stored as JSON files in a directory, one per session.
+///
+/// Files are replaced atomically (temporary file, then rename).
+#[derive(Clone, Debug)]
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+}
+
+impl FileCheckpointStore {
+    /// Store checkpoints in `dir`, creating it if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, peer: NodeId, session_id: SessionId) -> PathBuf {
+        self.dir.join(format!("{}-{}.json", peer, session_id))
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self, peer: NodeId, session_id: SessionId) -> Result<Option<SessionCheckpoint>> {
+        let path = self.path(peer, session_id);
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| StreamingError::Storage(format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, checkpoint: &SessionCheckpoint) -> Result<()> {
+        let json = serde_json::to_vec_pretty(checkpoint)
+            .map_err(|e| StreamingError::Storage(e.to_string()))?;
+        let path = self.path(checkpoint.peer, checkpoint.session_id);
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn remove(&self, peer: NodeId, session_id: SessionId) -> Result<()> {
+        match fs::remove_file(self.path(peer, session_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use corelib::token::murmur3::Murmur3Token;
+
+    fn range(start: u64, end: u64) -> TokenRange {
+        TokenRange::new(Murmur3Token(start), Murmur3Token(end))
+    }
+
+    #[test]
+    fn test_checkpoint_tracking() {
+        let mut checkpoint = SessionCheckpoint::new(SessionId(1), NodeId(1));
+        checkpoint.record_file(range(0, 10), "a", 100, 100);
+        checkpoint.complete_range(range(0, 10));
+        checkpoint.record_file(range(10, 20), "b", 100, 30);
+        checkpoint.record_file(range(10, 20), "b", 100, 60);
+
+        assert!(checkpoint.is_range_complete(range(0, 10)));
+        assert!(!checkpoint.is_range_complete(range(10, 20)));
+        assert!(!checkpoint.is_range_complete(range(20, 30)));
+        assert_eq!(checkpoint.resume_offset(range(10, 20), "b", 100), Some(60));
+        assert_eq!(checkpoint.resume_offset(range(10, 20), "c", 100), None);
+        assert_eq!(checkpoint.received_bytes(), 160);
+    }
+
+    #[test]
+    fn test_file_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileCheckpointStore::new(dir.path().join("checkpoints")).unwrap();
+        assert_eq!(store.load(NodeId(1), SessionId(7)).unwrap(), None);
+
+        let mut checkpoint = SessionCheckpoint::new(SessionId(7), NodeId(1));
+        checkpoint.record_file(range(5, 1), "a", 10, 4);
+        store.save(&checkpoint).unwrap();
+        assert_eq!(
+            store.load(NodeId(1), SessionId(7)).unwrap(),
+            Some(checkpoint)
+        );
+        // Keyed by peer as well as session
+        assert_eq!(store.load(NodeId(2), SessionId(7)).unwrap(), None);
+
+        store.remove(NodeId(1), SessionId(7)).unwrap();
+        store.remove(NodeId(1), SessionId(7)).unwrap();
+        assert_eq!(store.load(NodeId(1), SessionId(7)).unwrap(), None);
+    }
+}
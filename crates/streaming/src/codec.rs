@@ -1,4 +1,673 @@
 //! Streaming codecs.
 //!
-//! This module will contain framing and serialization logic for streaming messages.
+//! `MessageCodec` frames `Message`s for byte-stream transports. It
+//! implements tokio-util's `Encoder` and `Decoder`, so it plugs into
+//! `Framed`, `FramedRead` and `FramedWrite`.
+//!
+//! # Frame Layout
+//!
+//! ```text
+//! +-------+---------+------+-------+--------+--------+-----------------+
+//! | magic | version | type | flags | length | crc32c | payload         |
+//! | u16   | u16     | u8   | u8    | u32    | u32    | `length` bytes  |
+//! +-------+---------+------+-------+--------+--------+-----------------+
+//! ```
+//!
+//! All integers are little-endian. The payload is the bincode encoding of
+//! the `Message`. The CRC32C covers the header fields before it and the
+//! payload, so a flipped bit anywhere in the frame is detected.
+//!
+//! # Compression
+//!
+//! `flags` holds the `Compression` code of the payload (v3+). A compressed
+//! payload is the length of the bincode encoding followed by its
+//! compressed form:
+//!
+//! ```text
+//! +------------+---------------------------------+
+//! | raw length | compressed bincode              |
+//! | u32        | `length - 4` bytes              |
+//! +------------+---------------------------------+
+//! ```
+//!
+//! The checksum covers the bytes on the wire, so corruption is caught
+//! before anything is decompressed. Each frame is compressed on its own
+//! and only when that makes it smaller; see `crate::compression`.
+//!
+//! # Failure Handling
+//!
+//! Ring state applied from a corrupted frame would silently misroute keys,
+//! so the decoder never guesses:
+//!
+//! - Bad magic, unknown type or flags, checksum mismatch, undecodable
+//!   payload, a payload whose type disagrees with the header, or a frame
+//!   compressed with an algorithm the session did not negotiate:
+//!   `StreamingError::Corrupt`
+//! - Length above the maximum frame size: `StreamingError::FrameTooLarge`,
+//!   reported from the header alone, before buffering the payload (and
+//!   likewise for the raw length, before decompressing)
+//! - Version outside `MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION`:
+//!   `StreamingError::UnsupportedVersion`, except for `Hello` and `Error`,
+//!   whose layouts never change: the handshake must be able to read a
+//!   newer peer's `Hello` to tell it the versions do not overlap
+//! - Stream ends inside a frame: `StreamingError::Corrupt` from `decode_eof`
+//!
+//! The stream cannot be resynchronised after an error; the session must be
+//! torn down.
+
+use crate::compression::{Compression, MIN_COMPRESS_LEN};
+use crate::error::{Result, StreamingError};
+use crate::protocol::{Message, MessageType, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Marks the start of every frame ("CS" little-endian).
+pub const FRAME_MAGIC: u16 = 0x5343;
+
+/// Size of the fixed frame header in bytes.
+pub const HEADER_LEN: usize = 14;
+
+/// Default maximum payload size (16 MiB).
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Header bytes covered by the checksum (everything before it).
+const CHECKSUMMED_HEADER_LEN: usize = HEADER_LEN - 4;
+
+/// Size of the raw length prefix of a compressed payload.
+const RAW_LEN_PREFIX: usize = 4;
+
+/// Frame codec for `Message`.
+///
+/// Frames are written with the session's protocol version. Until
+/// `set_version()` records the negotiated one that is
+/// `MIN_PROTOCOL_VERSION`, so that any peer sharing a version with this
+/// release can read the handshake. Frames of any supported version are
+/// accepted.
+///
+/// Payloads are compressed once `set_compression()` records the
+/// negotiated algorithm; from then on frames compressed with it are
+/// accepted as well as uncompressed ones. The maximum frame size bounds
+/// payloads both before and after compression.
+///
+/// # Example
+/// ```rust
+/// # use streaming::codec::MessageCodec;
+/// # use streaming::protocol::{Message, Payload, SessionId};
+/// # use bytes::BytesMut;
+/// # use tokio_util::codec::{Decoder, Encoder};
+/// let mut codec = MessageCodec::new();
+/// let mut buf = BytesMut::new();
+///
+/// let msg = Message::new(SessionId(1), 0, Payload::KeepAlive);
+/// codec.encode(msg.clone(), &mut buf).unwrap();
+/// assert_eq!(codec.decode(&mut buf).unwrap(), Some(msg));
+/// ```
+#[derive(Clone, Debug)]
+pub struct MessageCodec {
+    version: u16,
+    max_frame_size: usize,
+    compression: Compression,
+}
+
+impl MessageCodec {
+    /// Codec writing `MIN_PROTOCOL_VERSION` with the default maximum frame
+    /// size.
+    pub fn new() -> Self {
+        Self {
+            version: MIN_PROTOCOL_VERSION,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            compression: Compression::None,
+        }
+    }
+
+    /// Set the maximum payload size, for both directions.
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Protocol version written into outgoing frames.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Maximum payload size in bytes.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Record the version negotiated during the handshake.
+    ///
+    /// # Errors
+    /// `StreamingError::UnsupportedVersion` if this release cannot speak it
+    pub fn set_version(&mut self, version: u16) -> Result<()> {
+        check_version(version)?;
+        self.version = version;
+        Ok(())
+    }
+
+    /// Compression algorithm of the session.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Record the compression negotiated during the handshake.
+    ///
+    /// # Errors
+    /// `StreamingError::Protocol` if the session's version predates
+    /// compressed frames
+    pub fn set_compression(&mut self, compression: Compression) -> Result<()> {
+        let since = MessageType::CompressionOffer.since();
+        if compression != Compression::None && self.version < since {
+            return Err(StreamingError::Protocol(format!(
+                "{} compression needs protocol version {}, session speaks {}",
+                compression, since, self.version
+            )));
+        }
+        self.compression = compression;
+        Ok(())
+    }
+
+    /// Compress `payload` if the session compresses and it pays off.
+    ///
+    /// # Returns
+    /// The frame flags and the bytes to send
+    fn compress(&self, payload: Vec<u8>) -> (u8, Vec<u8>) {
+        if self.compression == Compression::None || payload.len() < MIN_COMPRESS_LEN {
+            return (Compression::None.code(), payload);
+        }
+        match self.compression.compress(&payload) {
+            Ok(compressed) if RAW_LEN_PREFIX + compressed.len() < payload.len() => {
+                let mut body = Vec::with_capacity(RAW_LEN_PREFIX + compressed.len());
+                body.put_u32_le(payload.len() as u32);
+                body.extend_from_slice(&compressed);
+                (self.compression.code(), body)
+            }
+            // Incompressible (or the compressor failed): send it as is
+            _ => (Compression::None.code(), payload),
+        }
+    }
+
+    /// Undo `compress()` for a received payload.
+    fn decompress(&self, compression: Compression, mut payload: &[u8]) -> Result<Vec<u8>> {
+        if payload.len() < RAW_LEN_PREFIX {
+            return Err(StreamingError::Corrupt(format!(
+                "{} payload of {} bytes has no length prefix",
+                compression,
+                payload.len()
+            )));
+        }
+        let len = payload.get_u32_le() as usize;
+        if len > self.max_frame_size {
+            return Err(StreamingError::FrameTooLarge {
+                size: len,
+                max: self.max_frame_size,
+            });
+        }
+        compression.decompress(payload, len)
+    }
+}
+
+impl Default for MessageCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn check_version(version: u16) -> Result<()> {
+    if (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+        Ok(())
+    } else {
+        Err(StreamingError::UnsupportedVersion {
+            version,
+            min: MIN_PROTOCOL_VERSION,
+            max: PROTOCOL_VERSION,
+        })
+    }
+}
+
+/// Parsed fixed-size frame header.
+struct FrameHeader {
+    message_type: MessageType,
+    compression: Compression,
+    length: usize,
+    checksum: u32,
+}
+
+impl FrameHeader {
+    /// Parse and validate a header (`bytes` is at least `HEADER_LEN` long).
+    ///
+    /// `compression` is the session's algorithm, the only one accepted
+    /// besides none.
+    fn parse(mut bytes: &[u8], max_frame_size: usize, compression: Compression) -> Result<Self> {
+        let magic = bytes.get_u16_le();
+        if magic != FRAME_MAGIC {
+            return Err(StreamingError::Corrupt(format!(
+                "bad frame magic {:#06x}",
+                magic
+            )));
+        }
+        let version = bytes.get_u16_le();
+
+        let code = bytes.get_u8();
+        let message_type = MessageType::from_code(code)
+            .ok_or_else(|| StreamingError::Corrupt(format!("unknown message type {}", code)))?;
+        if !matches!(message_type, MessageType::Hello | MessageType::Error) {
+            check_version(version)?;
+        }
+        let flags = bytes.get_u8();
+        let frame_compression = Compression::from_code(flags).ok_or_else(|| {
+            StreamingError::Corrupt(format!("unknown frame flags {:#04x}", flags))
+        })?;
+        if frame_compression != Compression::None && frame_compression != compression {
+            return Err(StreamingError::Corrupt(format!(
+                "frame compressed with {} but the session uses {}",
+                frame_compression, compression
+            )));
+        }
+
+        let length = bytes.get_u32_le() as usize;
+        if length > max_frame_size {
+            return Err(StreamingError::FrameTooLarge {
+                size: length,
+                max: max_frame_size,
+            });
+        }
+        Ok(Self {
+            message_type,
+            compression: frame_compression,
+            length,
+            checksum: bytes.get_u32_le(),
+        })
+    }
+}
+
+impl Encoder<Message> for MessageCodec {
+    type Error = StreamingError;
+
+    fn encode(&mut self, item: Message, dst: &mut BytesMut) -> Result<()> {
+        let payload =
+            bincode::serialize(&item).map_err(|e| StreamingError::Corrupt(e.to_string()))?;
+        if payload.len() > self.max_frame_size {
+            return Err(StreamingError::FrameTooLarge {
+                size: payload.len(),
+                max: self.max_frame_size,
+            });
+        }
+
+        let (flags, payload) = self.compress(payload);
+
+        dst.reserve(HEADER_LEN + payload.len());
+        let start = dst.len();
+        dst.put_u16_le(FRAME_MAGIC);
+        dst.put_u16_le(self.version);
+        dst.put_u8(item.message_type().code());
+        dst.put_u8(flags);
+        dst.put_u32_le(payload.len() as u32);
+
+        let checksum = crc32c::crc32c_append(crc32c::crc32c(&dst[start..]), &payload);
+        dst.put_u32_le(checksum);
+        dst.put_slice(&payload);
+        Ok(())
+    }
+}
+
+impl Decoder for MessageCodec {
+    type Item = Message;
+    type Error = StreamingError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Message>> {
+        if src.len() < HEADER_LEN {
+            src.reserve(HEADER_LEN - src.len());
+            return Ok(None);
+        }
+        let header = FrameHeader::parse(&src[..HEADER_LEN], self.max_frame_size, self.compression)?;
+
+        let frame_len = HEADER_LEN + header.length;
+        if src.len() < frame_len {
+            src.reserve(frame_len - src.len());
+            return Ok(None);
+        }
+        let frame = src.split_to(frame_len);
+        let payload = &frame[HEADER_LEN..];
+
+        let actual =
+            crc32c::crc32c_append(crc32c::crc32c(&frame[..CHECKSUMMED_HEADER_LEN]), payload);
+        if actual != header.checksum {
+            return Err(StreamingError::Corrupt(format!(
+                "checksum mismatch: expected {:#010x}, got {:#010x}",
+                header.checksum, actual
+            )));
+        }
+
+        let decompressed;
+        let payload = match header.compression {
+            Compression::None => payload,
+            compression => {
+                decompressed = self.decompress(compression, payload)?;
+                &decompressed[..]
+            }
+        };
+        let message: Message =
+            bincode::deserialize(payload).map_err(|e| StreamingError::Corrupt(e.to_string()))?;
+        if message.message_type() != header.message_type {
+            return Err(StreamingError::Corrupt(format!(
+                "header says {} but payload is {}",
+                header.message_type,
+                message.message_type()
+            )));
+        }
+        Ok(Some(message))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Message>> {
+        match self.decode(src)? {
+            Some(message) => Ok(Some(message)),
+            None if src.is_empty() => Ok(None),
+            None => Err(StreamingError::Corrupt(format!(
+                "stream ended inside a frame ({} bytes buffered)",
+                src.len()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::SessionCheckpoint;
+    use crate::protocol::{Payload, SessionId, StreamPurpose, TokenRange};
+    use corelib::token::murmur3::Murmur3Token;
+    use corelib::NodeId;
+
+    fn messages() -> Vec<Message> {
+        let range = TokenRange::new(Murmur3Token(1), Murmur3Token(2));
+        let payloads = vec![
+            Payload::Hello {
+                node_id: NodeId(7),
+                min_version: 1,
+                max_version: 1,
+            },
+            Payload::RingStateRequest {
+                known_epoch: Some(3),
+            },
+            Payload::RingStateResponse { state: None },
+            Payload::StreamInit {
+                purpose: StreamPurpose::Bootstrap,
+                ranges: vec![range],
+            },
+            Payload::FileHeader {
+                file_id: 1,
+                range,
+                name: "data-1".to_string(),
+                size: 5,
+            },
+            Payload::FileChunk {
+                file_id: 1,
+                offset: 0,
+                data: b"hello".to_vec(),
+            },
+            Payload::Ack { sequence: 5 },
+            Payload::Complete { files: 1, bytes: 5 },
+            Payload::Error {
+                retryable: false,
+                message: "boom".to_string(),
+            },
+            Payload::KeepAlive,
+            Payload::Resume {
+                checkpoint: SessionCheckpoint::new(SessionId(42), NodeId(7)),
+            },
+            Payload::CompressionOffer {
+                algorithms: vec![1, 2],
+            },
+        ];
+        payloads
+            .into_iter()
+            .enumerate()
+            .map(|(i, payload)| Message::new(SessionId(42), i as u64, payload))
+            .collect()
+    }
+
+    fn encode(message: Message) -> BytesMut {
+        let mut buf = BytesMut::new();
+        MessageCodec::new().encode(message, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_round_trip_every_type() {
+        let mut codec = MessageCodec::new();
+        let mut buf = BytesMut::new();
+        let messages = messages();
+        for message in &messages {
+            codec.encode(message.clone(), &mut buf).unwrap();
+        }
+        for message in &messages {
+            assert_eq!(codec.decode(&mut buf).unwrap().as_ref(), Some(message));
+        }
+        assert!(buf.is_empty());
+        assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn test_truncated_frame() {
+        let full = encode(messages().remove(5));
+        let mut codec = MessageCodec::new();
+
+        // Every prefix is incomplete, never an error or a message
+        for len in 0..full.len() {
+            let mut partial = BytesMut::from(&full[..len]);
+            assert!(codec.decode(&mut partial).unwrap().is_none());
+            if len > 0 {
+                assert!(matches!(
+                    codec.decode_eof(&mut partial),
+                    Err(StreamingError::Corrupt(_))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_oversized_frame() {
+        let message = messages().remove(5);
+        let mut small = MessageCodec::new().with_max_frame_size(8);
+        assert!(matches!(
+            small.encode(message.clone(), &mut BytesMut::new()),
+            Err(StreamingError::FrameTooLarge { max: 8, .. })
+        ));
+
+        // Rejected from the header alone, before the payload arrives
+        let mut header_only = BytesMut::from(&encode(message)[..HEADER_LEN]);
+        assert!(matches!(
+            small.decode(&mut header_only),
+            Err(StreamingError::FrameTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_corrupted_frames() {
+        let good = encode(messages().remove(5));
+
+        // Any single flipped byte after the magic is detected
+        for i in 2..good.len() {
+            let mut bad = good.clone();
+            bad[i] ^= 0x01;
+            let result = MessageCodec::new().decode(&mut bad);
+            assert!(
+                !matches!(result, Ok(Some(_))),
+                "flip at byte {} went unnoticed",
+                i
+            );
+        }
+
+        let mut bad_magic = good.clone();
+        bad_magic[0] ^= 0xff;
+        assert!(matches!(
+            MessageCodec::new().decode(&mut bad_magic),
+            Err(StreamingError::Corrupt(_))
+        ));
+
+        let mut bad_payload = good;
+        let last = bad_payload.len() - 1;
+        bad_payload[last] ^= 0xff;
+        assert!(matches!(
+            MessageCodec::new().decode(&mut bad_payload),
+            Err(StreamingError::Corrupt(_))
+        ));
+    }
+
+    fn chunk(data: Vec<u8>) -> Message {
+        Message::new(
+            SessionId(42),
+            3,
+            Payload::FileChunk {
+                file_id: 1,
+                offset: 0,
+                data,
+            },
+        )
+    }
+
+    fn compressing(compression: Compression) -> MessageCodec {
+        let mut codec = MessageCodec::new();
+        codec.set_version(PROTOCOL_VERSION).unwrap();
+        codec.set_compression(compression).unwrap();
+        codec
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let json = br#"{"key":"user:1","value":{"name":"alice","visits":3}}"#.repeat(1000);
+        for compression in [Compression::Lz4, Compression::Zstd] {
+            let mut codec = compressing(compression);
+            let mut buf = BytesMut::new();
+            codec.encode(chunk(json.clone()), &mut buf).unwrap();
+            assert_eq!(buf[5], compression.code());
+            assert!(
+                buf.len() < json.len() / 10,
+                "{}: {}",
+                compression,
+                buf.len()
+            );
+
+            // Any single flipped byte is still detected
+            for i in 2..buf.len() {
+                let mut bad = buf.clone();
+                bad[i] ^= 0x01;
+                assert!(!matches!(codec.decode(&mut bad), Ok(Some(_))), "byte {}", i);
+            }
+            assert_eq!(codec.decode(&mut buf).unwrap(), Some(chunk(json.clone())));
+        }
+    }
+
+    #[test]
+    fn test_compression_falls_back_to_none() {
+        let mut codec = compressing(Compression::Lz4);
+
+        // Too small to bother
+        let mut buf = BytesMut::new();
+        let keep_alive = Message::new(SessionId(42), 1, Payload::KeepAlive);
+        codec.encode(keep_alive.clone(), &mut buf).unwrap();
+        assert_eq!(buf[5], 0);
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(keep_alive));
+
+        // Incompressible
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let noise: Vec<u8> = (0..4096)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        codec.encode(chunk(noise.clone()), &mut buf).unwrap();
+        assert_eq!(buf[5], 0);
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(chunk(noise)));
+    }
+
+    #[test]
+    fn test_compression_must_be_negotiated() {
+        let mut buf = BytesMut::new();
+        compressing(Compression::Lz4)
+            .encode(chunk(vec![b'x'; 4096]), &mut buf)
+            .unwrap();
+        for compression in [Compression::None, Compression::Zstd] {
+            assert!(matches!(
+                compressing(compression).decode(&mut buf.clone()),
+                Err(StreamingError::Corrupt(_))
+            ));
+        }
+
+        let mut bad_flags = buf;
+        bad_flags[5] = 0x80;
+        assert!(matches!(
+            compressing(Compression::Lz4).decode(&mut bad_flags),
+            Err(StreamingError::Corrupt(_))
+        ));
+
+        // Sessions below version 3 cannot compress
+        let mut codec = MessageCodec::new();
+        codec.set_version(2).unwrap();
+        assert!(codec.set_compression(Compression::Lz4).is_err());
+        assert!(codec.set_compression(Compression::None).is_ok());
+    }
+
+    #[test]
+    fn test_decompressed_size_is_bounded() {
+        let mut buf = BytesMut::new();
+        compressing(Compression::Zstd)
+            .encode(chunk(vec![0; 64 * 1024]), &mut buf)
+            .unwrap();
+        assert!(buf.len() < 1024);
+
+        // Small on the wire, but expands beyond the limit
+        let mut small = MessageCodec::new().with_max_frame_size(1024);
+        small.set_version(PROTOCOL_VERSION).unwrap();
+        small.set_compression(Compression::Zstd).unwrap();
+        assert!(matches!(
+            small.decode(&mut buf),
+            Err(StreamingError::FrameTooLarge { max: 1024, .. })
+        ));
+    }
+
+    /// Rewrite a frame's header version, keeping its checksum valid.
+    fn set_frame_version(frame: &mut BytesMut, version: u16) {
+        frame[2..4].copy_from_slice(&version.to_le_bytes());
+        let checksum = crc32c::crc32c_append(
+            crc32c::crc32c(&frame[..CHECKSUMMED_HEADER_LEN]),
+            &frame[HEADER_LEN..],
+        );
+        frame[CHECKSUMMED_HEADER_LEN..HEADER_LEN].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn test_version_header() {
+        // Until negotiation, frames carry the oldest version we speak
+        let mut buf = encode(messages().remove(9));
+        assert_eq!(u16::from_le_bytes([buf[2], buf[3]]), MIN_PROTOCOL_VERSION);
+
+        set_frame_version(&mut buf, PROTOCOL_VERSION + 1);
+        assert!(matches!(
+            MessageCodec::new().decode(&mut buf),
+            Err(StreamingError::UnsupportedVersion { .. })
+        ));
+
+        let mut codec = MessageCodec::new();
+        assert!(codec.set_version(PROTOCOL_VERSION).is_ok());
+        let mut buf = BytesMut::new();
+        codec.encode(messages().remove(9), &mut buf).unwrap();
+        assert_eq!(u16::from_le_bytes([buf[2], buf[3]]), PROTOCOL_VERSION);
+        assert!(codec.set_version(0).is_err());
+    }
 
+    #[test]
+    fn test_handshake_frames_of_any_version_decode() {
+        // A newer peer's Hello and Error must be readable to reject it
+        for message in [messages().remove(0), messages().remove(8)] {
+            let mut buf = encode(message.clone());
+            set_frame_version(&mut buf, PROTOCOL_VERSION + 1);
+            assert_eq!(MessageCodec::new().decode(&mut buf).unwrap(), Some(message));
+        }
+    }
+}
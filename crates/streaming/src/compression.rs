@@ -0,0 +1,190 @@
+//! Frame compression for streaming sessions.
+//!
+//! Range data is mostly JSON and compresses well, while cross-DC links are
+//! the most expensive part of a transfer. From protocol version 3 the two
+//! peers agree on a `Compression` algorithm during the handshake, and the
+//! codec then compresses each frame's payload on its own:
+//!
+//! - **Chunk-level**: every `FileChunk` is a separate frame, so the
+//!   receiver decompresses and writes a large range incrementally, with
+//!   memory bounded by the maximum frame size
+//! - **Fallback**: frames that are small or do not shrink are sent as is;
+//!   a session where the peers share no algorithm (or either is older)
+//!   uses `Compression::None`
+//!
+//! # Negotiation
+//!
+//! Each side lists the algorithms it accepts in `CompressionOffer`. The
+//! initiator's list is in order of preference and the first entry the
+//! other side also lists wins, so both sides reach the same choice
+//! without another round trip.
+
+use crate::error::{Result, StreamingError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Algorithms offered by default, in order of preference.
+///
+/// LZ4 first: it costs little CPU on either side. Prefer `Zstd` where
+/// bandwidth is scarcer than CPU.
+pub const DEFAULT_COMPRESSION: [Compression; 2] = [Compression::Lz4, Compression::Zstd];
+
+/// Payloads shorter than this are never compressed: the saving would not
+/// pay for the work.
+pub const MIN_COMPRESS_LEN: usize = 256;
+
+/// zstd level used for outgoing frames (zstd's own default).
+const ZSTD_LEVEL: i32 = 3;
+
+/// A frame payload compression algorithm.
+///
+/// The discriminant is the wire code, carried in the frame header's flags
+/// byte and in `CompressionOffer`. Codes are never reused.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Compression {
+    /// Payloads are sent as is.
+    #[default]
+    None = 0,
+    /// LZ4 block format: fast, moderate ratio.
+    Lz4 = 1,
+    /// Zstandard: slower, better ratio.
+    Zstd = 2,
+}
+
+impl Compression {
+    /// All algorithms, in code order.
+    pub const ALL: [Compression; 3] = [Compression::None, Compression::Lz4, Compression::Zstd];
+
+    /// Wire code.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Decode a wire code.
+    ///
+    /// # Returns
+    /// The algorithm, or `None` for codes unknown to this release
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|c| c.code() == code)
+    }
+
+    /// Compress `data`.
+    ///
+    /// # Errors
+    /// `StreamingError::Corrupt` if the compressor fails
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => Ok(lz4_flex::block::compress(data)),
+            Compression::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL)
+                .map_err(|e| StreamingError::Corrupt(format!("zstd: {}", e))),
+        }
+    }
+
+    /// Decompress `data`, which must expand to exactly `len` bytes.
+    ///
+    /// Never allocates more than `len` bytes, so a hostile or corrupted
+    /// frame cannot claim unbounded memory once `len` has been checked.
+    ///
+    /// # Errors
+    /// `StreamingError::Corrupt` if `data` is not valid for the algorithm
+    /// or has a different decompressed length
+    pub fn decompress(self, data: &[u8], len: usize) -> Result<Vec<u8>> {
+        let decompressed = match self {
+            Compression::None => data.to_vec(),
+            Compression::Lz4 => lz4_flex::block::decompress(data, len)
+                .map_err(|e| StreamingError::Corrupt(format!("lz4: {}", e)))?,
+            Compression::Zstd => zstd::bulk::decompress(data, len)
+                .map_err(|e| StreamingError::Corrupt(format!("zstd: {}", e)))?,
+        };
+        if decompressed.len() != len {
+            return Err(StreamingError::Corrupt(format!(
+                "{} payload expanded to {} bytes, expected {}",
+                self,
+                decompressed.len(),
+                len
+            )));
+        }
+        Ok(decompressed)
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Lz4 => write!(f, "lz4"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// Pick the algorithm a session will use.
+///
+/// # Arguments
+/// * `initiator` - Algorithms offered by the session's initiator, in order
+///   of preference
+/// * `acceptor` - Algorithms the other side accepts
+///
+/// # Returns
+/// The initiator's most preferred algorithm the acceptor also accepts, or
+/// `Compression::None` if they share none
+///
+/// # Example
+/// ```rust
+/// # use streaming::compression::{negotiate_compression, Compression};
+/// let (lz4, zstd) = (Compression::Lz4, Compression::Zstd);
+/// assert_eq!(negotiate_compression(&[zstd, lz4], &[lz4, zstd]), zstd);
+/// assert_eq!(negotiate_compression(&[zstd, lz4], &[lz4]), lz4);
+/// assert_eq!(negotiate_compression(&[zstd], &[]), Compression::None);
+/// ```
+pub fn negotiate_compression(initiator: &[Compression], acceptor: &[Compression]) -> Compression {
+    initiator
+        .iter()
+        .copied()
+        .find(|c| *c != Compression::None && acceptor.contains(c))
+        .unwrap_or(Compression::None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let json = br#"{"key":"user:1","value":"alice"}"#.repeat(100);
+        for compression in Compression::ALL {
+            let compressed = compression.compress(&json).unwrap();
+            if compression != Compression::None {
+                assert!(compressed.len() < json.len() / 4, "{}", compression);
+            }
+            assert_eq!(
+                compression.decompress(&compressed, json.len()).unwrap(),
+                json
+            );
+            assert_eq!(
+                Compression::from_code(compression.code()),
+                Some(compression)
+            );
+        }
+        assert_eq!(Compression::from_code(3), None);
+    }
+
+    #[test]
+    fn test_decompress_rejects_wrong_length() {
+        let data = vec![7u8; 4096];
+        for compression in [Compression::Lz4, Compression::Zstd] {
+            let compressed = compression.compress(&data).unwrap();
+            assert!(matches!(
+                compression.decompress(&compressed, 100),
+                Err(StreamingError::Corrupt(_))
+            ));
+            assert!(matches!(
+                compression.decompress(&compressed, 8192),
+                Err(StreamingError::Corrupt(_))
+            ));
+            assert!(compression.decompress(b"garbage", 4096).is_err());
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Range data access for streaming.
+//!
+//! The data owned by a token range is a set of named files. A sender reads
+//! them through a `RangeSource`; a receiver writes them through a
+//! `RangeSink`. Storage engines implement both; `MemoryRangeStore` is an
+//! in-memory implementation for tests and tooling.
+
+use crate::error::{Result, StreamingError};
+use crate::protocol::TokenRange;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// A file of range data, as listed by a `RangeSource`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileInfo {
+    /// File name, unique within its range.
+    pub name: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// Read side of range data.
+#[async_trait]
+pub trait RangeSource: Send + Sync {
+    /// List the files holding `range`'s data, in streaming order.
+    async fn files(&self, range: TokenRange) -> Result<Vec<FileInfo>>;
+
+    /// Read up to `len` bytes of a file starting at `offset`.
+    ///
+    /// Must return at least one byte when `offset` is below the file size.
+    async fn read(&self, range: TokenRange, name: &str, offset: u64, len: usize)
+        -> Result<Vec<u8>>;
+}
+
+/// Write side of range data.
+#[async_trait]
+pub trait RangeSink: Send + Sync {
+    /// Write `data` into a file at `offset`, creating the file if needed.
+    async fn write(&self, range: TokenRange, name: &str, offset: u64, data: &[u8]) -> Result<()>;
+
+    /// Called once every byte of a file has been written.
+    async fn finish_file(&self, range: TokenRange, name: &str, size: u64) -> Result<()>;
+
+    /// Make every write so far durable.
+    ///
+    /// Called before a session checkpoint records the written offsets.
+    /// Sinks that write through need not override it.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: RangeSource + ?Sized> RangeSource for Arc<T> {
+    async fn files(&self, range: TokenRange) -> Result<Vec<FileInfo>> {
+        (**self).files(range).await
+    }
+
+    async fn read(
+        &self,
+        range: TokenRange,
+        name: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>> {
+        (**self).read(range, name, offset, len).await
+    }
+}
+
+#[async_trait]
+impl<T: RangeSink + ?Sized> RangeSink for Arc<T> {
+    async fn write(&self, range: TokenRange, name: &str, offset: u64, data: &[u8]) -> Result<()> {
+        (**self).write(range, name, offset, data).await
+    }
+
+    async fn finish_file(&self, range: TokenRange, name: &str, size: u64) -> Result<()> {
+        (**self).finish_file(range, name, size).await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        (**self).flush().await
+    }
+}
+
+/// Range data held in memory.
+///
+/// Serves as both source and sink. Files are listed in name order.
+///
+/// # Example
+/// ```rust
+/// # use streaming::data::MemoryRangeStore;
+/// # use streaming::protocol::TokenRange;
+/// # use corelib::token::murmur3::Murmur3Token;
+/// let range = TokenRange::new(Murmur3Token(0), Murmur3Token(100));
+/// let store = MemoryRangeStore::new();
+/// store.insert(range, "data-1", b"hello".to_vec());
+/// assert_eq!(store.file(range, "data-1").unwrap(), b"hello");
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryRangeStore {
+    ranges: Mutex<HashMap<TokenRange, BTreeMap<String, Vec<u8>>>>,
+}
+
+impl MemoryRangeStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a file.
+    pub fn insert(&self, range: TokenRange, name: impl Into<String>, data: Vec<u8>) {
+        self.ranges
+            .lock()
+            .entry(range)
+            .or_default()
+            .insert(name.into(), data);
+    }
+
+    /// Contents of a file.
+    pub fn file(&self, range: TokenRange, name: &str) -> Option<Vec<u8>> {
+        self.ranges.lock().get(&range)?.get(name).cloned()
+    }
+
+    /// All files of a range, by name.
+    pub fn files_of(&self, range: TokenRange) -> BTreeMap<String, Vec<u8>> {
+        self.ranges.lock().get(&range).cloned().unwrap_or_default()
+    }
+
+    /// Total bytes stored.
+    pub fn total_bytes(&self) -> u64 {
+        self.ranges
+            .lock()
+            .values()
+            .flat_map(BTreeMap::values)
+            .map(|data| data.len() as u64)
+            .sum()
+    }
+}
+
+#[async_trait]
+impl RangeSource for MemoryRangeStore {
+    async fn files(&self, range: TokenRange) -> Result<Vec<FileInfo>> {
+        Ok(self
+            .ranges
+            .lock()
+            .get(&range)
+            .map(|files| {
+                files
+                    .iter()
+                    .map(|(name, data)| FileInfo {
+                        name: name.clone(),
+                        size: data.len() as u64,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn read(
+        &self,
+        range: TokenRange,
+        name: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>> {
+        let ranges = self.ranges.lock();
+        let data = ranges
+            .get(&range)
+            .and_then(|files| files.get(name))
+            .ok_or_else(|| StreamingError::Storage(format!("no file {} in {}", name, range)))?;
+
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(len).min(data.len());
+        Ok(data[start..end].to_vec())
+    }
+}
+
+#[async_trait]
+impl RangeSink for MemoryRangeStore {
+    async fn write(&self, range: TokenRange, name: &str, offset: u64, data: &[u8]) -> Result<()> {
+        let mut ranges = self.ranges.lock();
+        let file = ranges
+            .entry(range)
+            .or_default()
+            .entry(name.to_string())
+            .or_default();
+
+        let start = offset as usize;
+        let end = start + data.len();
+        if file.len() < end {
+            file.resize(end, 0);
+        }
+        file[start..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    async fn finish_file(&self, range: TokenRange, name: &str, size: u64) -> Result<()> {
+        let mut ranges = self.ranges.lock();
+        // Empty files never see a write
+        let file = ranges
+            .entry(range)
+            .or_default()
+            .entry(name.to_string())
+            .or_default();
+        if file.len() as u64 != size {
+            return Err(StreamingError::Storage(format!(
+                "file {} in {} has {} bytes, expected {}",
+                name,
+                range,
+                file.len(),
+                size
+            )));
+        }
+        Ok(())
+    }
+}
@@ -1,2 +1,183 @@
 //! Streaming-specific error types.
+//!
+//! # Retrying
+//!
+//! Sessions resume from receiver checkpoints, so retrying one is cheap.
+//! `StreamingError::is_retryable()` tells callers whether it can help:
+//!
+//! - **Retryable**: transport failures, closed or reset connections,
+//!   timeouts, corrupted frames (a new connection starts clean), storage
+//!   failures such as a full disk, and rejections the peer marked retryable
+//! - **Not retryable**: version mismatches, protocol violations, oversized
+//!   frames, ring errors, cancellation, local I/O misuse (permissions,
+//!   invalid input), and rejections the peer marked final
 
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+/// Result type alias for the streaming crate.
+pub type Result<T> = std::result::Result<T, StreamingError>;
+
+/// Errors that can occur while streaming.
+#[derive(Debug)]
+pub enum StreamingError {
+    /// Underlying I/O failed
+    Io(io::Error),
+    /// The transport failed (connection setup, TLS, stream errors)
+    Transport(String),
+    /// A frame exceeds the configured maximum size
+    FrameTooLarge { size: usize, max: usize },
+    /// A frame failed its checksum or could not be decoded
+    Corrupt(String),
+    /// The peer speaks a protocol version outside our supported window
+    UnsupportedVersion { version: u16, min: u16, max: u16 },
+    /// The peer sent a message that is invalid at this point in the session
+    Protocol(String),
+    /// The peer aborted the session with an `Error` message
+    Rejected { retryable: bool, message: String },
+    /// The connection closed before the session finished
+    Closed,
+    /// Reading or writing range data failed
+    Storage(String),
+    /// A ring operation failed (invalid snapshot, membership change)
+    Ring(corelib::Error),
+    /// The peer sent nothing for this long
+    Timeout(Duration),
+    /// The session was cancelled locally
+    Cancelled,
+}
+
+impl StreamingError {
+    /// True if the same operation may succeed when retried.
+    ///
+    /// Failures of the connection or of the peer's storage are transient;
+    /// incompatible peers, protocol violations and cancellation are not.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use streaming::StreamingError;
+    /// assert!(StreamingError::Closed.is_retryable());
+    /// assert!(!StreamingError::Cancelled.is_retryable());
+    /// let overloaded = StreamingError::Rejected {
+    ///     retryable: true,
+    ///     message: "too many sessions".to_string(),
+    /// };
+    /// assert!(overloaded.is_retryable());
+    /// ```
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            StreamingError::Io(e) => !matches!(
+                e.kind(),
+                io::ErrorKind::PermissionDenied
+                    | io::ErrorKind::InvalidInput
+                    | io::ErrorKind::InvalidData
+                    | io::ErrorKind::Unsupported
+            ),
+            StreamingError::Transport(_)
+            | StreamingError::Corrupt(_)
+            | StreamingError::Closed
+            | StreamingError::Storage(_)
+            | StreamingError::Timeout(_) => true,
+            StreamingError::Rejected { retryable, .. } => *retryable,
+            StreamingError::FrameTooLarge { .. }
+            | StreamingError::UnsupportedVersion { .. }
+            | StreamingError::Protocol(_)
+            | StreamingError::Ring(_)
+            | StreamingError::Cancelled => false,
+        }
+    }
+}
+
+impl fmt::Display for StreamingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamingError::Io(e) => write!(f, "I/O error: {}", e),
+            StreamingError::Transport(msg) => write!(f, "Transport error: {}", msg),
+            StreamingError::FrameTooLarge { size, max } => {
+                write!(f, "Frame too large: {} bytes (max {})", size, max)
+            }
+            StreamingError::Corrupt(msg) => write!(f, "Corrupt frame: {}", msg),
+            StreamingError::UnsupportedVersion { version, min, max } => write!(
+                f,
+                "Unsupported protocol version {} (supported {}..={})",
+                version, min, max
+            ),
+            StreamingError::Protocol(msg) => write!(f, "Protocol violation: {}", msg),
+            StreamingError::Rejected { message, .. } => {
+                write!(f, "Rejected by peer: {}", message)
+            }
+            StreamingError::Closed => write!(f, "Connection closed mid-session"),
+            StreamingError::Storage(msg) => write!(f, "Storage error: {}", msg),
+            StreamingError::Ring(e) => write!(f, "Ring error: {}", e),
+            StreamingError::Timeout(after) => write!(f, "Timed out after {:?}", after),
+            StreamingError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for StreamingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamingError::Io(e) => Some(e),
+            StreamingError::Ring(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StreamingError {
+    fn from(e: io::Error) -> Self {
+        StreamingError::Io(e)
+    }
+}
+
+impl From<corelib::Error> for StreamingError {
+    fn from(e: corelib::Error) -> Self {
+        StreamingError::Ring(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_classification() {
+        let retryable = [
+            StreamingError::Io(io::ErrorKind::ConnectionReset.into()),
+            StreamingError::Transport("connection refused".to_string()),
+            StreamingError::Corrupt("checksum mismatch".to_string()),
+            StreamingError::Closed,
+            StreamingError::Storage("disk full".to_string()),
+            StreamingError::Timeout(Duration::from_secs(30)),
+            StreamingError::Rejected {
+                retryable: true,
+                message: "busy".to_string(),
+            },
+        ];
+        for e in &retryable {
+            assert!(e.is_retryable(), "{}", e);
+        }
+
+        let fatal = [
+            StreamingError::Io(io::ErrorKind::PermissionDenied.into()),
+            StreamingError::FrameTooLarge { size: 2, max: 1 },
+            StreamingError::UnsupportedVersion {
+                version: 9,
+                min: 1,
+                max: 3,
+            },
+            StreamingError::Protocol("unexpected Ack".to_string()),
+            StreamingError::Rejected {
+                retryable: false,
+                message: "unknown range".to_string(),
+            },
+            StreamingError::Ring(corelib::Error::InvalidNode("n".to_string())),
+            StreamingError::Cancelled,
+        ];
+        for e in &fatal {
+            assert!(!e.is_retryable(), "{}", e);
+        }
+    }
+}
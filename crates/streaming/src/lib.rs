@@ -4,15 +4,27 @@
 //! - Ring state between nodes
 //! - Data migration during rebalancing
 //! - Bootstrap operations
+//! - Planning which replica streams each range
+//! - Bandwidth limits for streams sharing links with foreground traffic
+//! - Negotiated compression of streamed data
 
+pub mod checkpoint;
 pub mod codec;
+pub mod compression;
+pub mod data;
 pub mod error;
+pub mod planner;
 pub mod protocol;
 pub mod receiver;
 pub mod sender;
 pub mod snapshot;
+pub mod throttle;
+pub mod transport;
 
-pub use error::StreamingError;
-pub use protocol::{Message, MessageType};
+pub use codec::MessageCodec;
+pub use compression::Compression;
+pub use error::{Result, StreamingError};
+pub use protocol::{Message, MessageType, Payload, SessionId, TokenRange};
 pub use receiver::StreamReceiver;
-pub use sender::StreamSender;
+pub use sender::{StreamSender, StreamSummary};
+pub use transport::Transport;
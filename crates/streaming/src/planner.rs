@@ -0,0 +1,653 @@
+//! Streaming plans.
+//!
+//! A ring change yields a set of `RangeTransfer`s: ranges a destination
+//! gains, each held by one or more source replicas. Which replica serves
+//! each range decides how long the change takes; pulling every range from
+//! the first replica saturates that node while the others sit idle.
+//!
+//! `StreamPlanner` picks sources so that the busiest node finishes as early
+//! as possible, given every node's bandwidth and stream concurrency. The
+//! same planner covers each operation that moves data:
+//!
+//! | Operation    | Transfers                                          |
+//! |--------------|----------------------------------------------------|
+//! | Bootstrap    | `range_transfers(ring, ring + node)`               |
+//! | Decommission | `range_transfers(ring, ring - node)`               |
+//! | Replace      | `range_transfers(ring, ring with dead → new)`, with the dead node excluded |
+//!
+//! # Example
+//!
+//! ```rust
+//! # use streaming::planner::{NodeLimits, StreamPlanner};
+//! # use streaming::protocol::TokenRange;
+//! # use streaming::snapshot::RangeTransfer;
+//! # use corelib::token::murmur3::Murmur3Token;
+//! # use corelib::NodeId;
+//! let transfers: Vec<RangeTransfer> = (0..4)
+//!     .map(|i| RangeTransfer {
+//!         range: TokenRange::new(Murmur3Token(i * 10), Murmur3Token(i * 10 + 10)),
+//!         destination: NodeId(4),
+//!         sources: vec![NodeId(1), NodeId(2)],
+//!     })
+//!     .collect();
+//!
+//! let plan = StreamPlanner::new(NodeLimits::default()).plan(&transfers, |_| 1 << 30);
+//!
+//! // Both replicas serve half of the ranges
+//! assert_eq!(plan.bytes_sent(NodeId(1)), 2 << 30);
+//! assert_eq!(plan.bytes_sent(NodeId(2)), 2 << 30);
+//! ```
+
+use crate::protocol::TokenRange;
+use crate::snapshot::RangeTransfer;
+use corelib::node::NodeId;
+use petgraph::algo::ford_fulkerson;
+use petgraph::graph::{EdgeIndex, Graph, NodeIndex};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+/// Default per-node bandwidth: 128 MiB/s in each direction.
+pub const DEFAULT_BANDWIDTH: u64 = 128 * 1024 * 1024;
+
+/// Default number of streams a node runs at once.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Default throughput of a single stream: 64 MiB/s.
+pub const DEFAULT_STREAM_BANDWIDTH: u64 = 64 * 1024 * 1024;
+
+/// Streaming capacity of one node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NodeLimits {
+    /// Bytes per second the node can send, and separately receive.
+    pub bandwidth: u64,
+    /// Streams the node runs at once.
+    pub concurrency: usize,
+}
+
+impl Default for NodeLimits {
+    fn default() -> Self {
+        Self {
+            bandwidth: DEFAULT_BANDWIDTH,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+}
+
+/// A range assigned to one source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assignment {
+    /// The range to stream.
+    pub range: TokenRange,
+    /// Node receiving it.
+    pub destination: NodeId,
+    /// Replica chosen to send it.
+    pub source: NodeId,
+    /// Size used for planning.
+    pub bytes: u64,
+}
+
+/// Output of `StreamPlanner::plan()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamPlan {
+    /// One entry per plannable transfer, in input order.
+    pub assignments: Vec<Assignment>,
+    /// Transfers with no usable source (or an unusable destination).
+    pub unassigned: Vec<RangeTransfer>,
+    /// Time until the busiest node finishes, at its configured rate.
+    pub estimated_duration: Duration,
+}
+
+impl StreamPlan {
+    /// Ranges per session, keyed by `(source, destination)`.
+    pub fn sessions(&self) -> BTreeMap<(NodeId, NodeId), Vec<TokenRange>> {
+        let mut sessions: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for a in &self.assignments {
+            sessions
+                .entry((a.source, a.destination))
+                .or_default()
+                .push(a.range);
+        }
+        sessions
+    }
+
+    /// Bytes `node` sends under this plan.
+    ///
+    /// Wider than `Assignment::bytes`: widths of ranges covering the whole
+    /// ring add up to 2^64.
+    pub fn bytes_sent(&self, node: NodeId) -> u128 {
+        self.assignments
+            .iter()
+            .filter(|a| a.source == node)
+            .map(|a| a.bytes as u128)
+            .sum()
+    }
+
+    /// Bytes `node` receives under this plan.
+    pub fn bytes_received(&self, node: NodeId) -> u128 {
+        self.assignments
+            .iter()
+            .filter(|a| a.destination == node)
+            .map(|a| a.bytes as u128)
+            .sum()
+    }
+}
+
+/// Transfers with the same destination and candidate sources.
+///
+/// Such ranges are interchangeable in the flow network, so each group is a
+/// single vertex no matter how many ranges it holds.
+struct Group {
+    destination: NodeId,
+    sources: Vec<NodeId>,
+    /// `(transfer index, bytes)`
+    ranges: Vec<(usize, u64)>,
+    /// Sum of the ranges' bytes (2^64 for a whole ring).
+    bytes: u128,
+}
+
+/// Flow network over groups; node capacities depend on the time budget.
+struct FlowNetwork {
+    graph: Graph<(), u128>,
+    source: NodeIndex,
+    sink: NodeIndex,
+    /// Edge into each destination, with its node.
+    inbound: Vec<(NodeId, EdgeIndex)>,
+    /// Edge out of each source replica, with its node.
+    outbound: Vec<(NodeId, EdgeIndex)>,
+    /// Per group, the edge to each of its candidate sources.
+    offers: Vec<Vec<EdgeIndex>>,
+}
+
+impl FlowNetwork {
+    fn new(groups: &[Group]) -> Self {
+        let mut graph = Graph::new();
+        let source = graph.add_node(());
+        let sink = graph.add_node(());
+        let mut destinations: HashMap<NodeId, NodeIndex> = HashMap::new();
+        let mut replicas: HashMap<NodeId, NodeIndex> = HashMap::new();
+        let (mut inbound, mut outbound) = (Vec::new(), Vec::new());
+        let mut offers = Vec::with_capacity(groups.len());
+
+        for group in groups {
+            let destination = *destinations.entry(group.destination).or_insert_with(|| {
+                let vertex = graph.add_node(());
+                inbound.push((group.destination, graph.add_edge(source, vertex, 0)));
+                vertex
+            });
+            let vertex = graph.add_node(());
+            graph.add_edge(destination, vertex, group.bytes);
+
+            let edges = group
+                .sources
+                .iter()
+                .map(|&node| {
+                    let replica = *replicas.entry(node).or_insert_with(|| {
+                        let vertex = graph.add_node(());
+                        outbound.push((node, graph.add_edge(vertex, sink, 0)));
+                        vertex
+                    });
+                    graph.add_edge(vertex, replica, group.bytes)
+                })
+                .collect();
+            offers.push(edges);
+        }
+
+        Self {
+            graph,
+            source,
+            sink,
+            inbound,
+            outbound,
+            offers,
+        }
+    }
+
+    /// Max flow when destinations may receive for `inbound_millis` and
+    /// replicas may send for `outbound_millis`.
+    fn max_flow(
+        &mut self,
+        planner: &StreamPlanner,
+        inbound_millis: u128,
+        outbound_millis: u128,
+    ) -> (u128, Vec<u128>) {
+        let budgets = [
+            (&self.inbound, inbound_millis),
+            (&self.outbound, outbound_millis),
+        ];
+        for (links, millis) in budgets {
+            for &(node, edge) in links {
+                // Saturating only ever over-provisions a link
+                self.graph[edge] = (planner.rate(node) as u128).saturating_mul(millis) / 1000;
+            }
+        }
+        ford_fulkerson(&self.graph, self.source, self.sink)
+    }
+}
+
+/// Smallest budget in `(0, hi]` for which `feasible` holds, and its flow.
+///
+/// # Returns
+/// The budget and flow, or `None` if not even `hi` is feasible
+fn search(
+    hi: u128,
+    mut feasible: impl FnMut(u128) -> Option<Vec<u128>>,
+) -> Option<(u128, Vec<u128>)> {
+    let (mut lo, mut hi) = (0, hi);
+    let mut flows = feasible(hi)?;
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        match feasible(mid) {
+            Some(edges) => {
+                hi = mid;
+                flows = edges;
+            }
+            None => lo = mid,
+        }
+    }
+    Some((hi, flows))
+}
+
+/// Assigns ranges to source replicas, balancing load across nodes.
+///
+/// A node runs at most `concurrency` streams of at most the planner's
+/// stream bandwidth each; its rate is the lower of that product and its
+/// `bandwidth`, applied separately to what it sends and what it receives.
+#[derive(Clone, Debug)]
+pub struct StreamPlanner {
+    default_limits: NodeLimits,
+    limits: HashMap<NodeId, NodeLimits>,
+    stream_bandwidth: u64,
+    excluded: HashSet<NodeId>,
+}
+
+impl Default for StreamPlanner {
+    fn default() -> Self {
+        Self::new(NodeLimits::default())
+    }
+}
+
+impl StreamPlanner {
+    /// Create a planner where every node has `default_limits`.
+    pub fn new(default_limits: NodeLimits) -> Self {
+        Self {
+            default_limits,
+            limits: HashMap::new(),
+            stream_bandwidth: DEFAULT_STREAM_BANDWIDTH,
+            excluded: HashSet::new(),
+        }
+    }
+
+    /// Override the limits of one node.
+    pub fn with_node_limits(mut self, node: NodeId, limits: NodeLimits) -> Self {
+        self.limits.insert(node, limits);
+        self
+    }
+
+    /// Set the throughput of a single stream, in bytes per second.
+    pub fn with_stream_bandwidth(mut self, bytes_per_sec: u64) -> Self {
+        self.stream_bandwidth = bytes_per_sec;
+        self
+    }
+
+    /// Never stream from or to these nodes (e.g. dead or failed ones).
+    pub fn with_excluded(mut self, nodes: impl IntoIterator<Item = NodeId>) -> Self {
+        self.excluded.extend(nodes);
+        self
+    }
+
+    /// Limits of `node`.
+    pub fn limits(&self, node: NodeId) -> NodeLimits {
+        self.limits
+            .get(&node)
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+
+    /// Effective rate of `node` in bytes per second (0 if excluded).
+    pub fn rate(&self, node: NodeId) -> u64 {
+        if self.excluded.contains(&node) {
+            return 0;
+        }
+        let limits = self.limits(node);
+        let streams = self
+            .stream_bandwidth
+            .saturating_mul(limits.concurrency as u64);
+        limits.bandwidth.min(streams)
+    }
+
+    /// Choose a source for every transfer.
+    ///
+    /// # Algorithm
+    ///
+    /// Transfers sharing a destination and candidate sources are merged into
+    /// groups. For a time budget `T`, node capacities become flow
+    /// capacities:
+    ///
+    /// ```text
+    ///         rate(d)·T            bytes(g)            bytes(g)           rate(s)·T
+    /// source ---------> dest d ---------> group g ---------> replica s ---------> sink
+    /// ```
+    ///
+    /// `T` is feasible when the maximum flow (Edmonds-Karp, via petgraph's
+    /// `ford_fulkerson`) carries every group's bytes. A binary search over
+    /// `T` in milliseconds finds the smallest feasible budget. A second
+    /// search keeps destinations at that budget and shrinks the replicas'
+    /// budget alone, so that when destinations are the bottleneck the load
+    /// is still spread evenly over replicas.
+    ///
+    /// The final flow on the group → replica edges says how many bytes of
+    /// each group every replica should send. Ranges cannot be split, so each
+    /// group's ranges are dealt largest first to the replica with the most
+    /// unfilled share.
+    ///
+    /// # Performance
+    /// - **Time**: O(log(T) · V · E²) worst case, for V and E the vertices
+    ///   and edges of the grouped network (groups plus nodes), plus
+    ///   O(r log r) for r transfers
+    /// - **Space**: O(V + E + r)
+    ///
+    /// # Arguments
+    /// * `transfers` - Ranges to move, e.g. from `range_transfers()`
+    /// * `size_of` - Bytes to stream for a transfer; any consistent
+    ///   estimate works, such as `TokenRange::width()`
+    ///
+    /// # Returns
+    /// The assignments, the transfers that cannot be served (no candidate
+    /// source other than excluded or zero-rate nodes and the destination
+    /// itself), and the estimated duration of the plan
+    pub fn plan(
+        &self,
+        transfers: &[RangeTransfer],
+        size_of: impl Fn(&RangeTransfer) -> u64,
+    ) -> StreamPlan {
+        let mut plan = StreamPlan::default();
+        let mut grouped: BTreeMap<(NodeId, Vec<NodeId>), Group> = BTreeMap::new();
+        for (i, transfer) in transfers.iter().enumerate() {
+            let mut sources: Vec<NodeId> = transfer
+                .sources
+                .iter()
+                .copied()
+                .filter(|&s| s != transfer.destination && self.rate(s) > 0)
+                .collect();
+            sources.sort_unstable();
+            sources.dedup();
+            if sources.is_empty() || self.rate(transfer.destination) == 0 {
+                plan.unassigned.push(transfer.clone());
+                continue;
+            }
+
+            let bytes = size_of(transfer);
+            let group = grouped
+                .entry((transfer.destination, sources.clone()))
+                .or_insert_with(|| Group {
+                    destination: transfer.destination,
+                    sources,
+                    ranges: Vec::new(),
+                    bytes: 0,
+                });
+            group.ranges.push((i, bytes));
+            group.bytes += bytes as u128;
+        }
+
+        let groups: Vec<Group> = grouped.into_values().collect();
+        let shares = self.balance(&groups);
+
+        // Deal ranges against each group's share of the flow
+        let mut load: HashMap<NodeId, u128> = HashMap::new();
+        let mut assignments = Vec::with_capacity(transfers.len());
+        for (group, mut share) in groups.iter().zip(shares) {
+            let mut ranges = group.ranges.clone();
+            ranges.sort_unstable_by_key(|&(i, bytes)| (Reverse(bytes), i));
+            for (i, bytes) in ranges {
+                let pick = (0..group.sources.len())
+                    .max_by_key(|&j| {
+                        let node = group.sources[j];
+                        (
+                            share[j],
+                            Reverse(load.get(&node).copied().unwrap_or(0)),
+                            Reverse(node),
+                        )
+                    })
+                    .expect("groups have sources");
+                share[pick] -= bytes as i128;
+                let source = group.sources[pick];
+                *load.entry(source).or_default() += bytes as u128;
+                assignments.push((
+                    i,
+                    Assignment {
+                        range: transfers[i].range,
+                        destination: group.destination,
+                        source,
+                        bytes,
+                    },
+                ));
+            }
+        }
+        assignments.sort_unstable_by_key(|(i, _)| *i);
+        plan.assignments = assignments.into_iter().map(|(_, a)| a).collect();
+        plan.estimated_duration = self.duration(&plan.assignments);
+        plan
+    }
+
+    /// Bytes each group's replicas should send, by the minimal-time flow.
+    fn balance(&self, groups: &[Group]) -> Vec<Vec<i128>> {
+        let total: u128 = groups.iter().map(|g| g.bytes).sum();
+        let mut network = FlowNetwork::new(groups);
+        let slowest = network
+            .inbound
+            .iter()
+            .chain(&network.outbound)
+            .map(|&(node, _)| self.rate(node))
+            .min()
+            .unwrap_or(1);
+
+        // Within this budget every node alone can carry all bytes
+        let hi = total.saturating_mul(1000).div_ceil(slowest as u128);
+        let flows = match search(hi, |millis| {
+            let (flow, edges) = network.max_flow(self, millis, millis);
+            (flow == total).then_some(edges)
+        }) {
+            // When destinations are the bottleneck many flows meet the
+            // deadline; take the one that finishes replicas earliest,
+            // which spreads load evenly across them
+            Some((deadline, flows)) => search(deadline, |millis| {
+                let (flow, edges) = network.max_flow(self, deadline, millis);
+                (flow == total).then_some(edges)
+            })
+            .map_or(flows, |(_, flows)| flows),
+            // Only if `total * 1000` overflows: deal against the largest
+            // flow there is, which still spreads what it carries
+            None => network.max_flow(self, hi, hi).1,
+        };
+
+        network
+            .offers
+            .iter()
+            .map(|edges| edges.iter().map(|e| flows[e.index()] as i128).collect())
+            .collect()
+    }
+
+    /// Time for the busiest node to send and receive its assigned bytes.
+    fn duration(&self, assignments: &[Assignment]) -> Duration {
+        let mut sent: HashMap<NodeId, u128> = HashMap::new();
+        let mut received: HashMap<NodeId, u128> = HashMap::new();
+        for a in assignments {
+            *sent.entry(a.source).or_default() += a.bytes as u128;
+            *received.entry(a.destination).or_default() += a.bytes as u128;
+        }
+
+        let millis = sent
+            .iter()
+            .chain(&received)
+            .map(|(&node, &bytes)| (bytes * 1000).div_ceil(self.rate(node) as u128))
+            .max()
+            .unwrap_or(0);
+        Duration::from_millis(u64::try_from(millis).unwrap_or(u64::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use corelib::token::murmur3::Murmur3Token;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn transfer(i: u64, destination: u128, sources: &[u128]) -> RangeTransfer {
+        RangeTransfer {
+            range: TokenRange::new(Murmur3Token(i * 10), Murmur3Token(i * 10 + 10)),
+            destination: NodeId(destination),
+            sources: sources.iter().map(|&s| NodeId(s)).collect(),
+        }
+    }
+
+    fn limits(bandwidth: u64) -> NodeLimits {
+        NodeLimits {
+            bandwidth,
+            concurrency: 4,
+        }
+    }
+
+    #[test]
+    fn test_spreads_ranges_across_replicas() {
+        // Every range is on all three replicas; naive selection would use
+        // the first one only
+        let transfers: Vec<_> = (0..12).map(|i| transfer(i, 4, &[1, 2, 3])).collect();
+        let planner = StreamPlanner::new(limits(100 * MIB)).with_stream_bandwidth(100 * MIB);
+        let plan = planner.plan(&transfers, |_| 100 * MIB);
+
+        assert_eq!(plan.assignments.len(), 12);
+        for node in 1..=3 {
+            assert_eq!(plan.bytes_sent(NodeId(node)), u128::from(400 * MIB));
+        }
+        assert_eq!(plan.bytes_received(NodeId(4)), u128::from(1200 * MIB));
+        // The destination's inbound link is the bottleneck: 1200 MiB at 100 MiB/s
+        assert_eq!(plan.estimated_duration, Duration::from_secs(12));
+        assert_eq!(plan.sessions().len(), 3);
+    }
+
+    #[test]
+    fn test_faster_source_serves_more() {
+        let transfers: Vec<_> = (0..8).map(|i| transfer(i, 3, &[1, 2])).collect();
+        let planner = StreamPlanner::new(limits(1000 * MIB))
+            .with_stream_bandwidth(1000 * MIB)
+            .with_node_limits(NodeId(1), limits(100 * MIB))
+            .with_node_limits(NodeId(2), limits(300 * MIB));
+        let plan = planner.plan(&transfers, |_| 100 * MIB);
+
+        // 400 MiB/s in total: node 2 sends three times as much as node 1
+        assert_eq!(plan.bytes_sent(NodeId(1)), u128::from(200 * MIB));
+        assert_eq!(plan.bytes_sent(NodeId(2)), u128::from(600 * MIB));
+        assert_eq!(plan.estimated_duration, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_concurrency_caps_rate() {
+        let planner = StreamPlanner::new(NodeLimits {
+            bandwidth: 100 * MIB,
+            concurrency: 1,
+        })
+        .with_stream_bandwidth(10 * MIB);
+        assert_eq!(planner.rate(NodeId(1)), 10 * MIB);
+    }
+
+    #[test]
+    fn test_sole_replica_load_is_balanced_around() {
+        // Range 0 is only on node 1, so node 1 must send it; the rest
+        // balance around it
+        let mut transfers = vec![transfer(0, 3, &[1])];
+        transfers.extend((1..5).map(|i| transfer(i, 4, &[1, 2])));
+        let planner = StreamPlanner::new(limits(100 * MIB))
+            .with_stream_bandwidth(100 * MIB)
+            .with_node_limits(NodeId(3), limits(1000 * MIB))
+            .with_node_limits(NodeId(4), limits(1000 * MIB));
+        let plan = planner.plan(&transfers, |_| MIB);
+
+        assert_eq!(plan.assignments[0].source, NodeId(1));
+        assert_eq!(
+            plan.bytes_sent(NodeId(1)) + plan.bytes_sent(NodeId(2)),
+            u128::from(5 * MIB)
+        );
+        assert_eq!(
+            plan.bytes_sent(NodeId(1)).max(plan.bytes_sent(NodeId(2))),
+            u128::from(3 * MIB)
+        );
+        assert_eq!(plan.bytes_received(NodeId(3)), u128::from(MIB));
+        assert_eq!(plan.bytes_received(NodeId(4)), u128::from(4 * MIB));
+    }
+
+    #[test]
+    fn test_excluded_sources() {
+        let transfers = vec![transfer(0, 3, &[1, 2]), transfer(1, 3, &[1])];
+        let plan = StreamPlanner::default()
+            .with_excluded([NodeId(1)])
+            .plan(&transfers, |t| t.range.width());
+
+        assert_eq!(plan.assignments.len(), 1);
+        assert_eq!(plan.assignments[0].source, NodeId(2));
+        assert_eq!(plan.unassigned, vec![transfers[1].clone()]);
+    }
+
+    #[test]
+    fn test_full_ring_join() {
+        // A node joining a one-node ring gains every range, whose widths
+        // add up to 2^64
+        let tokens = [0, 1 << 62, 1 << 63, 3 << 62];
+        let transfers: Vec<_> = (0..4)
+            .map(|i| RangeTransfer {
+                range: TokenRange::new(Murmur3Token(tokens[i]), Murmur3Token(tokens[(i + 1) % 4])),
+                destination: NodeId(2),
+                sources: vec![NodeId(1)],
+            })
+            .collect();
+        let plan = StreamPlanner::default().plan(&transfers, |t| t.range.width());
+
+        assert_eq!(plan.assignments.len(), 4);
+        assert_eq!(plan.bytes_sent(NodeId(1)), 1 << 64);
+        assert_eq!(plan.bytes_received(NodeId(2)), 1 << 64);
+        assert!(plan.estimated_duration > Duration::from_secs(1 << 30));
+
+        // The whole ring as a single range saturates at u64::MAX
+        let whole = RangeTransfer {
+            range: TokenRange::new(Murmur3Token(5), Murmur3Token(5)),
+            destination: NodeId(2),
+            sources: vec![NodeId(1), NodeId(3)],
+        };
+        let plan = StreamPlanner::default().plan(&[whole.clone(), whole], |t| t.range.width());
+        assert_eq!(
+            plan.bytes_sent(NodeId(1)) + plan.bytes_sent(NodeId(3)),
+            2 * u64::MAX as u128
+        );
+    }
+
+    #[test]
+    fn test_full_ring_join_at_tiny_bandwidth() {
+        let tokens = [0, 1 << 62, 1 << 63, 3 << 62];
+        let transfers: Vec<_> = (0..4)
+            .map(|i| RangeTransfer {
+                range: TokenRange::new(Murmur3Token(tokens[i]), Murmur3Token(tokens[(i + 1) % 4])),
+                destination: NodeId(3),
+                sources: vec![NodeId(1), NodeId(2)],
+            })
+            .collect();
+        // The budget needed exceeds u64::MAX milliseconds
+        let planner = StreamPlanner::new(limits(1_000)).with_stream_bandwidth(1);
+        let plan = planner.plan(&transfers, |t| t.range.width());
+
+        assert_eq!(plan.assignments.len(), 4);
+        assert_eq!(plan.bytes_sent(NodeId(1)), 1 << 63);
+        assert_eq!(plan.bytes_sent(NodeId(2)), 1 << 63);
+        assert_eq!(plan.estimated_duration, Duration::from_millis(u64::MAX));
+    }
+
+    #[test]
+    fn test_empty_plan() {
+        let plan = StreamPlanner::default().plan(&[], |_| 0);
+        assert_eq!(plan, StreamPlan::default());
+
+        let zero = StreamPlanner::default().plan(&[transfer(0, 2, &[1])], |_| 0);
+        assert_eq!(zero.assignments[0].source, NodeId(1));
+        assert_eq!(zero.estimated_duration, Duration::ZERO);
+    }
+}
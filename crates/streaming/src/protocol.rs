@@ -1,4 +1,487 @@
 //! Streaming protocol definitions.
 //!
-//! This module will describe the wire-level protocol for ring and data streaming.
+//! Peers exchange `Message`s over a transport: ring state for metadata sync,
+//! and range data (files split into chunks) for bootstrap, decommission and
+//! rebalancing. Every message carries the session it belongs to and a
+//! per-sender sequence number, so a receiver can detect gaps, acknowledge
+//! progress and resume.
+//!
+//! # Session Flow
+//!
+//! ```text
+//! sender                               receiver
+//!   | -- Hello ------------------------->  |   both sides announce versions
+//!   | <------------------------ Hello --   |
+//!   | -- CompressionOffer -------------->  |   v3+: algorithms each side
+//!   | <-------------- CompressionOffer --  |   accepts (`crate::compression`)
+//!   | -- RingStateRequest -------------->  |   optional metadata sync
+//!   | <------------- RingStateResponse --  |
+//!   | -- StreamInit (ranges) ----------->  |
+//!   | <----------- Resume (checkpoint) --  |   v2+: what an earlier attempt
+//!   |                                      |   of the session delivered
+//!   | -- FileHeader, FileChunk* -------->  |   repeated per file
+//!   | <-------------------------- Ack ---  |   cumulative, any time
+//!   | -- Complete ---------------------->  |
+//!   | <-------------------------- Ack ---  |
+//! ```
+//!
+//! Either side may send `KeepAlive` while idle and `Error` to abort.
+//!
+//! Retrying a failed session with the same `SessionId` resumes it: the
+//! sender skips what the `Resume` checkpoint lists as received (see
+//! `crate::checkpoint`).
+//!
+//! # Versioning Rules
+//!
+//! Mixed-version clusters must keep streaming during rolling upgrades:
+//!
+//! 1. Each release supports a contiguous window of protocol versions,
+//!    `MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION`. The window always includes
+//!    the previous release's `PROTOCOL_VERSION`.
+//! 2. `Hello` is the first message in each direction and its layout never
+//!    changes. Both sides compute `negotiate_version()` from the two
+//!    windows; if the windows do not overlap the session is aborted with
+//!    `Error`.
+//! 3. After the handshake, a peer only sends message types whose
+//!    `MessageType::since()` is at most the negotiated version.
+//! 4. The layout of an existing message type is frozen for every version
+//!    that supports it. Changing a layout means a new message type (or a
+//!    new version whose codec encodes the old type differently); fields
+//!    are never repurposed.
+//! 5. Message type codes are never reused, even after a type is retired.
+
+use crate::checkpoint::SessionCheckpoint;
+use corelib::node::NodeId;
+use corelib::ring::RingState;
+use corelib::token::murmur3::Murmur3Token;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Protocol version spoken by this release (highest supported).
+///
+/// - 1: initial protocol
+/// - 2: `Resume`
+/// - 3: `CompressionOffer` and compressed frames
+pub const PROTOCOL_VERSION: u16 = 3;
+
+/// Oldest protocol version this release can still speak.
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// Pick the version two peers will speak.
+///
+/// # Algorithm
+///
+/// The highest version inside both `[min, max]` windows:
+/// `min(local_max, remote_max)`, provided it is at least
+/// `max(local_min, remote_min)`.
+///
+/// # Returns
+/// The negotiated version, or `None` if the windows do not overlap
+///
+/// # Example
+/// ```rust
+/// # use streaming::protocol::negotiate_version;
+/// assert_eq!(negotiate_version((1, 3), (2, 5)), Some(3));
+/// assert_eq!(negotiate_version((1, 1), (2, 2)), None);
+/// ```
+pub fn negotiate_version(local: (u16, u16), remote: (u16, u16)) -> Option<u16> {
+    let (local_min, local_max) = local;
+    let (remote_min, remote_max) = remote;
+    let version = local_max.min(remote_max);
+    (version >= local_min.max(remote_min)).then_some(version)
+}
+
+/// Identifier of a streaming session, unique per (sender, receiver) pair.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub struct SessionId(pub u64);
+
+impl fmt::Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// A range of ring tokens, `(start, end]`.
+///
+/// Matches ring ownership: a vnode at token `end` owns everything after the
+/// preceding vnode `start` up to and including `end`. If `start >= end` the
+/// range wraps past the top of the ring; `start == end` is the whole ring.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub struct TokenRange {
+    /// Exclusive lower bound.
+    pub start: Murmur3Token,
+    /// Inclusive upper bound.
+    pub end: Murmur3Token,
+}
+
+impl TokenRange {
+    /// Construct the range `(start, end]`.
+    pub fn new(start: Murmur3Token, end: Murmur3Token) -> Self {
+        Self { start, end }
+    }
+
+    /// True if the range crosses the top of the ring.
+    pub fn wraps(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// Check whether `token` lies in the range.
+    pub fn contains(&self, token: Murmur3Token) -> bool {
+        if self.wraps() {
+            token > self.start || token <= self.end
+        } else {
+            token > self.start && token <= self.end
+        }
+    }
+
+    /// Number of tokens in the range.
+    ///
+    /// The whole ring (`start == end`) has 2^64 tokens and saturates at
+    /// `u64::MAX`.
+    pub fn width(&self) -> u64 {
+        match self.end.0.wrapping_sub(self.start.0) {
+            0 => u64::MAX,
+            width => width,
+        }
+    }
+}
+
+impl fmt::Display for TokenRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:#018x}, {:#018x}]", self.start.0, self.end.0)
+    }
+}
+
+/// Why a data stream is being opened.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum StreamPurpose {
+    /// A joining node pulls the ranges it will own.
+    Bootstrap,
+    /// A leaving node pushes its ranges to their new owners.
+    Decommission,
+    /// Ranges move after a token change.
+    Rebalance,
+    /// A node takes over the ranges of a dead node.
+    Replace,
+}
+
+/// Wire code of each message, sent in every frame header.
+///
+/// Codes are stable: never renumber or reuse them (see the versioning rules
+/// in the module docs).
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum MessageType {
+    Hello = 1,
+    RingStateRequest = 2,
+    RingStateResponse = 3,
+    StreamInit = 4,
+    FileHeader = 5,
+    FileChunk = 6,
+    Ack = 7,
+    Complete = 8,
+    Error = 9,
+    KeepAlive = 10,
+    Resume = 11,
+    CompressionOffer = 12,
+}
+
+impl MessageType {
+    /// All message types, in code order.
+    pub const ALL: [MessageType; 12] = [
+        MessageType::Hello,
+        MessageType::RingStateRequest,
+        MessageType::RingStateResponse,
+        MessageType::StreamInit,
+        MessageType::FileHeader,
+        MessageType::FileChunk,
+        MessageType::Ack,
+        MessageType::Complete,
+        MessageType::Error,
+        MessageType::KeepAlive,
+        MessageType::Resume,
+        MessageType::CompressionOffer,
+    ];
+
+    /// Wire code.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Decode a wire code.
+    ///
+    /// # Returns
+    /// The message type, or `None` for codes unknown to this release
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|t| t.code() == code)
+    }
+
+    /// Protocol version that introduced this message type.
+    ///
+    /// A peer must not send this type on a session negotiated below it.
+    pub fn since(self) -> u16 {
+        match self {
+            MessageType::Resume => 2,
+            MessageType::CompressionOffer => 3,
+            _ => 1,
+        }
+    }
+
+    /// True if the type may be sent before the handshake completes.
+    pub fn is_handshake(self) -> bool {
+        matches!(
+            self,
+            MessageType::Hello | MessageType::Error | MessageType::CompressionOffer
+        )
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Message contents, one variant per `MessageType`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Payload {
+    /// First message in each direction: who we are and what we speak.
+    Hello {
+        /// Sending node.
+        node_id: NodeId,
+        /// Oldest protocol version the sender supports.
+        min_version: u16,
+        /// Newest protocol version the sender supports.
+        max_version: u16,
+    },
+
+    /// Ask the peer for its ring state.
+    RingStateRequest {
+        /// Epoch the requester already has; the peer may skip the state if
+        /// its own epoch is not newer.
+        known_epoch: Option<u64>,
+    },
+
+    /// Reply to `RingStateRequest`.
+    RingStateResponse {
+        /// The peer's ring, or `None` if `known_epoch` is already current.
+        state: Option<RingState>,
+    },
+
+    /// Announce the ranges about to be streamed.
+    StreamInit {
+        /// Why the stream is happening.
+        purpose: StreamPurpose,
+        /// Ranges covered by this session.
+        ranges: Vec<TokenRange>,
+    },
+
+    /// Start of one file of range data.
+    FileHeader {
+        /// Identifier of the file within the session.
+        file_id: u32,
+        /// Range the file's data belongs to.
+        range: TokenRange,
+        /// File name, for logging and on-disk placement.
+        name: String,
+        /// Total size in bytes.
+        size: u64,
+    },
+
+    /// A slice of a file's contents.
+    FileChunk {
+        /// File announced by an earlier `FileHeader`.
+        file_id: u32,
+        /// Byte offset of `data` within the file.
+        offset: u64,
+        /// Chunk contents.
+        data: Vec<u8>,
+    },
+
+    /// Cumulative acknowledgement.
+    Ack {
+        /// Every message up to and including this sequence number was
+        /// received and applied.
+        sequence: u64,
+    },
+
+    /// The sender has streamed every range.
+    Complete {
+        /// Number of files sent.
+        files: u32,
+        /// Number of data bytes sent.
+        bytes: u64,
+    },
+
+    /// Abort the session.
+    Error {
+        /// Whether the peer may retry (e.g. overload) or should give up.
+        retryable: bool,
+        /// Human-readable reason.
+        message: String,
+    },
+
+    /// Liveness probe on an otherwise idle session.
+    KeepAlive,
+
+    /// Receiver's reply to `StreamInit`: what earlier attempts of this
+    /// session already delivered.
+    Resume {
+        /// Received ranges and file offsets (empty for a new session).
+        checkpoint: SessionCheckpoint,
+    },
+
+    /// Sent by both sides right after `Hello`: the compression algorithms
+    /// the sender accepts.
+    CompressionOffer {
+        /// `Compression` wire codes, the initiator's in order of
+        /// preference. Codes unknown to the receiver are ignored.
+        algorithms: Vec<u8>,
+    },
+}
+
+impl Payload {
+    /// The wire type of this payload.
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Payload::Hello { .. } => MessageType::Hello,
+            Payload::RingStateRequest { .. } => MessageType::RingStateRequest,
+            Payload::RingStateResponse { .. } => MessageType::RingStateResponse,
+            Payload::StreamInit { .. } => MessageType::StreamInit,
+            Payload::FileHeader { .. } => MessageType::FileHeader,
+            Payload::FileChunk { .. } => MessageType::FileChunk,
+            Payload::Ack { .. } => MessageType::Ack,
+            Payload::Complete { .. } => MessageType::Complete,
+            Payload::Error { .. } => MessageType::Error,
+            Payload::KeepAlive => MessageType::KeepAlive,
+            Payload::Resume { .. } => MessageType::Resume,
+            Payload::CompressionOffer { .. } => MessageType::CompressionOffer,
+        }
+    }
+}
+
+/// A protocol message: session, sequence number and payload.
+///
+/// Sequence numbers start at 0 and increase by one per message sent on the
+/// session, independently in each direction.
+///
+/// # Example
+/// ```rust
+/// # use streaming::protocol::{Message, MessageType, Payload, SessionId};
+/// let msg = Message::new(SessionId(7), 0, Payload::KeepAlive);
+/// assert_eq!(msg.message_type(), MessageType::KeepAlive);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    /// Session the message belongs to.
+    pub session_id: SessionId,
+    /// Per-direction sequence number.
+    pub sequence: u64,
+    /// Message contents.
+    pub payload: Payload,
+}
+
+impl Message {
+    /// Construct a message.
+    pub fn new(session_id: SessionId, sequence: u64, payload: Payload) -> Self {
+        Self {
+            session_id,
+            sequence,
+            payload,
+        }
+    }
+
+    /// `Hello` announcing this release's supported version window.
+    pub fn hello(session_id: SessionId, node_id: NodeId) -> Self {
+        Self::new(
+            session_id,
+            0,
+            Payload::Hello {
+                node_id,
+                min_version: MIN_PROTOCOL_VERSION,
+                max_version: PROTOCOL_VERSION,
+            },
+        )
+    }
+
+    /// The wire type of this message.
+    pub fn message_type(&self) -> MessageType {
+        self.payload.message_type()
+    }
+
+    /// Check whether this message may be sent on a session at `version`.
+    pub fn allowed_at(&self, version: u16) -> bool {
+        self.message_type().since() <= version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_type_codes_round_trip() {
+        for message_type in MessageType::ALL {
+            assert_eq!(
+                MessageType::from_code(message_type.code()),
+                Some(message_type)
+            );
+        }
+        assert_eq!(MessageType::from_code(0), None);
+        assert_eq!(MessageType::from_code(200), None);
+    }
+
+    #[test]
+    fn test_resume_needs_version_2() {
+        let resume = Message::new(
+            SessionId(1),
+            1,
+            Payload::Resume {
+                checkpoint: SessionCheckpoint::new(SessionId(1), NodeId(1)),
+            },
+        );
+        assert!(!resume.allowed_at(1));
+        assert!(resume.allowed_at(2));
+        assert!(Message::hello(SessionId(1), NodeId(1)).allowed_at(MIN_PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn test_compression_offer_needs_version_3() {
+        let offer = Message::new(
+            SessionId(1),
+            0,
+            Payload::CompressionOffer {
+                algorithms: vec![1, 2],
+            },
+        );
+        assert!(!offer.allowed_at(2));
+        assert!(offer.allowed_at(3));
+        assert!(offer.message_type().is_handshake());
+    }
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(negotiate_version((1, 1), (1, 1)), Some(1));
+        // Rolling upgrade: new release speaks 1..=2, old one 1..=1
+        assert_eq!(negotiate_version((1, 2), (1, 1)), Some(1));
+        assert_eq!(negotiate_version((1, 3), (1, 2)), Some(2));
+        assert_eq!(negotiate_version((1, 1), (1, 2)), Some(1));
+        assert_eq!(negotiate_version((3, 4), (1, 2)), None);
+    }
+
+    #[test]
+    fn test_token_range_contains() {
+        let range = TokenRange::new(Murmur3Token(10), Murmur3Token(20));
+        assert!(!range.contains(Murmur3Token(10)));
+        assert!(range.contains(Murmur3Token(20)));
+        assert!(!range.wraps());
+
+        let wrapping = TokenRange::new(Murmur3Token(20), Murmur3Token(10));
+        assert!(wrapping.contains(Murmur3Token(u64::MAX)));
+        assert!(wrapping.contains(Murmur3Token(0)));
+        assert!(!wrapping.contains(Murmur3Token(15)));
 
+        let full = TokenRange::new(Murmur3Token(5), Murmur3Token(5));
+        assert!(full.contains(Murmur3Token(5)) && full.contains(Murmur3Token(6)));
+    }
+}
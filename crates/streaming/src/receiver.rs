@@ -1,4 +1,433 @@
 //! Inbound streaming.
 //!
-//! This module will manage receiving ring and data streams from peers.
+//! `StreamReceiver` accepts a session from a peer's `StreamSender` and
+//! writes the streamed range data into a `RangeSink`.
+//!
+//! # State Machine
+//!
+//! ```text
+//! Handshake --> AwaitInit --> Receiving --> Done
+//!                                 |
+//!                                 +--> Failed (bad sequence, unknown file,
+//!                                      out-of-order chunk, Error, close)
+//! ```
+//!
+//! Every data-plane message is validated before anything is written:
+//! sequence numbers must be contiguous, chunks must belong to an announced
+//! file and arrive in offset order, and `Complete` must match what was
+//! received. Each accepted message is acknowledged.
+//!
+//! # Checkpoints
+//!
+//! From protocol version 2 the receiver keeps a `SessionCheckpoint` of the
+//! session in a `CheckpointStore`: ranges the sender moved past, and bytes
+//! written per file. It is saved at range boundaries, every
+//! `checkpoint_interval` bytes and when the session fails, always after
+//! flushing the sink, and sent back in `Resume` when the sender retries.
+//! A completed session's checkpoint is removed.
+
+use crate::checkpoint::{CheckpointStore, MemoryCheckpointStore, SessionCheckpoint};
+use crate::compression::{Compression, DEFAULT_COMPRESSION};
+use crate::data::RangeSink;
+use crate::error::{Result, StreamingError};
+use crate::protocol::{Message, MessageType, Payload, SessionId, TokenRange};
+use crate::sender::StreamSummary;
+use crate::transport::{handshake, within, Transport};
+use corelib::node::NodeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default number of bytes received between checkpoint saves (64 MiB).
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 64 * 1024 * 1024;
+
+/// A file announced by `FileHeader` and not yet complete.
+struct IncomingFile {
+    range: TokenRange,
+    name: String,
+    size: u64,
+    received: u64,
+}
+
+/// Receives range data from one peer.
+///
+/// See `StreamSender` for an end-to-end example.
+pub struct StreamReceiver<K> {
+    node_id: NodeId,
+    sink: K,
+    checkpoints: Arc<dyn CheckpointStore>,
+    checkpoint_interval: u64,
+    compression: Vec<Compression>,
+    idle_timeout: Option<Duration>,
+}
+
+/// Per-session receive state.
+struct Session {
+    session_id: SessionId,
+    version: u16,
+    /// Sequence number expected from the sender next.
+    expected: u64,
+    /// Sequence number of our next outgoing message.
+    next_sequence: u64,
+    ranges: Option<Vec<TokenRange>>,
+    open: HashMap<u32, IncomingFile>,
+    next_file_id: u32,
+    /// Index of the range currently being streamed.
+    current_range: usize,
+    files: u32,
+    bytes: u64,
+    checkpoint: SessionCheckpoint,
+    /// Bytes received before this attempt.
+    resumed: u64,
+    /// Bytes received since the checkpoint was last saved.
+    unsaved: u64,
+}
+
+impl<K: RangeSink> StreamReceiver<K> {
+    /// Create a receiver.
+    ///
+    /// # Arguments
+    /// * `node_id` - This node, announced in `Hello`
+    /// * `sink` - Where received range data is written
+    pub fn new(node_id: NodeId, sink: K) -> Self {
+        Self {
+            node_id,
+            sink,
+            checkpoints: Arc::new(MemoryCheckpointStore::new()),
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            compression: DEFAULT_COMPRESSION.to_vec(),
+            idle_timeout: None,
+        }
+    }
+
+    /// Keep checkpoints in `store` instead of this receiver's memory, e.g.
+    /// a `FileCheckpointStore` to resume sessions across restarts.
+    pub fn with_checkpoint_store(mut self, store: Arc<dyn CheckpointStore>) -> Self {
+        self.checkpoints = store;
+        self
+    }
+
+    /// Set the number of bytes received between checkpoint saves
+    /// (at least 1).
+    pub fn with_checkpoint_interval(mut self, bytes: u64) -> Self {
+        self.checkpoint_interval = bytes.max(1);
+        self
+    }
+
+    /// Set the compression algorithms accepted from senders (default
+    /// `DEFAULT_COMPRESSION`). The sender's preference decides among them;
+    /// an empty list disables compression.
+    pub fn with_compression(mut self, algorithms: impl IntoIterator<Item = Compression>) -> Self {
+        self.compression = algorithms.into_iter().collect();
+        self
+    }
+
+    /// Fail the session with `StreamingError::Timeout` when the handshake,
+    /// or a wait for the sender's next message, takes longer than `timeout`
+    /// (default: wait indefinitely). Keep it above the longest pause a
+    /// throttled sender may leave between chunks.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Accept one session and receive until the sender completes.
+    ///
+    /// # Returns
+    /// Summary of what was received
+    ///
+    /// # Errors
+    /// Fails on handshake failure, transport or storage errors, an `Error`
+    /// from the sender, any protocol violation, or the idle timeout.
+    /// Violations and storage failures are reported to the sender with an
+    /// `Error` first, marked retryable as `StreamingError::is_retryable()`
+    /// classifies them. The session's checkpoint is saved so that a retry
+    /// can resume it.
+    pub async fn run<T: Transport + ?Sized>(&mut self, transport: &mut T) -> Result<StreamSummary> {
+        let handshake = within(
+            self.idle_timeout,
+            handshake(transport, self.node_id, None, &self.compression),
+        )
+        .await?;
+        let checkpoint = if handshake.version >= MessageType::Resume.since() {
+            self.checkpoints
+                .load(handshake.peer, handshake.session_id)?
+        } else {
+            None
+        };
+        let checkpoint = checkpoint
+            .unwrap_or_else(|| SessionCheckpoint::new(handshake.session_id, handshake.peer));
+        let mut session = Session {
+            session_id: handshake.session_id,
+            version: handshake.version,
+            expected: 1,
+            next_sequence: 1,
+            ranges: None,
+            open: HashMap::new(),
+            next_file_id: 0,
+            current_range: 0,
+            files: 0,
+            bytes: 0,
+            resumed: checkpoint.received_bytes(),
+            checkpoint,
+            unsaved: 0,
+        };
+
+        let result = self.receive(transport, &mut session).await;
+        match &result {
+            Ok(()) => {
+                // A stale checkpoint only costs disk space
+                let _ = self
+                    .checkpoints
+                    .remove(handshake.peer, handshake.session_id);
+            }
+            Err(_) => {
+                let _ = self.save(&mut session).await;
+            }
+        }
+        if let Err(e @ (StreamingError::Protocol(_) | StreamingError::Storage(_))) = &result {
+            let abort = Payload::Error {
+                retryable: e.is_retryable(),
+                message: e.to_string(),
+            };
+            let _ = transport
+                .send(Message::new(
+                    session.session_id,
+                    session.next_sequence,
+                    abort,
+                ))
+                .await;
+        }
+        result?;
+
+        Ok(StreamSummary {
+            session_id: session.session_id,
+            peer: handshake.peer,
+            version: handshake.version,
+            compression: handshake.compression,
+            ranges: session.ranges.unwrap_or_default(),
+            files: session.files,
+            bytes: session.bytes,
+            resumed: session.resumed,
+        })
+    }
+
+    async fn receive<T: Transport + ?Sized>(
+        &mut self,
+        transport: &mut T,
+        session: &mut Session,
+    ) -> Result<()> {
+        loop {
+            let message = within(self.idle_timeout, transport.recv())
+                .await?
+                .ok_or(StreamingError::Closed)?;
+            if message.session_id != session.session_id {
+                return Err(StreamingError::Protocol(format!(
+                    "message for session {} on session {}",
+                    message.session_id, session.session_id
+                )));
+            }
+            if let Payload::KeepAlive = message.payload {
+                continue;
+            }
+            if message.sequence != session.expected {
+                return Err(StreamingError::Protocol(format!(
+                    "expected sequence {}, got {}",
+                    session.expected, message.sequence
+                )));
+            }
+            session.expected += 1;
+
+            let init = matches!(message.payload, Payload::StreamInit { .. });
+            let done = self.handle(session, message.payload).await?;
+            let ack = Payload::Ack {
+                sequence: message.sequence,
+            };
+            self.reply(transport, session, ack).await?;
+            if init && session.version >= MessageType::Resume.since() {
+                let checkpoint = session.checkpoint.clone();
+                self.reply(transport, session, Payload::Resume { checkpoint })
+                    .await?;
+            }
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn reply<T: Transport + ?Sized>(
+        &self,
+        transport: &mut T,
+        session: &mut Session,
+        payload: Payload,
+    ) -> Result<()> {
+        let message = Message::new(session.session_id, session.next_sequence, payload);
+        transport.send(message).await?;
+        session.next_sequence += 1;
+        Ok(())
+    }
+
+    /// Validate and apply one payload.
+    ///
+    /// # Returns
+    /// `true` once the session is complete
+    async fn handle(&self, session: &mut Session, payload: Payload) -> Result<bool> {
+        let Some(ranges) = &session.ranges else {
+            return match payload {
+                Payload::StreamInit { ranges, .. } => {
+                    session.ranges = Some(ranges);
+                    Ok(false)
+                }
+                Payload::Error { retryable, message } => {
+                    Err(StreamingError::Rejected { retryable, message })
+                }
+                other => Err(StreamingError::Protocol(format!(
+                    "expected StreamInit, got {}",
+                    other.message_type()
+                ))),
+            };
+        };
+
+        match payload {
+            Payload::FileHeader {
+                file_id,
+                range,
+                name,
+                size,
+            } => {
+                let Some(index) = ranges.iter().position(|r| *r == range) else {
+                    return Err(StreamingError::Protocol(format!(
+                        "file {} belongs to unannounced range {}",
+                        name, range
+                    )));
+                };
+                if index < session.current_range {
+                    return Err(StreamingError::Protocol(format!(
+                        "file {} of range {} follows a later range",
+                        name, range
+                    )));
+                }
+                if file_id != session.next_file_id {
+                    return Err(StreamingError::Protocol(format!(
+                        "expected file {}, got {}",
+                        session.next_file_id, file_id
+                    )));
+                }
+                session.next_file_id += 1;
+                if index > session.current_range {
+                    self.complete_ranges(session, index).await?;
+                }
+
+                // Continue where an earlier attempt stopped
+                let received = session
+                    .checkpoint
+                    .resume_offset(range, &name, size)
+                    .filter(|&received| received < size)
+                    .unwrap_or(0);
+                session.checkpoint.record_file(range, &name, size, received);
+                let file = IncomingFile {
+                    range,
+                    name,
+                    size,
+                    received,
+                };
+                if size == 0 {
+                    self.finish(session, file).await?;
+                } else {
+                    session.open.insert(file_id, file);
+                }
+                Ok(false)
+            }
+            Payload::FileChunk {
+                file_id,
+                offset,
+                data,
+            } => {
+                let file = session.open.get_mut(&file_id).ok_or_else(|| {
+                    StreamingError::Protocol(format!("chunk for unknown file {}", file_id))
+                })?;
+                let end = offset.checked_add(data.len() as u64).ok_or_else(|| {
+                    StreamingError::Protocol(format!(
+                        "chunk at {} of {} ends past the largest offset",
+                        offset, file.name
+                    ))
+                })?;
+                if offset != file.received || end > file.size {
+                    return Err(StreamingError::Protocol(format!(
+                        "chunk {}..{} of {} does not follow byte {} (size {})",
+                        offset, end, file.name, file.received, file.size
+                    )));
+                }
+                self.sink
+                    .write(file.range, &file.name, offset, &data)
+                    .await?;
+                file.received = end;
+                session
+                    .checkpoint
+                    .record_file(file.range, &file.name, file.size, end);
+                session.bytes += data.len() as u64;
+                session.unsaved += data.len() as u64;
+
+                if end == file.size {
+                    let file = session.open.remove(&file_id).expect("file is open");
+                    self.finish(session, file).await?;
+                }
+                if session.unsaved >= self.checkpoint_interval {
+                    self.save(session).await?;
+                }
+                Ok(false)
+            }
+            Payload::Complete { files, bytes } => {
+                if !session.open.is_empty() || files != session.files || bytes != session.bytes {
+                    return Err(StreamingError::Protocol(format!(
+                        "sender completed with {} files / {} bytes, received {} / {} ({} open)",
+                        files,
+                        bytes,
+                        session.files,
+                        session.bytes,
+                        session.open.len()
+                    )));
+                }
+                let end = session.ranges.as_ref().map_or(0, Vec::len);
+                self.complete_ranges(session, end).await?;
+                Ok(true)
+            }
+            Payload::Error { retryable, message } => {
+                Err(StreamingError::Rejected { retryable, message })
+            }
+            other => Err(StreamingError::Protocol(format!(
+                "receiver got unexpected {}",
+                other.message_type()
+            ))),
+        }
+    }
+
+    async fn finish(&self, session: &mut Session, file: IncomingFile) -> Result<()> {
+        self.sink
+            .finish_file(file.range, &file.name, file.size)
+            .await?;
+        session.files += 1;
+        Ok(())
+    }
+
+    /// Mark the ranges before index `end` complete; the sender has moved on.
+    async fn complete_ranges(&self, session: &mut Session, end: usize) -> Result<()> {
+        let ranges = session.ranges.as_deref().unwrap_or_default();
+        for &range in &ranges[session.current_range..end] {
+            session.checkpoint.complete_range(range);
+        }
+        session.current_range = end;
+        self.save(session).await
+    }
 
+    /// Flush the sink and save the checkpoint (sessions at version 2+).
+    async fn save(&self, session: &mut Session) -> Result<()> {
+        if session.version < MessageType::Resume.since() {
+            return Ok(());
+        }
+        self.sink.flush().await?;
+        self.checkpoints.save(&session.checkpoint)?;
+        session.unsaved = 0;
+        Ok(())
+    }
+}
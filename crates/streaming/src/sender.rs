@@ -1,4 +1,440 @@
 //! Outbound streaming.
 //!
-//! This module will manage sending ring and data streams to peers.
+//! `StreamSender` pushes the data of a set of token ranges to a peer's
+//! `StreamReceiver` over any `Transport`.
+//!
+//! # State Machine
+//!
+//! ```text
+//! Handshake --> Init --> Streaming --> Completing --> Done
+//!    |            |          |             |
+//!    +------------+----------+-------------+--> Failed (Error sent/received,
+//!                                                transport closed, bad ack)
+//! ```
+//!
+//! - **Handshake**: exchange `Hello`, agree on a protocol version and,
+//!   from version 3, on a compression algorithm
+//! - **Init**: announce purpose and ranges with `StreamInit`; from protocol
+//!   version 2 the receiver answers with a `Resume` checkpoint
+//! - **Streaming**: per range, per file: `FileHeader` then `FileChunk`s,
+//!   skipping whatever the checkpoint lists as received
+//! - **Completing**: send `Complete`, wait until it is acknowledged
+//!
+//! # Flow Control
+//!
+//! The receiver acknowledges every data-plane message. The sender keeps at
+//! most `window` unacknowledged messages in flight and waits for acks
+//! beyond that, so a slow receiver slows the sender instead of filling
+//! buffers without bound.
+//!
+//! Independently of the receiver, chunks are read only as fast as the
+//! sender's bandwidth limiters allow: its own per-session limiter and an
+//! optional global one shared with other senders (see `crate::throttle`).
+//!
+//! # Resuming
+//!
+//! A failed session is resumed by running a sender with the same
+//! `SessionId` against the same receiver. Complete ranges and files are
+//! skipped and a partial file continues from its checkpointed offset, as
+//! long as its size is unchanged.
+
+use crate::checkpoint::SessionCheckpoint;
+use crate::compression::{Compression, DEFAULT_COMPRESSION};
+use crate::data::RangeSource;
+use crate::error::{Result, StreamingError};
+use crate::protocol::{Message, MessageType, Payload, SessionId, StreamPurpose, TokenRange};
+use crate::throttle::{BandwidthLimiter, ThrottleMetrics};
+use crate::transport::{handshake, within, Transport};
+use corelib::node::NodeId;
+use std::time::Duration;
+
+/// Default size of each `FileChunk` (64 KiB).
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Default number of unacknowledged messages in flight.
+pub const DEFAULT_WINDOW: u64 = 64;
+
+/// Outcome of a finished session, reported by both ends.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamSummary {
+    /// Session identifier.
+    pub session_id: SessionId,
+    /// The other end of the session.
+    pub peer: NodeId,
+    /// Negotiated protocol version.
+    pub version: u16,
+    /// Negotiated frame compression.
+    pub compression: Compression,
+    /// Ranges streamed.
+    pub ranges: Vec<TokenRange>,
+    /// Files transferred.
+    pub files: u32,
+    /// Data bytes transferred.
+    pub bytes: u64,
+    /// Data bytes skipped because earlier attempts of the session
+    /// delivered them.
+    pub resumed: u64,
+}
+
+/// Streams range data to one peer.
+///
+/// # Example
+/// ```rust
+/// # use streaming::data::MemoryRangeStore;
+/// # use streaming::protocol::{SessionId, StreamPurpose, TokenRange};
+/// # use streaming::transport::duplex;
+/// # use streaming::{StreamReceiver, StreamSender};
+/// # use corelib::token::murmur3::Murmur3Token;
+/// # use corelib::NodeId;
+/// # use std::sync::Arc;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let range = TokenRange::new(Murmur3Token(0), Murmur3Token(100));
+/// let source = Arc::new(MemoryRangeStore::new());
+/// source.insert(range, "data-1", vec![7; 1000]);
+/// let sink = Arc::new(MemoryRangeStore::new());
+///
+/// let (mut a, mut b) = duplex(64 * 1024);
+/// let mut sender = StreamSender::new(SessionId(1), NodeId(1), source);
+/// let mut receiver = StreamReceiver::new(NodeId(2), sink.clone());
+///
+/// let (sent, received) = tokio::join!(
+///     sender.run(&mut a, StreamPurpose::Bootstrap, vec![range]),
+///     receiver.run(&mut b),
+/// );
+/// assert_eq!(sent.unwrap().bytes, 1000);
+/// assert_eq!(received.unwrap().files, 1);
+/// assert_eq!(sink.file(range, "data-1").unwrap(), vec![7; 1000]);
+/// # }
+/// ```
+pub struct StreamSender<S> {
+    session_id: SessionId,
+    node_id: NodeId,
+    source: S,
+    chunk_size: usize,
+    window: u64,
+    limiter: BandwidthLimiter,
+    global_limiter: Option<BandwidthLimiter>,
+    compression: Vec<Compression>,
+    idle_timeout: Option<Duration>,
+    /// Sequence number of the next message to send.
+    next_sequence: u64,
+    /// Highest sequence number the receiver has acknowledged.
+    acked: u64,
+}
+
+impl<S: RangeSource> StreamSender<S> {
+    /// Create a sender for one session.
+    ///
+    /// # Arguments
+    /// * `session_id` - Identifier proposed to the receiver
+    /// * `node_id` - This node, announced in `Hello`
+    /// * `source` - Where range data is read from
+    pub fn new(session_id: SessionId, node_id: NodeId, source: S) -> Self {
+        Self {
+            session_id,
+            node_id,
+            source,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            window: DEFAULT_WINDOW,
+            limiter: BandwidthLimiter::unlimited(),
+            global_limiter: None,
+            compression: DEFAULT_COMPRESSION.to_vec(),
+            idle_timeout: None,
+            next_sequence: 0,
+            acked: 0,
+        }
+    }
+
+    /// Set the maximum `FileChunk` payload size (at least 1 byte).
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Set the number of unacknowledged messages allowed in flight
+    /// (at least 1).
+    pub fn with_window(mut self, window: u64) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Limit this session to `bytes_per_sec` of range data.
+    pub fn with_bandwidth_limit(self, bytes_per_sec: u64) -> Self {
+        self.limiter.set_limit(Some(bytes_per_sec));
+        self
+    }
+
+    /// Also acquire bandwidth from `limiter`, shared with other senders.
+    pub fn with_global_limiter(mut self, limiter: BandwidthLimiter) -> Self {
+        self.global_limiter = Some(limiter);
+        self
+    }
+
+    /// Set the compression algorithms offered to the receiver, most
+    /// preferred first (default `DEFAULT_COMPRESSION`). An empty list
+    /// disables compression.
+    pub fn with_compression(mut self, algorithms: impl IntoIterator<Item = Compression>) -> Self {
+        self.compression = algorithms.into_iter().collect();
+        self
+    }
+
+    /// Fail the session with `StreamingError::Timeout` when the handshake,
+    /// or a wait for the receiver's reply, takes longer than `timeout`
+    /// (default: wait indefinitely).
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Session identifier.
+    pub fn session_id(&self) -> SessionId {
+        self.session_id
+    }
+
+    /// Handle to this session's limiter, to change its limit while the
+    /// session runs.
+    pub fn limiter(&self) -> BandwidthLimiter {
+        self.limiter.clone()
+    }
+
+    /// This session's bandwidth limit and throughput.
+    pub fn metrics(&self) -> ThrottleMetrics {
+        self.limiter.metrics()
+    }
+
+    /// Stream every range's data to the peer.
+    ///
+    /// # Returns
+    /// Summary of what was sent, once the receiver acknowledged `Complete`
+    ///
+    /// # Errors
+    /// Fails on handshake failure, transport or storage errors, an `Error`
+    /// from the peer, an invalid acknowledgement, or the idle timeout. The
+    /// peer is sent an `Error` for local storage failures.
+    /// `StreamingError::is_retryable()` tells whether running the session
+    /// again (resuming it) may succeed.
+    pub async fn run<T: Transport + ?Sized>(
+        &mut self,
+        transport: &mut T,
+        purpose: StreamPurpose,
+        ranges: Vec<TokenRange>,
+    ) -> Result<StreamSummary> {
+        let handshake = within(
+            self.idle_timeout,
+            handshake(
+                transport,
+                self.node_id,
+                Some(self.session_id),
+                &self.compression,
+            ),
+        )
+        .await?;
+        // Hello used sequence 0 and needs no ack
+        self.next_sequence = 1;
+        self.acked = 0;
+
+        let result = self
+            .stream(transport, purpose, &ranges, handshake.version)
+            .await;
+        if let Err(e @ StreamingError::Storage(message)) = &result {
+            let abort = Payload::Error {
+                retryable: e.is_retryable(),
+                message: message.clone(),
+            };
+            let _ = transport
+                .send(Message::new(self.session_id, self.next_sequence, abort))
+                .await;
+        }
+        let (files, bytes, resumed) = result?;
+
+        Ok(StreamSummary {
+            session_id: self.session_id,
+            peer: handshake.peer,
+            version: handshake.version,
+            compression: handshake.compression,
+            ranges,
+            files,
+            bytes,
+            resumed,
+        })
+    }
+
+    async fn stream<T: Transport + ?Sized>(
+        &mut self,
+        transport: &mut T,
+        purpose: StreamPurpose,
+        ranges: &[TokenRange],
+        version: u16,
+    ) -> Result<(u32, u64, u64)> {
+        let init = Payload::StreamInit {
+            purpose,
+            ranges: ranges.to_vec(),
+        };
+        self.send(transport, init).await?;
+        let checkpoint = if version >= MessageType::Resume.since() {
+            Some(self.await_resume(transport).await?)
+        } else {
+            None
+        };
+
+        let mut files = 0u32;
+        let mut bytes = 0u64;
+        let mut resumed = 0u64;
+        for &range in ranges {
+            let range_complete = checkpoint
+                .as_ref()
+                .is_some_and(|c| c.is_range_complete(range));
+            for file in self.source.files(range).await? {
+                let received = match &checkpoint {
+                    _ if range_complete => Some(file.size),
+                    Some(c) => c.resume_offset(range, &file.name, file.size),
+                    None => None,
+                };
+                let mut offset = match received {
+                    Some(received) if received >= file.size => {
+                        resumed += file.size;
+                        continue;
+                    }
+                    Some(received) => received,
+                    None => 0,
+                };
+                resumed += offset;
+
+                let file_id = files;
+                files += 1;
+                let header = Payload::FileHeader {
+                    file_id,
+                    range,
+                    name: file.name.clone(),
+                    size: file.size,
+                };
+                self.send(transport, header).await?;
+
+                while offset < file.size {
+                    let len = (file.size - offset).min(self.chunk_size as u64) as usize;
+                    self.throttle(len as u64).await;
+                    let data = self.source.read(range, &file.name, offset, len).await?;
+                    if data.is_empty() || data.len() > len {
+                        return Err(StreamingError::Storage(format!(
+                            "read of {} at offset {} returned {} bytes, expected 1..={}",
+                            file.name,
+                            offset,
+                            data.len(),
+                            len
+                        )));
+                    }
+                    let chunk_len = data.len() as u64;
+                    let chunk = Payload::FileChunk {
+                        file_id,
+                        offset,
+                        data,
+                    };
+                    self.send(transport, chunk).await?;
+                    offset += chunk_len;
+                    bytes += chunk_len;
+                }
+            }
+        }
+
+        let complete = self
+            .send(transport, Payload::Complete { files, bytes })
+            .await?;
+        while self.acked < complete {
+            self.await_ack(transport).await?;
+        }
+        Ok((files, bytes, resumed))
+    }
+
+    /// Wait until every limiter admits `bytes`.
+    async fn throttle(&self, bytes: u64) {
+        self.limiter.acquire(bytes).await;
+        if let Some(global) = &self.global_limiter {
+            global.acquire(bytes).await;
+        }
+    }
+
+    /// Send a payload, waiting for acks while the window is full.
+    ///
+    /// # Returns
+    /// The sequence number the payload was sent with
+    async fn send<T: Transport + ?Sized>(
+        &mut self,
+        transport: &mut T,
+        payload: Payload,
+    ) -> Result<u64> {
+        let sequence = self.next_sequence;
+        transport
+            .send(Message::new(self.session_id, sequence, payload))
+            .await?;
+        self.next_sequence += 1;
+
+        while sequence - self.acked >= self.window {
+            self.await_ack(transport).await?;
+        }
+        Ok(sequence)
+    }
+
+    /// Wait for the next acknowledgement.
+    async fn await_ack<T: Transport + ?Sized>(&mut self, transport: &mut T) -> Result<()> {
+        loop {
+            match self.recv_reply(transport).await? {
+                Payload::Ack { sequence } => return self.acknowledge(sequence),
+                Payload::KeepAlive => continue,
+                other => return Err(unexpected(other)),
+            }
+        }
+    }
+
+    /// Wait for the receiver's checkpoint, applying acks that come first.
+    async fn await_resume<T: Transport + ?Sized>(
+        &mut self,
+        transport: &mut T,
+    ) -> Result<SessionCheckpoint> {
+        loop {
+            match self.recv_reply(transport).await? {
+                Payload::Resume { checkpoint } => return Ok(checkpoint),
+                Payload::Ack { sequence } => self.acknowledge(sequence)?,
+                Payload::KeepAlive => continue,
+                other => return Err(unexpected(other)),
+            }
+        }
+    }
+
+    /// Next payload from the receiver; an `Error` fails the session.
+    async fn recv_reply<T: Transport + ?Sized>(&mut self, transport: &mut T) -> Result<Payload> {
+        let message = within(self.idle_timeout, transport.recv())
+            .await?
+            .ok_or(StreamingError::Closed)?;
+        if message.session_id != self.session_id {
+            return Err(StreamingError::Protocol(format!(
+                "message for session {} on session {}",
+                message.session_id, self.session_id
+            )));
+        }
+        match message.payload {
+            Payload::Error { retryable, message } => {
+                Err(StreamingError::Rejected { retryable, message })
+            }
+            payload => Ok(payload),
+        }
+    }
+
+    fn acknowledge(&mut self, sequence: u64) -> Result<()> {
+        if sequence >= self.next_sequence {
+            return Err(StreamingError::Protocol(format!(
+                "ack for unsent sequence {}",
+                sequence
+            )));
+        }
+        self.acked = self.acked.max(sequence);
+        Ok(())
+    }
+}
 
+fn unexpected(payload: Payload) -> StreamingError {
+    StreamingError::Protocol(format!(
+        "sender received unexpected {}",
+        payload.message_type()
+    ))
+}
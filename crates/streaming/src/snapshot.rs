@@ -1,4 +1,676 @@
 //! Snapshot and bootstrap support.
 //!
-//! This module will contain operations for taking snapshots and bootstrapping nodes.
+//! A joining node must hold the data of every range it will own before it
+//! starts serving reads. `Bootstrap` drives that process:
+//!
+//! ```text
+//! 1. fetch ring snapshot     seed --RingStateResponse--> joining node
+//! 2. plan                    ring diff (current vs. current + node)
+//!                            -> ranges gained, with their current replicas
+//! 3. stream                  per source replica: pull its assigned ranges
+//!                            (checkpointed after every finished session)
+//! 4. join                    add the node's tokens to the ring: Normal
+//! ```
+//!
+//! # Resuming
+//!
+//! Progress is written to a JSON file after each step. A bootstrap that
+//! crashes or loses its sources is rerun with the same file and only pulls
+//! the ranges that are still pending. If the ring moved on in the meantime
+//! the plan is recomputed, keeping ranges that were already completed.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use streaming::snapshot::{Bootstrap, BootstrapNetwork};
+//! # use corelib::partitioner::Murmur3Partitioner;
+//! # use corelib::{Node, NodeId};
+//! # use std::sync::Arc;
+//! # async fn example(network: impl BootstrapNetwork) -> streaming::Result<()> {
+//! let bootstrap = Bootstrap::new(
+//!     Node::new(NodeId(4), "node4"),
+//!     256,
+//!     Arc::new(Murmur3Partitioner),
+//!     "/var/lib/ring/bootstrap.json",
+//! );
+//! let (ring, progress) = bootstrap.run(&network).await?;
+//! assert!(ring.get_node(&NodeId(4)).is_some());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::compression::DEFAULT_COMPRESSION;
+use crate::error::{Result, StreamingError};
+use crate::planner::StreamPlanner;
+use crate::protocol::{Message, Payload, SessionId, TokenRange};
+use crate::sender::StreamSummary;
+use crate::transport::{handshake, Transport};
+use async_trait::async_trait;
+use corelib::node::{Node, NodeId};
+use corelib::partitioner::RingPartitioner;
+use corelib::ring::{HashRing, RingChange, RingState};
+use corelib::token::murmur3::Murmur3Token;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Default number of replicas per range.
+pub const DEFAULT_REPLICATION_FACTOR: usize = 3;
+
+/// Default number of sessions tried against one source before it is
+/// excluded.
+pub const DEFAULT_SOURCE_ATTEMPTS: u32 = 3;
+
+// ============================================================================
+// Ring Diff
+// ============================================================================
+
+/// A range a node gains in a ring change, and who holds its data today.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeTransfer {
+    /// The range gained.
+    pub range: TokenRange,
+    /// Node that gains it.
+    pub destination: NodeId,
+    /// Current replicas of the range, in ring order (empty if none).
+    pub sources: Vec<NodeId>,
+}
+
+/// First `count` distinct owners walking clockwise from `token`.
+fn replicas_from(
+    tokens: &[(Murmur3Token, NodeId)],
+    token: Murmur3Token,
+    count: usize,
+) -> Vec<NodeId> {
+    let mut replicas = Vec::with_capacity(count);
+    if tokens.is_empty() {
+        return replicas;
+    }
+
+    let start = tokens.partition_point(|(t, _)| *t < token) % tokens.len();
+    for i in 0..tokens.len() {
+        if replicas.len() == count {
+            break;
+        }
+        let owner = tokens[(start + i) % tokens.len()].1;
+        if !replicas.contains(&owner) {
+            replicas.push(owner);
+        }
+    }
+    replicas
+}
+
+/// Diff two rings: every range whose replica set gains a node.
+///
+/// # Algorithm
+///
+/// Token boundaries of both rings split the token space into elementary
+/// ranges, each with a single replica set before and after. For each
+/// elementary range `(b[i-1], b[i]]`, the replicas on either side are the
+/// first `replication_factor` distinct owners walking clockwise from
+/// `b[i]`. Nodes replicating the range after but not before gain it.
+///
+/// # Performance
+/// - **Time**: O(b · (log t + w)) for b boundaries, where w is the walk
+///   needed to find `replication_factor` distinct owners
+/// - **Space**: O(b)
+///
+/// # Arguments
+/// * `before`, `after` - Ring tokens sorted by token (as `HashRing::tokens()`)
+/// * `replication_factor` - Replicas per range
+///
+/// # Example
+/// ```rust
+/// # use streaming::snapshot::range_transfers;
+/// # use corelib::token::murmur3::Murmur3Token;
+/// # use corelib::NodeId;
+/// let before = vec![(Murmur3Token(100), NodeId(1))];
+/// let after = vec![(Murmur3Token(50), NodeId(2)), (Murmur3Token(100), NodeId(1))];
+///
+/// // Node 2 takes (100, 50] (wrapping) from node 1
+/// let transfers = range_transfers(&before, &after, 1);
+/// assert_eq!(transfers.len(), 1);
+/// assert_eq!(transfers[0].destination, NodeId(2));
+/// assert_eq!(transfers[0].sources, vec![NodeId(1)]);
+/// ```
+pub fn range_transfers(
+    before: &[(Murmur3Token, NodeId)],
+    after: &[(Murmur3Token, NodeId)],
+    replication_factor: usize,
+) -> Vec<RangeTransfer> {
+    let mut boundaries: Vec<Murmur3Token> = before
+        .iter()
+        .chain(after)
+        .map(|(token, _)| *token)
+        .collect();
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut transfers = Vec::new();
+    for (i, &end) in boundaries.iter().enumerate() {
+        let start = boundaries[(i + boundaries.len() - 1) % boundaries.len()];
+        let old = replicas_from(before, end, replication_factor);
+        for destination in replicas_from(after, end, replication_factor) {
+            if !old.contains(&destination) {
+                transfers.push(RangeTransfer {
+                    range: TokenRange::new(start, end),
+                    destination,
+                    sources: old.clone(),
+                });
+            }
+        }
+    }
+    transfers
+}
+
+// ============================================================================
+// Ring State Exchange
+// ============================================================================
+
+/// Ask a peer for its ring state.
+///
+/// # Arguments
+/// * `known_epoch` - Epoch already held; the peer skips the state if it
+///   has nothing newer
+///
+/// # Returns
+/// The validated ring state, or `None` if `known_epoch` is current
+pub async fn fetch_ring_state<T: Transport + ?Sized>(
+    transport: &mut T,
+    session_id: SessionId,
+    node_id: NodeId,
+    known_epoch: Option<u64>,
+) -> Result<Option<RingState>> {
+    handshake(transport, node_id, Some(session_id), &DEFAULT_COMPRESSION).await?;
+    let request = Payload::RingStateRequest { known_epoch };
+    transport.send(Message::new(session_id, 1, request)).await?;
+
+    loop {
+        let message = transport.recv().await?.ok_or(StreamingError::Closed)?;
+        match message.payload {
+            Payload::RingStateResponse { state } if message.session_id == session_id => {
+                if let Some(state) = &state {
+                    state.validate()?;
+                }
+                return Ok(state);
+            }
+            Payload::KeepAlive => continue,
+            Payload::Error { retryable, message } => {
+                return Err(StreamingError::Rejected { retryable, message })
+            }
+            other => {
+                return Err(StreamingError::Protocol(format!(
+                    "expected RingStateResponse, got {}",
+                    other.message_type()
+                )))
+            }
+        }
+    }
+}
+
+/// Answer one `fetch_ring_state()` request with `ring`'s snapshot.
+pub async fn serve_ring_state<T: Transport + ?Sized>(
+    transport: &mut T,
+    node_id: NodeId,
+    ring: &HashRing,
+) -> Result<()> {
+    let handshake = handshake(transport, node_id, None, &DEFAULT_COMPRESSION).await?;
+    let message = transport.recv().await?.ok_or(StreamingError::Closed)?;
+    let known_epoch = match message.payload {
+        Payload::RingStateRequest { known_epoch } if message.sequence == 1 => known_epoch,
+        other => {
+            return Err(StreamingError::Protocol(format!(
+                "expected RingStateRequest, got {}",
+                other.message_type()
+            )))
+        }
+    };
+
+    let state = match known_epoch {
+        Some(epoch) if epoch >= ring.epoch() => None,
+        _ => Some(ring.snapshot()),
+    };
+    let response = Payload::RingStateResponse { state };
+    transport
+        .send(Message::new(handshake.session_id, 1, response))
+        .await
+}
+
+// ============================================================================
+// Bootstrap
+// ============================================================================
+
+/// How a bootstrapping node reaches the rest of the cluster.
+#[async_trait]
+pub trait BootstrapNetwork: Send + Sync {
+    /// Fetch the current ring from a seed (e.g. via `fetch_ring_state()`).
+    async fn ring_state(&self) -> Result<RingState>;
+
+    /// Have `source` stream `ranges` to this node, writing them locally.
+    async fn stream_ranges(&self, source: &Node, ranges: Vec<TokenRange>) -> Result<StreamSummary>;
+}
+
+/// Membership state of a bootstrapping node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NodeState {
+    /// Streaming its ranges; not yet in the ring.
+    Joining,
+    /// All ranges streamed and tokens added to the ring.
+    Normal,
+}
+
+/// Progress of one range.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeProgress {
+    /// The range to pull.
+    pub range: TokenRange,
+    /// Replicas holding the range, in preference order.
+    pub sources: Vec<NodeId>,
+    /// Replica the range was pulled from, once complete.
+    pub completed_from: Option<NodeId>,
+}
+
+/// Persistent record of a bootstrap, for resuming after a crash.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootstrapProgress {
+    /// The joining node.
+    pub node: Node,
+    /// Virtual nodes it joins with.
+    pub vnodes: usize,
+    /// Ring epoch the plan was computed against.
+    pub ring_epoch: u64,
+    /// Membership state.
+    pub state: NodeState,
+    /// Every range to pull.
+    pub ranges: Vec<RangeProgress>,
+    /// Bytes streamed so far.
+    pub bytes: u64,
+}
+
+impl BootstrapProgress {
+    /// Ranges not yet pulled.
+    pub fn pending(&self) -> impl Iterator<Item = &RangeProgress> {
+        self.ranges.iter().filter(|r| r.completed_from.is_none())
+    }
+
+    /// True once every range has been pulled.
+    pub fn is_complete(&self) -> bool {
+        self.pending().next().is_none()
+    }
+
+    /// Read progress from `path`.
+    ///
+    /// # Returns
+    /// The progress, or `None` if the file does not exist
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| StreamingError::Storage(format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write progress to `path` atomically (temporary file, then rename).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json =
+            serde_json::to_vec_pretty(self).map_err(|e| StreamingError::Storage(e.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Coordinates bootstrapping one joining node.
+///
+/// # Source Selection
+///
+/// Pending ranges are spread over their live replicas by a `StreamPlanner`,
+/// weighting each range by its token width (the partitioner spreads data
+/// evenly over the token space). Ranges are pulled in one session per
+/// source, all sources concurrently. A source whose session fails with a
+/// retryable error (`StreamingError::is_retryable()`) stays eligible, so
+/// the next round can resume the session, until it has failed
+/// `source_attempts` times. Any other failure excludes it at once. Ranges
+/// of excluded sources are replanned onto the remaining replicas; when a
+/// range has none left the bootstrap stops with the last error, and can be
+/// resumed later. A cancelled session stops the bootstrap immediately.
+pub struct Bootstrap {
+    node: Node,
+    vnodes: usize,
+    replication_factor: usize,
+    source_attempts: u32,
+    partitioner: Arc<RingPartitioner>,
+    planner: StreamPlanner,
+    progress_path: PathBuf,
+}
+
+impl Bootstrap {
+    /// Create a coordinator.
+    ///
+    /// # Arguments
+    /// * `node` - The joining node
+    /// * `vnodes` - Virtual nodes it joins with
+    /// * `partitioner` - The cluster's partitioner (to rebuild the ring)
+    /// * `progress_path` - Where progress is persisted
+    pub fn new(
+        node: Node,
+        vnodes: usize,
+        partitioner: Arc<RingPartitioner>,
+        progress_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            node,
+            vnodes,
+            replication_factor: DEFAULT_REPLICATION_FACTOR,
+            source_attempts: DEFAULT_SOURCE_ATTEMPTS,
+            partitioner,
+            planner: StreamPlanner::default(),
+            progress_path: progress_path.into(),
+        }
+    }
+
+    /// Set the number of replicas per range.
+    pub fn with_replication_factor(mut self, replication_factor: usize) -> Self {
+        self.replication_factor = replication_factor.max(1);
+        self
+    }
+
+    /// Set the number of failed sessions after which a source is excluded
+    /// (at least 1).
+    pub fn with_source_attempts(mut self, attempts: u32) -> Self {
+        self.source_attempts = attempts.max(1);
+        self
+    }
+
+    /// Set the planner used to choose sources (e.g. with per-node limits).
+    pub fn with_planner(mut self, planner: StreamPlanner) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Compute the ranges the node will own and where to pull them from.
+    ///
+    /// Ranges with no current replica (an empty ring) need no streaming
+    /// and are left out.
+    ///
+    /// # Errors
+    /// `StreamingError::Ring` if the node is already a member or its tokens
+    /// collide with existing ones
+    pub fn plan(&self, ring: &HashRing) -> Result<Vec<RangeProgress>> {
+        if ring.get_node(&self.node.id).is_some() {
+            return Err(corelib::Error::InvalidNode(format!(
+                "node {} is already a ring member",
+                self.node.id
+            ))
+            .into());
+        }
+
+        let future = HashRing::from_state(ring.snapshot(), self.partitioner.clone())?;
+        future.apply(RingChange::add_node(self.node.clone(), self.vnodes))?;
+
+        Ok(
+            range_transfers(&ring.tokens(), &future.tokens(), self.replication_factor)
+                .into_iter()
+                .filter(|t| t.destination == self.node.id && !t.sources.is_empty())
+                .map(|t| RangeProgress {
+                    range: t.range,
+                    sources: t.sources,
+                    completed_from: None,
+                })
+                .collect(),
+        )
+    }
+
+    /// Run (or resume) the bootstrap to completion.
+    ///
+    /// # Returns
+    /// The ring with the node added, and the final progress (`Normal`)
+    ///
+    /// # Errors
+    /// Any planning error, progress file error, `StreamingError::Cancelled`
+    /// from a session, or the last streaming error once a range has no
+    /// source left. Progress made so far is kept.
+    pub async fn run<N: BootstrapNetwork + ?Sized>(
+        &self,
+        network: &N,
+    ) -> Result<(HashRing, BootstrapProgress)> {
+        let ring = HashRing::from_state(network.ring_state().await?, self.partitioner.clone())?;
+        let saved = BootstrapProgress::load(&self.progress_path)?;
+        if let Some(saved) = &saved {
+            if saved.node.id != self.node.id {
+                return Err(StreamingError::Storage(format!(
+                    "{} records a bootstrap of node {}",
+                    self.progress_path.display(),
+                    saved.node.id
+                )));
+            }
+            if saved.state == NodeState::Normal {
+                // The cluster may not have seen the join yet
+                if ring.get_node(&self.node.id).is_none() {
+                    ring.apply(RingChange::add_node(saved.node.clone(), saved.vnodes))?;
+                }
+                return Ok((ring, saved.clone()));
+            }
+        }
+
+        let mut progress = match saved {
+            Some(saved) if saved.ring_epoch == ring.epoch() => saved,
+            saved => self.replan(&ring, saved)?,
+        };
+        progress.save(&self.progress_path)?;
+
+        self.stream_pending(&ring, &mut progress, network).await?;
+
+        ring.apply(RingChange::add_node(self.node.clone(), self.vnodes))?;
+        progress.state = NodeState::Normal;
+        progress.save(&self.progress_path)?;
+        Ok((ring, progress))
+    }
+
+    /// Fresh plan for `ring`, keeping ranges completed under an older plan.
+    fn replan(
+        &self,
+        ring: &HashRing,
+        saved: Option<BootstrapProgress>,
+    ) -> Result<BootstrapProgress> {
+        let mut ranges = self.plan(ring)?;
+        let mut bytes = 0;
+        if let Some(saved) = saved {
+            let completed: HashMap<TokenRange, NodeId> = saved
+                .ranges
+                .iter()
+                .filter_map(|r| Some((r.range, r.completed_from?)))
+                .collect();
+            for range in &mut ranges {
+                range.completed_from = completed.get(&range.range).copied();
+            }
+            bytes = saved.bytes;
+        }
+
+        Ok(BootstrapProgress {
+            node: self.node.clone(),
+            vnodes: self.vnodes,
+            ring_epoch: ring.epoch(),
+            state: NodeState::Joining,
+            ranges,
+            bytes,
+        })
+    }
+
+    async fn stream_pending<N: BootstrapNetwork + ?Sized>(
+        &self,
+        ring: &HashRing,
+        progress: &mut BootstrapProgress,
+        network: &N,
+    ) -> Result<()> {
+        let mut failed: HashSet<NodeId> = HashSet::new();
+        let mut attempts: HashMap<NodeId, u32> = HashMap::new();
+        let mut last_error = None;
+
+        loop {
+            // Plan every pending range over its live replicas
+            let pending: Vec<usize> = (0..progress.ranges.len())
+                .filter(|&i| progress.ranges[i].completed_from.is_none())
+                .collect();
+            let transfers: Vec<RangeTransfer> = pending
+                .iter()
+                .map(|&i| RangeTransfer {
+                    range: progress.ranges[i].range,
+                    destination: self.node.id,
+                    sources: progress.ranges[i]
+                        .sources
+                        .iter()
+                        .copied()
+                        .filter(|id| ring.get_node(id).is_some())
+                        .collect(),
+                })
+                .collect();
+            let plan = self
+                .planner
+                .clone()
+                .with_excluded(failed.iter().copied())
+                .plan(&transfers, |t| t.range.width());
+            if let Some(transfer) = plan.unassigned.first() {
+                return Err(last_error.unwrap_or_else(|| {
+                    StreamingError::Transport(format!(
+                        "no live source for range {}",
+                        transfer.range
+                    ))
+                }));
+            }
+
+            let index: HashMap<TokenRange, usize> = pending
+                .iter()
+                .map(|&i| (progress.ranges[i].range, i))
+                .collect();
+            let mut batches: BTreeMap<NodeId, Vec<usize>> = BTreeMap::new();
+            for assignment in plan.assignments {
+                batches
+                    .entry(assignment.source)
+                    .or_default()
+                    .push(index[&assignment.range]);
+            }
+            if batches.is_empty() {
+                return Ok(());
+            }
+
+            let sessions = batches.iter().map(|(source, indices)| {
+                let node = ring.get_node(source).expect("source is a ring member");
+                let ranges = indices.iter().map(|&i| progress.ranges[i].range).collect();
+                async move { network.stream_ranges(&node, ranges).await }
+            });
+            let results = join_all(sessions).await;
+
+            let mut cancelled = false;
+            for ((source, indices), result) in batches.into_iter().zip(results) {
+                match result {
+                    Ok(summary) => {
+                        for i in indices {
+                            progress.ranges[i].completed_from = Some(source);
+                        }
+                        progress.bytes += summary.bytes;
+                    }
+                    Err(e) => {
+                        let attempt = attempts.entry(source).or_default();
+                        *attempt += 1;
+                        if !e.is_retryable() || *attempt >= self.source_attempts {
+                            failed.insert(source);
+                        }
+                        cancelled |= matches!(e, StreamingError::Cancelled);
+                        last_error = Some(e);
+                    }
+                }
+            }
+            progress.save(&self.progress_path)?;
+            if cancelled {
+                return Err(StreamingError::Cancelled);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(token: u64) -> Murmur3Token {
+        Murmur3Token(token)
+    }
+
+    #[test]
+    fn test_range_transfers_on_join() {
+        let before = vec![(t(100), NodeId(1)), (t(200), NodeId(2))];
+        let after = vec![
+            (t(100), NodeId(1)),
+            (t(150), NodeId(3)),
+            (t(200), NodeId(2)),
+        ];
+
+        let transfers = range_transfers(&before, &after, 1);
+        assert_eq!(
+            transfers,
+            vec![RangeTransfer {
+                range: TokenRange::new(t(100), t(150)),
+                destination: NodeId(3),
+                sources: vec![NodeId(2)],
+            }]
+        );
+
+        // With two replicas node 3 also becomes the second replica of the
+        // range ending at 100 (previously held by nodes 1 and 2)
+        let transfers = range_transfers(&before, &after, 2);
+        assert_eq!(transfers.len(), 2);
+        assert!(transfers.iter().all(|tr| tr.destination == NodeId(3)));
+        assert!(transfers
+            .iter()
+            .any(|tr| tr.range == TokenRange::new(t(200), t(100))));
+    }
+
+    #[test]
+    fn test_range_transfers_on_leave() {
+        // Node 3 leaves: its range moves to the next node clockwise
+        let before = vec![
+            (t(100), NodeId(1)),
+            (t(150), NodeId(3)),
+            (t(200), NodeId(2)),
+        ];
+        let after = vec![(t(100), NodeId(1)), (t(200), NodeId(2))];
+
+        let transfers = range_transfers(&before, &after, 1);
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].range, TokenRange::new(t(100), t(150)));
+        assert_eq!(transfers[0].destination, NodeId(2));
+        assert_eq!(transfers[0].sources, vec![NodeId(3)]);
+    }
+
+    #[test]
+    fn test_progress_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bootstrap.json");
+        assert_eq!(BootstrapProgress::load(&path).unwrap(), None);
 
+        let progress = BootstrapProgress {
+            node: Node::new(NodeId(1), "node1"),
+            vnodes: 8,
+            ring_epoch: 3,
+            state: NodeState::Joining,
+            ranges: vec![RangeProgress {
+                range: TokenRange::new(t(1), t(2)),
+                sources: vec![NodeId(2)],
+                completed_from: None,
+            }],
+            bytes: 0,
+        };
+        progress.save(&path).unwrap();
+        assert_eq!(BootstrapProgress::load(&path).unwrap(), Some(progress));
+    }
+}
@@ -0,0 +1,300 @@
+//! Bandwidth throttling for streaming.
+//!
+//! Rebalance and bootstrap streams share links with foreground traffic.
+//! A `BandwidthLimiter` caps the rate at which a `StreamSender` reads and
+//! sends range data:
+//!
+//! - **Per session**: every sender owns a limiter (unlimited by default)
+//! - **Global**: one limiter shared by all senders of a node bounds their
+//!   combined rate
+//!
+//! A sender acquires bytes from each of its limiters before reading a
+//! chunk, so a throttled session stops pulling data from its source instead
+//! of buffering it. Limits can be changed at any time through a limiter
+//! handle and apply to sessions already running.
+//!
+//! # Token Bucket
+//!
+//! Tokens (bytes) accrue at the configured rate up to `BURST` worth of
+//! them. A request proceeds as soon as the bucket is not in debt and then
+//! takes its full size, possibly going into debt; later requests wait for
+//! the debt to be repaid. Chunks larger than the bucket are therefore
+//! allowed, and the long-run rate still equals the limit.
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Time's worth of tokens a bucket holds at most.
+pub const BURST: Duration = Duration::from_millis(100);
+
+/// Longest a waiting request sleeps before re-reading the limit, so that
+/// raised limits take effect promptly.
+const MAX_WAIT: Duration = Duration::from_millis(100);
+
+/// Window over which throughput is measured.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Throughput estimate over a sliding one-second window.
+///
+/// Interpolates between the previous and current fixed windows, weighting
+/// the previous one by how much of it the sliding window still covers.
+#[derive(Debug)]
+struct Meter {
+    window_start: Instant,
+    current: u64,
+    previous: u64,
+    total: u64,
+}
+
+impl Meter {
+    fn new(now: Instant) -> Self {
+        Self {
+            window_start: now,
+            current: 0,
+            previous: 0,
+            total: 0,
+        }
+    }
+
+    fn roll(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= 2 * WINDOW {
+            self.previous = 0;
+            self.current = 0;
+            self.window_start = now;
+        } else if elapsed >= WINDOW {
+            self.previous = self.current;
+            self.current = 0;
+            self.window_start += WINDOW;
+        }
+    }
+
+    fn record(&mut self, bytes: u64, now: Instant) {
+        self.roll(now);
+        self.current += bytes;
+        self.total += bytes;
+    }
+
+    /// Bytes per second.
+    fn rate(&mut self, now: Instant) -> u64 {
+        self.roll(now);
+        let covered = now
+            .saturating_duration_since(self.window_start)
+            .as_secs_f64()
+            / WINDOW.as_secs_f64();
+        let bytes = self.previous as f64 * (1.0 - covered) + self.current as f64;
+        (bytes / WINDOW.as_secs_f64()) as u64
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes per second, or `None` for no limit.
+    limit: Option<u64>,
+    /// Available bytes; negative while in debt.
+    tokens: f64,
+    last_refill: Instant,
+    meter: Meter,
+}
+
+impl Bucket {
+    fn capacity(limit: u64) -> f64 {
+        limit as f64 * BURST.as_secs_f64()
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(limit) = self.limit {
+            let elapsed = now
+                .saturating_duration_since(self.last_refill)
+                .as_secs_f64();
+            self.tokens = (self.tokens + elapsed * limit as f64).min(Self::capacity(limit));
+        }
+        self.last_refill = now;
+    }
+}
+
+/// Current state of a limiter, for monitoring.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ThrottleMetrics {
+    /// Configured limit in bytes per second (`None`: unlimited).
+    pub limit: Option<u64>,
+    /// Bytes per second passed over the last second.
+    pub throughput: u64,
+    /// Bytes passed since the limiter was created.
+    pub total_bytes: u64,
+}
+
+/// A token-bucket byte rate limit, shared by cloning.
+///
+/// Clones are handles to the same bucket: share one between senders for a
+/// global limit, or keep one to adjust a running session.
+///
+/// # Example
+/// ```rust
+/// # use streaming::throttle::BandwidthLimiter;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let global = BandwidthLimiter::new(Some(100 * 1024 * 1024));
+/// global.acquire(64 * 1024).await;
+/// assert_eq!(global.metrics().total_bytes, 64 * 1024);
+///
+/// // Foreground traffic picked up: slow every stream down
+/// global.set_limit(Some(10 * 1024 * 1024));
+/// assert_eq!(global.limit(), Some(10 * 1024 * 1024));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BandwidthLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+impl BandwidthLimiter {
+    /// Create a limiter.
+    ///
+    /// # Arguments
+    /// * `limit` - Bytes per second, or `None` for no limit (a limit of 0
+    ///   is treated as 1 byte per second)
+    pub fn new(limit: Option<u64>) -> Self {
+        let now = Instant::now();
+        let limit = limit.map(|limit| limit.max(1));
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                limit,
+                tokens: limit.map_or(0.0, Bucket::capacity),
+                last_refill: now,
+                meter: Meter::new(now),
+            })),
+        }
+    }
+
+    /// A limiter that only measures throughput.
+    pub fn unlimited() -> Self {
+        Self::new(None)
+    }
+
+    /// Configured limit in bytes per second.
+    pub fn limit(&self) -> Option<u64> {
+        self.bucket.lock().limit
+    }
+
+    /// Change the limit; waiting and future requests use the new one.
+    ///
+    /// Debt accrued under the old limit is kept, so lowering the limit
+    /// never lets a burst through.
+    pub fn set_limit(&self, limit: Option<u64>) {
+        let mut bucket = self.bucket.lock();
+        bucket.refill(Instant::now());
+        bucket.limit = limit.map(|limit| limit.max(1));
+        if let Some(limit) = bucket.limit {
+            bucket.tokens = bucket.tokens.min(Bucket::capacity(limit));
+        }
+    }
+
+    /// Wait until `bytes` may be sent, then take them from the bucket.
+    pub async fn acquire(&self, bytes: u64) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock();
+                let now = Instant::now();
+                bucket.refill(now);
+                match bucket.limit {
+                    Some(limit) if bucket.tokens < 0.0 => {
+                        Duration::from_secs_f64(-bucket.tokens / limit as f64)
+                    }
+                    _ => {
+                        if bucket.limit.is_some() {
+                            bucket.tokens -= bytes as f64;
+                        }
+                        bucket.meter.record(bytes, now);
+                        return;
+                    }
+                }
+            };
+            tokio::time::sleep(wait.min(MAX_WAIT)).await;
+        }
+    }
+
+    /// Current limit and measured throughput.
+    pub fn metrics(&self) -> ThrottleMetrics {
+        let mut bucket = self.bucket.lock();
+        let limit = bucket.limit;
+        ThrottleMetrics {
+            limit,
+            throughput: bucket.meter.rate(Instant::now()),
+            total_bytes: bucket.meter.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limits_rate() {
+        let limiter = BandwidthLimiter::new(Some(1000));
+        let start = Instant::now();
+        for _ in 0..30 {
+            limiter.acquire(100).await;
+        }
+        // 3000 bytes at 1000 B/s, less the initial burst of 100 bytes
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(2800), "{:?}", elapsed);
+        assert!(elapsed <= Duration::from_millis(3000), "{:?}", elapsed);
+
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.limit, Some(1000));
+        assert_eq!(metrics.total_bytes, 3000);
+        assert!((800..=1200).contains(&metrics.throughput), "{:?}", metrics);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_oversized_request_goes_into_debt() {
+        let limiter = BandwidthLimiter::new(Some(1000));
+        let start = Instant::now();
+        limiter.acquire(5000).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        // The next request waits for the debt to be repaid
+        limiter.acquire(1).await;
+        assert!(start.elapsed() >= Duration::from_millis(4900));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_limit_changes_at_runtime() {
+        let limiter = BandwidthLimiter::new(Some(10));
+        limiter.acquire(1000).await;
+
+        // ~100 s of debt, but raising the limit releases the waiter quickly
+        let waiter = {
+            let limiter = limiter.clone();
+            tokio::spawn(async move { limiter.acquire(1).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let start = Instant::now();
+        limiter.set_limit(None);
+        waiter.await.unwrap();
+        assert!(start.elapsed() <= MAX_WAIT);
+        assert_eq!(limiter.limit(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throughput_decays_when_idle() {
+        let limiter = BandwidthLimiter::unlimited();
+        limiter.acquire(5000).await;
+        assert_eq!(limiter.metrics().throughput, 5000);
+
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        let metrics = limiter.metrics();
+        assert_eq!(metrics.throughput, 0);
+        assert_eq!(metrics.total_bytes, 5000);
+    }
+}
@@ -0,0 +1,32 @@
+//! In-memory transport.
+
+use super::FramedTransport;
+use tokio::io::DuplexStream;
+
+/// In-process transport backed by `tokio::io::duplex`.
+pub type MemoryTransport = FramedTransport<DuplexStream>;
+
+/// Create a connected pair of in-memory transports.
+///
+/// Messages still go through `MessageCodec`, so framing and checksums are
+/// exercised exactly as on a socket.
+///
+/// # Arguments
+/// * `max_buf_size` - Bytes buffered per direction before writes wait
+///
+/// # Example
+/// ```rust
+/// # use streaming::transport::{duplex, Transport};
+/// # use streaming::protocol::{Message, Payload, SessionId};
+/// # #[tokio::main]
+/// # async fn main() {
+/// let (mut a, mut b) = duplex(64 * 1024);
+/// let msg = Message::new(SessionId(1), 0, Payload::KeepAlive);
+/// a.send(msg.clone()).await.unwrap();
+/// assert_eq!(b.recv().await.unwrap(), Some(msg));
+/// # }
+/// ```
+pub fn duplex(max_buf_size: usize) -> (MemoryTransport, MemoryTransport) {
+    let (a, b) = tokio::io::duplex(max_buf_size);
+    (FramedTransport::new(a), FramedTransport::new(b))
+}
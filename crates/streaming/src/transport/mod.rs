@@ -0,0 +1,251 @@
+//! Message transports for streaming sessions.
+//!
+//! `StreamSender` and `StreamReceiver` only need an ordered, reliable
+//! channel of `Message`s, described by the `Transport` trait. Any byte
+//! stream becomes a transport through `FramedTransport`, which frames
+//! messages with `MessageCodec`.
+//!
+//! # Implementations
+//!
+//! - **memory**: in-process duplex pipe, for tests and local streaming
+//! - **tcp**: TCP connections, one session per connection
+//! - **quic**: QUIC connections, one stream per token range
+
+pub mod memory;
+pub mod quic;
+pub mod tcp;
+
+use crate::codec::MessageCodec;
+use crate::compression::{negotiate_compression, Compression};
+use crate::error::{Result, StreamingError};
+use crate::protocol::{
+    negotiate_version, Message, MessageType, Payload, SessionId, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
+};
+use async_trait::async_trait;
+use corelib::node::NodeId;
+use futures::{Future, SinkExt, StreamExt};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::Framed;
+
+pub use memory::duplex;
+pub use quic::{QuicEndpoint, QuicTransport};
+pub use tcp::TcpTransport;
+
+/// An ordered, reliable, bidirectional channel of messages.
+#[async_trait]
+pub trait Transport: Send {
+    /// Send one message.
+    async fn send(&mut self, message: Message) -> Result<()>;
+
+    /// Receive the next message.
+    ///
+    /// # Returns
+    /// The message, or `None` if the peer closed the channel cleanly
+    async fn recv(&mut self) -> Result<Option<Message>>;
+
+    /// Switch to the protocol version negotiated during the handshake.
+    fn set_version(&mut self, version: u16) -> Result<()>;
+
+    /// Switch to the compression negotiated during the handshake.
+    fn set_compression(&mut self, compression: Compression) -> Result<()>;
+}
+
+/// Transport over any async byte stream, framed with `MessageCodec`.
+#[derive(Debug)]
+pub struct FramedTransport<T> {
+    framed: Framed<T, MessageCodec>,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> FramedTransport<T> {
+    /// Wrap a byte stream with the default codec.
+    pub fn new(io: T) -> Self {
+        Self::with_codec(io, MessageCodec::new())
+    }
+
+    /// Wrap a byte stream with a configured codec.
+    pub fn with_codec(io: T, codec: MessageCodec) -> Self {
+        Self {
+            framed: Framed::new(io, codec),
+        }
+    }
+
+    /// The underlying byte stream.
+    pub fn get_ref(&self) -> &T {
+        self.framed.get_ref()
+    }
+}
+
+#[async_trait]
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Transport for FramedTransport<T> {
+    async fn send(&mut self, message: Message) -> Result<()> {
+        self.framed.send(message).await
+    }
+
+    async fn recv(&mut self) -> Result<Option<Message>> {
+        self.framed.next().await.transpose()
+    }
+
+    fn set_version(&mut self, version: u16) -> Result<()> {
+        self.framed.codec_mut().set_version(version)
+    }
+
+    fn set_compression(&mut self, compression: Compression) -> Result<()> {
+        self.framed.codec_mut().set_compression(compression)
+    }
+}
+
+/// Run `future`, failing if it takes longer than `timeout`.
+///
+/// # Arguments
+/// * `timeout` - Time limit, or `None` to wait indefinitely
+///
+/// # Errors
+/// `StreamingError::Timeout` once the limit passes, or the future's error
+pub(crate) async fn within<F, R>(timeout: Option<Duration>, future: F) -> Result<R>
+where
+    F: Future<Output = Result<R>>,
+{
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .map_err(|_| StreamingError::Timeout(timeout))?,
+        None => future.await,
+    }
+}
+
+/// Result of a successful handshake.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Handshake {
+    pub session_id: SessionId,
+    pub peer: NodeId,
+    pub version: u16,
+    pub compression: Compression,
+}
+
+/// Exchange `Hello`s and switch the transport to the negotiated version.
+///
+/// The initiator sends first and proposes the session ID; the other side
+/// adopts it from the initiator's `Hello`. The `Hello`s, and the `Error`
+/// sent when the windows do not overlap, are framed at
+/// `MIN_PROTOCOL_VERSION` so that a peer of any release can read them;
+/// later messages use the negotiated version. From version 3 both sides then
+/// send a `CompressionOffer` listing `compression`, and the transport
+/// switches to the algorithm picked by `negotiate_compression()`. Every
+/// handshake message uses sequence 0.
+///
+/// # Errors
+/// - `StreamingError::UnsupportedVersion` if the windows do not overlap
+///   (the peer is told with an `Error` first)
+/// - `StreamingError::Rejected` if the peer aborts
+/// - `StreamingError::Protocol` if the first message is not `Hello`, or
+///   the second not `CompressionOffer` (v3+)
+pub(crate) async fn handshake<T: Transport + ?Sized>(
+    transport: &mut T,
+    node_id: NodeId,
+    initiator: Option<SessionId>,
+    compression: &[Compression],
+) -> Result<Handshake> {
+    transport.set_compression(Compression::None)?;
+    transport.set_version(MIN_PROTOCOL_VERSION)?;
+    if let Some(session_id) = initiator {
+        transport.send(Message::hello(session_id, node_id)).await?;
+    }
+
+    let hello = transport.recv().await?.ok_or(StreamingError::Closed)?;
+    let session_id = initiator.unwrap_or(hello.session_id);
+    let (peer, remote) = match hello.payload {
+        Payload::Hello {
+            node_id,
+            min_version,
+            max_version,
+        } if hello.session_id == session_id && hello.sequence == 0 => {
+            (node_id, (min_version, max_version))
+        }
+        Payload::Error { retryable, message } => {
+            return Err(StreamingError::Rejected { retryable, message })
+        }
+        _ => {
+            return Err(StreamingError::Protocol(format!(
+                "expected Hello for session {}, got {} for session {}",
+                session_id,
+                hello.message_type(),
+                hello.session_id
+            )))
+        }
+    };
+
+    let Some(version) = negotiate_version((MIN_PROTOCOL_VERSION, PROTOCOL_VERSION), remote) else {
+        let error = StreamingError::UnsupportedVersion {
+            version: remote.1,
+            min: MIN_PROTOCOL_VERSION,
+            max: PROTOCOL_VERSION,
+        };
+        let reply = Payload::Error {
+            retryable: false,
+            message: error.to_string(),
+        };
+        // Best effort: the session is over either way
+        let _ = transport.send(Message::new(session_id, 0, reply)).await;
+        return Err(error);
+    };
+
+    if initiator.is_none() {
+        transport.send(Message::hello(session_id, node_id)).await?;
+    }
+    transport.set_version(version)?;
+
+    let compression = if version >= MessageType::CompressionOffer.since() {
+        let offer = Payload::CompressionOffer {
+            algorithms: compression.iter().map(|c| c.code()).collect(),
+        };
+        transport.send(Message::new(session_id, 0, offer)).await?;
+        let remote = recv_compression_offer(transport, session_id).await?;
+        let compression = match initiator {
+            Some(_) => negotiate_compression(compression, &remote),
+            None => negotiate_compression(&remote, compression),
+        };
+        transport.set_compression(compression)?;
+        compression
+    } else {
+        Compression::None
+    };
+
+    Ok(Handshake {
+        session_id,
+        peer,
+        version,
+        compression,
+    })
+}
+
+/// Receive the peer's `CompressionOffer`.
+///
+/// # Returns
+/// The algorithms offered, minus those unknown to this release
+async fn recv_compression_offer<T: Transport + ?Sized>(
+    transport: &mut T,
+    session_id: SessionId,
+) -> Result<Vec<Compression>> {
+    let message = transport.recv().await?.ok_or(StreamingError::Closed)?;
+    match message.payload {
+        Payload::CompressionOffer { algorithms }
+            if message.session_id == session_id && message.sequence == 0 =>
+        {
+            Ok(algorithms
+                .into_iter()
+                .filter_map(Compression::from_code)
+                .collect())
+        }
+        Payload::Error { retryable, message } => {
+            Err(StreamingError::Rejected { retryable, message })
+        }
+        other => Err(StreamingError::Protocol(format!(
+            "expected CompressionOffer for session {}, got {} for session {}",
+            session_id,
+            other.message_type(),
+            message.session_id
+        ))),
+    }
+}
@@ -0,0 +1,446 @@
+//! QUIC transport.
+//!
+//! TCP carries a whole session on one ordered byte stream, so a lost packet
+//! stalls every range behind it. Over QUIC each token range gets its own
+//! bidirectional stream on a shared connection: loss on one stream only
+//! delays that range, and each stream is flow-controlled separately.
+//!
+//! # Layout
+//!
+//! ```text
+//! QuicEndpoint (one per node, UDP socket, accepts and dials)
+//!   +-- QuicConnection (one per peer, reused across sessions)
+//!         +-- stream 0: StreamSender/StreamReceiver for range 0
+//!         +-- stream 1: ... range 1
+//!         +-- ...
+//! ```
+//!
+//! Endpoints authenticate peers with TLS: each node presents a certificate
+//! and trusts a set of certificates (a cluster CA, or for local testing the
+//! peers' self-signed certificates from `QuicIdentity::self_signed()`).
+
+use super::FramedTransport;
+use crate::data::{RangeSink, RangeSource};
+use crate::error::{Result, StreamingError};
+use crate::protocol::{SessionId, StreamPurpose, TokenRange};
+use crate::receiver::StreamReceiver;
+use crate::sender::{StreamSender, StreamSummary};
+use parking_lot::Mutex;
+use quinn::{RecvStream, SendStream, VarInt};
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+/// Transport over one QUIC bidirectional stream.
+pub type QuicTransport = FramedTransport<QuicStream>;
+
+fn transport_error(e: impl std::fmt::Display) -> StreamingError {
+    StreamingError::Transport(e.to_string())
+}
+
+/// Flow-control and liveness settings for QUIC connections.
+///
+/// Windows bound how much unacknowledged data a peer may send: per stream
+/// (one range) and per connection (all ranges together). Larger windows
+/// fill high bandwidth-delay links such as cross-DC streams.
+#[derive(Clone, Debug)]
+pub struct QuicConfig {
+    /// Bytes a peer may send on one stream before it is read.
+    pub stream_receive_window: u32,
+    /// Bytes a peer may send on the whole connection before it is read.
+    pub receive_window: u32,
+    /// Bytes we buffer for sending across all streams.
+    pub send_window: u64,
+    /// Ranges streamed concurrently per connection.
+    pub max_concurrent_streams: u32,
+    /// Interval between keep-alive packets on idle connections.
+    pub keep_alive_interval: Option<Duration>,
+}
+
+impl Default for QuicConfig {
+    fn default() -> Self {
+        Self {
+            stream_receive_window: 4 * 1024 * 1024,
+            receive_window: 32 * 1024 * 1024,
+            send_window: 32 * 1024 * 1024,
+            max_concurrent_streams: 64,
+            keep_alive_interval: Some(Duration::from_secs(5)),
+        }
+    }
+}
+
+impl QuicConfig {
+    fn transport_config(&self) -> Arc<quinn::TransportConfig> {
+        let mut transport = quinn::TransportConfig::default();
+        transport
+            .stream_receive_window(VarInt::from_u32(self.stream_receive_window))
+            .receive_window(VarInt::from_u32(self.receive_window))
+            .send_window(self.send_window)
+            .max_concurrent_bidi_streams(VarInt::from_u32(self.max_concurrent_streams))
+            .keep_alive_interval(self.keep_alive_interval);
+        Arc::new(transport)
+    }
+}
+
+/// A node's TLS certificate and private key (DER).
+#[derive(Clone)]
+pub struct QuicIdentity {
+    certificate: rustls::Certificate,
+    key: rustls::PrivateKey,
+}
+
+impl QuicIdentity {
+    /// Use an existing certificate chain leaf and key.
+    pub fn new(certificate_der: Vec<u8>, key_der: Vec<u8>) -> Self {
+        Self {
+            certificate: rustls::Certificate(certificate_der),
+            key: rustls::PrivateKey(key_der),
+        }
+    }
+
+    /// Generate a self-signed certificate, for local testing.
+    ///
+    /// # Arguments
+    /// * `names` - DNS names peers will dial this node by (e.g. "localhost")
+    pub fn self_signed(names: &[&str]) -> Result<Self> {
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        let cert = rcgen::generate_simple_self_signed(names).map_err(transport_error)?;
+        let certificate_der = cert.serialize_der().map_err(transport_error)?;
+        Ok(Self::new(certificate_der, cert.serialize_private_key_der()))
+    }
+
+    /// The certificate (DER), for peers to trust.
+    pub fn certificate_der(&self) -> &[u8] {
+        &self.certificate.0
+    }
+}
+
+// Private keys are secrets: never print them
+impl std::fmt::Debug for QuicIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuicIdentity")
+            .field(
+                "certificate",
+                &format!("{} bytes", self.certificate.0.len()),
+            )
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// A node's QUIC endpoint: accepts peers and dials them.
+///
+/// Connections to a peer are cached and reused for later sessions until
+/// they close.
+///
+/// # Example
+/// ```rust
+/// # use streaming::data::MemoryRangeStore;
+/// # use streaming::protocol::{SessionId, StreamPurpose, TokenRange};
+/// # use streaming::transport::quic::{send_ranges, QuicConfig, QuicEndpoint, QuicIdentity};
+/// # use streaming::{StreamReceiver, StreamSender};
+/// # use corelib::token::murmur3::Murmur3Token;
+/// # use corelib::NodeId;
+/// # use std::sync::Arc;
+/// # #[tokio::main]
+/// # async fn main() {
+/// let identity = QuicIdentity::self_signed(&["localhost"]).unwrap();
+/// let trusted = [identity.certificate_der().to_vec()];
+/// let config = QuicConfig::default();
+/// let local = "127.0.0.1:0".parse().unwrap();
+/// let a = QuicEndpoint::bind(local, &identity, &trusted, &config).unwrap();
+/// let b = QuicEndpoint::bind(local, &identity, &trusted, &config).unwrap();
+/// let b_addr = b.local_addr().unwrap();
+///
+/// let range = TokenRange::new(Murmur3Token(0), Murmur3Token(100));
+/// let source = Arc::new(MemoryRangeStore::new());
+/// source.insert(range, "data-1", vec![1; 100]);
+/// let sink = Arc::new(MemoryRangeStore::new());
+///
+/// let serving = tokio::spawn(async move {
+///     let connection = b.accept().await.unwrap();
+///     let mut results = connection.serve(move || StreamReceiver::new(NodeId(2), sink.clone()));
+///     let received = results.recv().await.unwrap();
+///     (b, received)
+/// });
+///
+/// let connection = a.connect(b_addr, "localhost").await.unwrap();
+/// let ranges = vec![range];
+/// let sender = |session_id| StreamSender::new(session_id, NodeId(1), source.clone());
+/// let sent = send_ranges(&connection, SessionId(1), sender, StreamPurpose::Bootstrap, ranges)
+///     .await
+///     .unwrap();
+/// let (_b, received) = serving.await.unwrap();
+/// assert_eq!(received.unwrap().bytes, sent[0].bytes);
+///
+/// // Later sessions to the same peer reuse the connection
+/// assert!(a.connect(b_addr, "localhost").await.unwrap().same_connection(&connection));
+/// # }
+/// ```
+pub struct QuicEndpoint {
+    endpoint: quinn::Endpoint,
+    connections: Mutex<HashMap<SocketAddr, quinn::Connection>>,
+}
+
+impl QuicEndpoint {
+    /// Bind an endpoint that both accepts and dials.
+    ///
+    /// # Arguments
+    /// * `addr` - Local UDP address
+    /// * `identity` - Certificate presented to peers
+    /// * `trusted` - Certificates (DER) trusted when dialing peers
+    /// * `config` - Flow-control settings, for both directions
+    ///
+    /// # Errors
+    /// `StreamingError::Transport` for invalid certificates,
+    /// `StreamingError::Io` if the socket cannot be bound
+    pub fn bind(
+        addr: SocketAddr,
+        identity: &QuicIdentity,
+        trusted: &[Vec<u8>],
+        config: &QuicConfig,
+    ) -> Result<Self> {
+        let transport = config.transport_config();
+
+        let mut server = quinn::ServerConfig::with_single_cert(
+            vec![identity.certificate.clone()],
+            identity.key.clone(),
+        )
+        .map_err(transport_error)?;
+        server.transport_config(transport.clone());
+
+        let mut roots = rustls::RootCertStore::empty();
+        for certificate in trusted {
+            roots
+                .add(&rustls::Certificate(certificate.clone()))
+                .map_err(transport_error)?;
+        }
+        let mut client = quinn::ClientConfig::with_root_certificates(roots);
+        client.transport_config(transport);
+
+        let mut endpoint = quinn::Endpoint::server(server, addr)?;
+        endpoint.set_default_client_config(client);
+        Ok(Self {
+            endpoint,
+            connections: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Local UDP address.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.endpoint.local_addr()?)
+    }
+
+    /// Connection to a peer, reusing an open one if there is one.
+    ///
+    /// # Arguments
+    /// * `addr` - Peer address
+    /// * `server_name` - Name the peer's certificate must be valid for
+    pub async fn connect(&self, addr: SocketAddr, server_name: &str) -> Result<QuicConnection> {
+        if let Some(connection) = self.connections.lock().get(&addr) {
+            if connection.close_reason().is_none() {
+                return Ok(QuicConnection(connection.clone()));
+            }
+        }
+
+        let connection = self
+            .endpoint
+            .connect(addr, server_name)
+            .map_err(transport_error)?
+            .await
+            .map_err(transport_error)?;
+
+        let mut connections = self.connections.lock();
+        // Another task may have connected meanwhile; keep whichever is open
+        let cached = connections
+            .entry(addr)
+            .and_modify(|cached| {
+                if cached.close_reason().is_some() {
+                    *cached = connection.clone();
+                }
+            })
+            .or_insert(connection);
+        Ok(QuicConnection(cached.clone()))
+    }
+
+    /// Wait for the next incoming connection.
+    ///
+    /// # Returns
+    /// The connection, or `None` once the endpoint is closed
+    pub async fn accept(&self) -> Option<QuicConnection> {
+        loop {
+            let connecting = self.endpoint.accept().await?;
+            // A failed handshake affects only that peer
+            if let Ok(connection) = connecting.await {
+                return Some(QuicConnection(connection));
+            }
+        }
+    }
+
+    /// Number of cached open connections.
+    pub fn open_connections(&self) -> usize {
+        self.connections
+            .lock()
+            .values()
+            .filter(|connection| connection.close_reason().is_none())
+            .count()
+    }
+
+    /// Close every connection and stop accepting.
+    pub fn close(&self) {
+        self.endpoint.close(VarInt::from_u32(0), b"shutdown");
+        self.connections.lock().clear();
+    }
+}
+
+/// A QUIC connection to one peer.
+#[derive(Clone, Debug)]
+pub struct QuicConnection(quinn::Connection);
+
+impl QuicConnection {
+    /// Peer address.
+    pub fn remote_address(&self) -> SocketAddr {
+        self.0.remote_address()
+    }
+
+    /// True if both handles refer to the same underlying connection.
+    pub fn same_connection(&self, other: &QuicConnection) -> bool {
+        self.0.stable_id() == other.0.stable_id()
+    }
+
+    /// Open a new stream, waiting if the concurrent stream limit is reached.
+    pub async fn open_stream(&self) -> Result<QuicTransport> {
+        let (send, recv) = self.0.open_bi().await.map_err(transport_error)?;
+        Ok(FramedTransport::new(QuicStream { send, recv }))
+    }
+
+    /// Accept the next stream opened by the peer.
+    ///
+    /// # Returns
+    /// The stream, or `None` once the connection is closed
+    pub async fn accept_stream(&self) -> Option<QuicTransport> {
+        let (send, recv) = self.0.accept_bi().await.ok()?;
+        Some(FramedTransport::new(QuicStream { send, recv }))
+    }
+
+    /// Receive sessions on every stream the peer opens, concurrently.
+    ///
+    /// # Arguments
+    /// * `receiver` - Builds the receiver for each stream. Share one
+    ///   `CheckpointStore` between them (`with_checkpoint_store()`) so a
+    ///   retried range resumes whichever stream it arrives on
+    ///
+    /// # Returns
+    /// A channel yielding each session's outcome as it finishes; it closes
+    /// once the connection is closed and all sessions are done
+    pub fn serve<K, F>(&self, receiver: F) -> mpsc::UnboundedReceiver<Result<StreamSummary>>
+    where
+        K: RangeSink + 'static,
+        F: Fn() -> StreamReceiver<K> + Send + 'static,
+    {
+        let (results, outcomes) = mpsc::unbounded_channel();
+        let connection = self.clone();
+        tokio::spawn(async move {
+            while let Some(mut transport) = connection.accept_stream().await {
+                let results = results.clone();
+                let mut stream_receiver = receiver();
+                tokio::spawn(async move {
+                    let _ = results.send(stream_receiver.run(&mut transport).await);
+                });
+            }
+        });
+        outcomes
+    }
+}
+
+/// Stream each range on its own stream of `connection`, concurrently.
+///
+/// Range `i` uses session `first_session + i`. Concurrency is bounded by
+/// the peer's `QuicConfig::max_concurrent_streams`.
+///
+/// # Arguments
+/// * `sender` - Builds the sender for a session ID. Give them all one
+///   `with_global_limiter()` to cap the connection's total rate
+///
+/// # Returns
+/// One summary per range, in range order
+///
+/// # Errors
+/// The first range that failed; other ranges may have completed
+pub async fn send_ranges<S, F>(
+    connection: &QuicConnection,
+    first_session: SessionId,
+    sender: F,
+    purpose: StreamPurpose,
+    ranges: Vec<TokenRange>,
+) -> Result<Vec<StreamSummary>>
+where
+    S: RangeSource + 'static,
+    F: Fn(SessionId) -> StreamSender<S>,
+{
+    let mut tasks = JoinSet::new();
+    for (i, range) in ranges.into_iter().enumerate() {
+        let connection = connection.clone();
+        let mut sender = sender(SessionId(first_session.0 + i as u64));
+        tasks.spawn(async move {
+            let mut transport = connection.open_stream().await?;
+            let summary = sender.run(&mut transport, purpose, vec![range]).await?;
+            Ok::<_, StreamingError>((i, summary))
+        });
+    }
+
+    let mut summaries = Vec::with_capacity(tasks.len());
+    while let Some(joined) = tasks.join_next().await {
+        summaries.push(joined.map_err(|e| {
+            if e.is_cancelled() {
+                StreamingError::Cancelled
+            } else {
+                StreamingError::Transport(e.to_string())
+            }
+        })??);
+    }
+    summaries.sort_by_key(|(i, _)| *i);
+    Ok(summaries.into_iter().map(|(_, summary)| summary).collect())
+}
+
+/// One QUIC bidirectional stream as an async byte stream.
+#[derive(Debug)]
+pub struct QuicStream {
+    send: SendStream,
+    recv: RecvStream,
+}
+
+impl AsyncRead for QuicStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.recv).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for QuicStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.send).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.send).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.send).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,31 @@
+//! TCP transport.
+
+use super::FramedTransport;
+use crate::error::Result;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+/// Transport over a TCP connection.
+pub type TcpTransport = FramedTransport<TcpStream>;
+
+impl FramedTransport<TcpStream> {
+    /// Connect to a peer.
+    ///
+    /// Nagle's algorithm is disabled: acks and control messages are small
+    /// and latency-sensitive, while chunks are large enough not to need it.
+    pub async fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream))
+    }
+
+    /// Accept the next incoming connection.
+    ///
+    /// # Returns
+    /// The transport and the peer's address
+    pub async fn accept(listener: &TcpListener) -> Result<(Self, SocketAddr)> {
+        let (stream, addr) = listener.accept().await?;
+        stream.set_nodelay(true)?;
+        Ok((Self::new(stream), addr))
+    }
+}
@@ -0,0 +1,297 @@
+//! Tests for snapshot-based node bootstrap.
+//!
+//! # Test Strategy
+//!
+//! 1. **Happy path**: a joining node pulls exactly the ranges it gains
+//! 2. **Failover**: a dead replica's ranges are pulled from another replica
+//! 3. **Resume**: a failed bootstrap continues from its progress file
+//! 4. **Retries**: transient failures retry the same source, final ones
+//!    exclude it, cancellation stops the bootstrap
+
+use async_trait::async_trait;
+use corelib::partitioner::Murmur3Partitioner;
+use corelib::ring::HashRing;
+use corelib::{Node, NodeId};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use streaming::data::{FileInfo, MemoryRangeStore, RangeSource};
+use streaming::protocol::{SessionId, StreamPurpose, TokenRange};
+use streaming::snapshot::{
+    fetch_ring_state, range_transfers, serve_ring_state, Bootstrap, BootstrapNetwork, NodeState,
+};
+use streaming::transport::duplex;
+use streaming::{StreamReceiver, StreamSender, StreamSummary, StreamingError};
+
+const JOINING: NodeId = NodeId(4);
+
+/// Deterministic contents of a range, identical on every replica.
+fn range_bytes(range: TokenRange) -> Vec<u8> {
+    format!("{}..{}", range.start.0, range.end.0).into_bytes()
+}
+
+/// Serves any requested range from generated data.
+struct Replica;
+
+#[async_trait]
+impl RangeSource for Replica {
+    async fn files(&self, range: TokenRange) -> streaming::Result<Vec<FileInfo>> {
+        Ok(vec![FileInfo {
+            name: "data".to_string(),
+            size: range_bytes(range).len() as u64,
+        }])
+    }
+
+    async fn read(
+        &self,
+        range: TokenRange,
+        _: &str,
+        offset: u64,
+        len: usize,
+    ) -> streaming::Result<Vec<u8>> {
+        let data = range_bytes(range);
+        let start = offset as usize;
+        Ok(data[start..(start + len).min(data.len())].to_vec())
+    }
+}
+
+/// A three-node cluster reachable over in-memory transports.
+struct Cluster {
+    ring: HashRing,
+    sink: Arc<MemoryRangeStore>,
+    down: Mutex<HashSet<NodeId>>,
+    /// Errors each node fails its next sessions with.
+    faults: Mutex<HashMap<NodeId, VecDeque<StreamingError>>>,
+    sessions: Mutex<Vec<(NodeId, Vec<TokenRange>)>>,
+}
+
+impl Cluster {
+    fn new() -> Self {
+        let ring = HashRing::new();
+        for i in 1..=3 {
+            ring.add_node(Node::new(NodeId(i), format!("node{}", i)), 8);
+        }
+        Self {
+            ring,
+            sink: Arc::new(MemoryRangeStore::new()),
+            down: Mutex::new(HashSet::new()),
+            faults: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl BootstrapNetwork for Cluster {
+    async fn ring_state(&self) -> streaming::Result<corelib::ring::RingState> {
+        let (mut a, mut b) = duplex(64 * 1024);
+        let (state, served) = tokio::join!(
+            fetch_ring_state(&mut a, SessionId(0), JOINING, None),
+            serve_ring_state(&mut b, NodeId(1), &self.ring),
+        );
+        served?;
+        Ok(state?.expect("no epoch was known"))
+    }
+
+    async fn stream_ranges(
+        &self,
+        source: &Node,
+        ranges: Vec<TokenRange>,
+    ) -> streaming::Result<StreamSummary> {
+        if self.down.lock().contains(&source.id) {
+            return Err(StreamingError::Transport(format!(
+                "{} is down",
+                source.name
+            )));
+        }
+        let fault = self
+            .faults
+            .lock()
+            .get_mut(&source.id)
+            .and_then(|faults| faults.pop_front());
+        if let Some(e) = fault {
+            return Err(e);
+        }
+        self.sessions.lock().push((source.id, ranges.clone()));
+
+        let (mut a, mut b) = duplex(64 * 1024);
+        let mut sender = StreamSender::new(SessionId(1), source.id, Replica);
+        let mut receiver = StreamReceiver::new(JOINING, self.sink.clone());
+        let (sent, received) = tokio::join!(
+            sender.run(&mut a, StreamPurpose::Bootstrap, ranges),
+            receiver.run(&mut b),
+        );
+        sent?;
+        received
+    }
+}
+
+fn bootstrap(dir: &tempfile::TempDir, replication_factor: usize) -> Bootstrap {
+    Bootstrap::new(
+        Node::new(JOINING, "node4"),
+        8,
+        Arc::new(Murmur3Partitioner),
+        dir.path().join("bootstrap.json"),
+    )
+    .with_replication_factor(replication_factor)
+}
+
+#[tokio::test]
+async fn test_bootstrap_pulls_gained_ranges_and_joins() {
+    let cluster = Cluster::new();
+    let before = cluster.ring.tokens();
+    let dir = tempfile::tempdir().unwrap();
+
+    let (ring, progress) = bootstrap(&dir, 2).run(&cluster).await.unwrap();
+    assert_eq!(progress.state, NodeState::Normal);
+    assert!(progress.is_complete());
+    assert!(ring.get_node(&JOINING).is_some());
+
+    // Exactly the ranges the node gained, each from a previous replica
+    let gained: Vec<_> = range_transfers(&before, &ring.tokens(), 2)
+        .into_iter()
+        .filter(|t| t.destination == JOINING)
+        .collect();
+    assert_eq!(progress.ranges.len(), gained.len());
+    for (range, transfer) in progress.ranges.iter().zip(&gained) {
+        assert_eq!(range.range, transfer.range);
+        assert!(transfer.sources.contains(&range.completed_from.unwrap()));
+        assert_eq!(
+            cluster.sink.file(range.range, "data"),
+            Some(range_bytes(range.range))
+        );
+    }
+
+    // Rerunning a finished bootstrap streams nothing, and the ring it
+    // returns has the node even though the cluster has not seen the join
+    cluster.sessions.lock().clear();
+    let (again_ring, again) = bootstrap(&dir, 2).run(&cluster).await.unwrap();
+    assert_eq!(again.state, NodeState::Normal);
+    assert!(again_ring.get_node(&JOINING).is_some());
+    assert_eq!(again_ring.tokens(), ring.tokens());
+    assert!(cluster.sessions.lock().is_empty());
+}
+
+#[tokio::test]
+async fn test_bootstrap_fails_over_to_live_replica() {
+    let cluster = Cluster::new();
+    cluster.down.lock().insert(NodeId(1));
+    let dir = tempfile::tempdir().unwrap();
+
+    let (_, progress) = bootstrap(&dir, 2).run(&cluster).await.unwrap();
+    assert!(progress.is_complete());
+    assert!(progress
+        .ranges
+        .iter()
+        .all(|r| r.completed_from != Some(NodeId(1))));
+}
+
+#[tokio::test]
+async fn test_bootstrap_resumes_from_progress() {
+    let cluster = Cluster::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    // With one replica per range, node 2's ranges have no other source
+    cluster.down.lock().insert(NodeId(2));
+    let result = bootstrap(&dir, 1).run(&cluster).await;
+    assert!(matches!(result, Err(StreamingError::Transport(_))));
+    assert!(cluster.ring.get_node(&JOINING).is_none());
+
+    cluster.down.lock().clear();
+    cluster.sessions.lock().clear();
+    let (ring, progress) = bootstrap(&dir, 1).run(&cluster).await.unwrap();
+    assert_eq!(progress.state, NodeState::Normal);
+    assert!(ring.get_node(&JOINING).is_some());
+
+    // Only the ranges that were pending are pulled again
+    let sessions = cluster.sessions.lock();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].0, NodeId(2));
+    for range in &progress.ranges {
+        assert!(cluster.sink.file(range.range, "data").is_some());
+    }
+}
+
+#[tokio::test]
+async fn test_bootstrap_rejects_existing_member() {
+    let cluster = Cluster::new();
+    let dir = tempfile::tempdir().unwrap();
+    let existing = Bootstrap::new(
+        Node::new(NodeId(1), "node1"),
+        8,
+        Arc::new(Murmur3Partitioner),
+        dir.path().join("bootstrap.json"),
+    );
+    assert!(matches!(
+        existing.run(&cluster).await,
+        Err(StreamingError::Ring(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_bootstrap_retries_transient_failures() {
+    let cluster = Cluster::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    // With one replica per range, only a retry of node 2 can succeed
+    cluster.faults.lock().insert(
+        NodeId(2),
+        VecDeque::from([
+            StreamingError::Closed,
+            StreamingError::Timeout(std::time::Duration::from_secs(1)),
+        ]),
+    );
+    let (_, progress) = bootstrap(&dir, 1).run(&cluster).await.unwrap();
+    assert!(progress.is_complete());
+    assert!(progress
+        .ranges
+        .iter()
+        .any(|r| r.completed_from == Some(NodeId(2))));
+}
+
+#[tokio::test]
+async fn test_bootstrap_gives_up_on_source_after_final_rejection() {
+    let cluster = Cluster::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    // The next session would succeed, but the rejection was final
+    cluster.faults.lock().insert(
+        NodeId(2),
+        VecDeque::from([StreamingError::Rejected {
+            retryable: false,
+            message: "range not owned".to_string(),
+        }]),
+    );
+    let result = bootstrap(&dir, 1).run(&cluster).await;
+    assert!(matches!(
+        result,
+        Err(StreamingError::Rejected {
+            retryable: false,
+            ..
+        })
+    ));
+    assert!(cluster
+        .sessions
+        .lock()
+        .iter()
+        .all(|(source, _)| *source != NodeId(2)));
+}
+
+#[tokio::test]
+async fn test_bootstrap_stops_when_cancelled() {
+    let cluster = Cluster::new();
+    let dir = tempfile::tempdir().unwrap();
+
+    // Other replicas could serve node 1's ranges, but cancelling is final
+    cluster
+        .faults
+        .lock()
+        .insert(NodeId(1), VecDeque::from([StreamingError::Cancelled]));
+    let result = bootstrap(&dir, 2).run(&cluster).await;
+    assert!(matches!(result, Err(StreamingError::Cancelled)));
+    assert!(cluster.ring.get_node(&JOINING).is_none());
+
+    // Whatever other sources delivered is kept for the next run
+    let (_, progress) = bootstrap(&dir, 2).run(&cluster).await.unwrap();
+    assert!(progress.is_complete());
+}
@@ -0,0 +1,926 @@
+//! End-to-end tests for streaming sessions.
+//!
+//! # Test Strategy
+//!
+//! 1. **Transports**: the same session over the in-memory duplex and TCP
+//! 2. **Flow control**: tiny chunks and windows still deliver every byte
+//! 3. **Failures**: version mismatch, peer errors, early close
+//! 4. **Resume**: a retried session skips what the receiver checkpointed
+//! 5. **Throttling**: per-session and global bandwidth limits (paused clock)
+//! 6. **Compression**: negotiation outcomes and bytes saved on the wire
+//! 7. **Compatibility**: sessions with a peer that only speaks
+//!    `MIN_PROTOCOL_VERSION`
+
+use async_trait::async_trait;
+use bytes::BytesMut;
+use corelib::token::murmur3::Murmur3Token;
+use corelib::NodeId;
+use parking_lot::Mutex;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use streaming::checkpoint::{FileCheckpointStore, MemoryCheckpointStore};
+use streaming::codec::{MessageCodec, HEADER_LEN};
+use streaming::data::{MemoryRangeStore, RangeSink};
+use streaming::protocol::{
+    Message, Payload, SessionId, StreamPurpose, TokenRange, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+use streaming::throttle::BandwidthLimiter;
+use streaming::transport::quic::{send_ranges, QuicConfig, QuicEndpoint, QuicIdentity};
+use streaming::transport::{duplex, FramedTransport, TcpTransport};
+use streaming::{Compression, StreamReceiver, StreamSender, StreamingError, Transport};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+use tokio::net::TcpListener;
+use tokio::time::Instant;
+use tokio_util::codec::{Decoder, Encoder};
+
+fn range(start: u64, end: u64) -> TokenRange {
+    TokenRange::new(Murmur3Token(start), Murmur3Token(end))
+}
+
+/// Two ranges: several files of varied sizes, including an empty one.
+fn source() -> (Arc<MemoryRangeStore>, Vec<TokenRange>) {
+    let ranges = vec![range(0, 100), range(100, 0)];
+    let store = Arc::new(MemoryRangeStore::new());
+    store.insert(ranges[0], "a", (0..10_000u32).map(|i| i as u8).collect());
+    store.insert(ranges[0], "b", Vec::new());
+    store.insert(ranges[1], "c", vec![42; 3]);
+    (store, ranges)
+}
+
+fn assert_copied(source: &MemoryRangeStore, sink: &MemoryRangeStore, ranges: &[TokenRange]) {
+    for &range in ranges {
+        assert_eq!(
+            source.files_of(range),
+            sink.files_of(range),
+            "range {}",
+            range
+        );
+    }
+}
+
+// ============================================================================
+// Transport Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_stream_over_memory_duplex() {
+    let (source, ranges) = source();
+    let sink = Arc::new(MemoryRangeStore::new());
+    let (mut a, mut b) = duplex(1024);
+
+    // Small chunks and window force many round trips through flow control
+    let mut sender = StreamSender::new(SessionId(9), NodeId(1), source.clone())
+        .with_chunk_size(100)
+        .with_window(4);
+    let mut receiver = StreamReceiver::new(NodeId(2), sink.clone());
+
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Bootstrap, ranges.clone()),
+        receiver.run(&mut b),
+    );
+    let (sent, received) = (sent.unwrap(), received.unwrap());
+
+    assert_eq!((sent.files, sent.bytes), (3, 10_003));
+    assert_eq!((received.files, received.bytes), (3, 10_003));
+    assert_eq!(received.session_id, SessionId(9));
+    assert_eq!((sent.peer, received.peer), (NodeId(2), NodeId(1)));
+    assert_eq!(received.ranges, ranges);
+    assert_copied(&source, &sink, &ranges);
+}
+
+#[tokio::test]
+async fn test_stream_over_tcp_loopback() {
+    let (source, ranges) = source();
+    let sink = Arc::new(MemoryRangeStore::new());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let receiving = {
+        let sink = sink.clone();
+        tokio::spawn(async move {
+            let (mut transport, _) = TcpTransport::accept(&listener).await.unwrap();
+            StreamReceiver::new(NodeId(2), sink)
+                .run(&mut transport)
+                .await
+        })
+    };
+
+    let mut transport = TcpTransport::connect(addr).await.unwrap();
+    let sent = StreamSender::new(SessionId(1), NodeId(1), source.clone())
+        .run(&mut transport, StreamPurpose::Rebalance, ranges.clone())
+        .await
+        .unwrap();
+    let received = receiving.await.unwrap().unwrap();
+
+    assert_eq!(sent.bytes, received.bytes);
+    assert_copied(&source, &sink, &ranges);
+}
+
+#[tokio::test]
+async fn test_empty_stream() {
+    let (mut a, mut b) = duplex(1024);
+    let sink = Arc::new(MemoryRangeStore::new());
+    let mut sender = StreamSender::new(SessionId(1), NodeId(1), MemoryRangeStore::new());
+    let mut receiver = StreamReceiver::new(NodeId(2), sink.clone());
+
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Decommission, vec![range(0, 10)]),
+        receiver.run(&mut b),
+    );
+    assert_eq!(sent.unwrap().files, 0);
+    assert_eq!(received.unwrap().bytes, 0);
+    assert_eq!(sink.total_bytes(), 0);
+}
+
+// ============================================================================
+// QUIC Tests
+// ============================================================================
+
+fn quic_pair(config: &QuicConfig) -> (QuicEndpoint, QuicEndpoint) {
+    let identity = QuicIdentity::self_signed(&["localhost"]).unwrap();
+    let trusted = [identity.certificate_der().to_vec()];
+    let local = "127.0.0.1:0".parse().unwrap();
+    (
+        QuicEndpoint::bind(local, &identity, &trusted, config).unwrap(),
+        QuicEndpoint::bind(local, &identity, &trusted, config).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_stream_over_quic_one_stream_per_range() {
+    let (source, ranges) = source();
+    let sink = Arc::new(MemoryRangeStore::new());
+    // One stream at a time still completes every range
+    let config = QuicConfig {
+        max_concurrent_streams: 1,
+        stream_receive_window: 1024,
+        ..QuicConfig::default()
+    };
+    let (a, b) = quic_pair(&config);
+    let b_addr = b.local_addr().unwrap();
+
+    // Shared by every stream of the connection
+    let global = BandwidthLimiter::unlimited();
+
+    let serving = {
+        let sink = sink.clone();
+        tokio::spawn(async move {
+            let connection = b.accept().await.unwrap();
+            let mut results = connection.serve(move || {
+                StreamReceiver::new(NodeId(2), sink.clone())
+                    .with_idle_timeout(Duration::from_secs(10))
+            });
+            let mut summaries = Vec::new();
+            for _ in 0..2 {
+                summaries.push(results.recv().await.unwrap().unwrap());
+            }
+            (b, summaries)
+        })
+    };
+
+    let connection = a.connect(b_addr, "localhost").await.unwrap();
+    let sender = |session_id| {
+        StreamSender::new(session_id, NodeId(1), source.clone())
+            .with_global_limiter(global.clone())
+            .with_compression([Compression::Zstd])
+    };
+    let sent = send_ranges(
+        &connection,
+        SessionId(100),
+        sender,
+        StreamPurpose::Bootstrap,
+        ranges.clone(),
+    )
+    .await
+    .unwrap();
+    let (_b, received) = serving.await.unwrap();
+
+    assert_eq!(sent.len(), 2);
+    for (i, summary) in sent.iter().enumerate() {
+        assert_eq!(summary.session_id, SessionId(100 + i as u64));
+        assert_eq!(summary.ranges, vec![ranges[i]]);
+        assert_eq!(summary.compression, Compression::Zstd);
+    }
+    assert_eq!(received.iter().map(|s| s.bytes).sum::<u64>(), 10_003);
+    assert_copied(&source, &sink, &ranges);
+    assert_eq!(global.metrics().total_bytes, 10_003);
+
+    // The connection is reused rather than re-established
+    let again = a.connect(b_addr, "localhost").await.unwrap();
+    assert!(again.same_connection(&connection));
+    assert_eq!(a.open_connections(), 1);
+}
+
+#[tokio::test]
+async fn test_quic_retry_resumes_from_shared_checkpoints() {
+    let (source, ranges) = source();
+    let sink = FlakySink::new("a", 5_000);
+    let checkpoints = Arc::new(MemoryCheckpointStore::new());
+    let (a, b) = quic_pair(&QuicConfig::default());
+    let b_addr = b.local_addr().unwrap();
+
+    let serving = {
+        let sink = sink.clone();
+        let checkpoints = checkpoints.clone();
+        tokio::spawn(async move {
+            let connection = b.accept().await.unwrap();
+            let mut results = connection.serve(move || {
+                StreamReceiver::new(NodeId(2), sink.clone())
+                    .with_checkpoint_store(checkpoints.clone())
+                    .with_checkpoint_interval(1_000)
+            });
+            let mut outcomes = Vec::new();
+            for _ in 0..4 {
+                outcomes.push(results.recv().await.unwrap());
+            }
+            (b, outcomes)
+        })
+    };
+
+    let connection = a.connect(b_addr, "localhost").await.unwrap();
+    let sender =
+        |session_id| StreamSender::new(session_id, NodeId(1), source.clone()).with_chunk_size(100);
+    let send = || {
+        send_ranges(
+            &connection,
+            SessionId(100),
+            sender,
+            StreamPurpose::Bootstrap,
+            ranges.clone(),
+        )
+    };
+    assert!(send().await.is_err());
+
+    // The retry's new streams find the first attempt's checkpoint
+    let sent = send().await.unwrap();
+    let (_b, outcomes) = serving.await.unwrap();
+    assert_eq!((sent[0].resumed, sent[0].bytes), (5_000, 5_000));
+    assert_eq!(outcomes.iter().filter(|o| o.is_err()).count(), 1);
+    assert_copied(&source, &sink.store, &ranges);
+    assert!(checkpoints.is_empty());
+}
+
+#[tokio::test]
+async fn test_quic_rejects_untrusted_peer() {
+    let config = QuicConfig::default();
+    let local = "127.0.0.1:0".parse().unwrap();
+    let server_identity = QuicIdentity::self_signed(&["localhost"]).unwrap();
+    let other = QuicIdentity::self_signed(&["localhost"]).unwrap();
+
+    let b = QuicEndpoint::bind(local, &server_identity, &[], &config).unwrap();
+    let b_addr = b.local_addr().unwrap();
+    tokio::spawn(async move { b.accept().await });
+
+    // The client only trusts a different certificate
+    let a =
+        QuicEndpoint::bind(local, &other, &[other.certificate_der().to_vec()], &config).unwrap();
+    assert!(matches!(
+        a.connect(b_addr, "localhost").await,
+        Err(StreamingError::Transport(_))
+    ));
+}
+
+// ============================================================================
+// Failure Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_version_mismatch_is_rejected() {
+    let (mut a, mut b) = duplex(1024);
+    let future = Message::new(
+        SessionId(1),
+        0,
+        Payload::Hello {
+            node_id: NodeId(1),
+            min_version: 100,
+            max_version: 101,
+        },
+    );
+    a.send(future).await.unwrap();
+
+    let result = StreamReceiver::new(NodeId(2), MemoryRangeStore::new())
+        .run(&mut b)
+        .await;
+    assert!(matches!(
+        result,
+        Err(StreamingError::UnsupportedVersion { version: 101, .. })
+    ));
+
+    // The old side is told why before the session ends
+    let reply = a.recv().await.unwrap().unwrap();
+    assert!(matches!(
+        reply.payload,
+        Payload::Error {
+            retryable: false,
+            ..
+        }
+    ));
+}
+
+/// A sink whose disk is full.
+struct FullSink;
+
+#[async_trait]
+impl RangeSink for FullSink {
+    async fn write(&self, _: TokenRange, _: &str, _: u64, _: &[u8]) -> streaming::Result<()> {
+        Err(StreamingError::Storage("disk full".to_string()))
+    }
+
+    async fn finish_file(&self, _: TokenRange, _: &str, _: u64) -> streaming::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_receiver_storage_failure_aborts_sender() {
+    let (source, ranges) = source();
+    let (mut a, mut b) = duplex(64 * 1024);
+    let mut sender = StreamSender::new(SessionId(1), NodeId(1), source);
+    let mut receiver = StreamReceiver::new(NodeId(2), FullSink);
+
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Bootstrap, ranges),
+        receiver.run(&mut b),
+    );
+    assert!(matches!(received, Err(StreamingError::Storage(_))));
+    match sent {
+        Err(StreamingError::Rejected { message, .. }) => assert!(message.contains("disk full")),
+        other => panic!("expected rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_silent_sender_times_out() {
+    let (mut a, mut b) = duplex(1024);
+    a.send(Message::hello(SessionId(1), NodeId(1)))
+        .await
+        .unwrap();
+
+    // Connected, but never sends its CompressionOffer
+    let result = StreamReceiver::new(NodeId(2), MemoryRangeStore::new())
+        .with_idle_timeout(Duration::from_secs(5))
+        .run(&mut b)
+        .await;
+    match result {
+        Err(e @ StreamingError::Timeout(_)) => assert!(e.is_retryable()),
+        other => panic!("expected timeout, got {:?}", other),
+    }
+    drop(a);
+}
+
+#[tokio::test]
+async fn test_sender_disconnect_is_detected() {
+    let (mut a, mut b) = duplex(1024);
+    a.send(Message::hello(SessionId(1), NodeId(1)))
+        .await
+        .unwrap();
+    drop(a);
+
+    let result = StreamReceiver::new(NodeId(2), MemoryRangeStore::new())
+        .run(&mut b)
+        .await;
+    assert!(matches!(
+        result,
+        Err(StreamingError::Closed) | Err(StreamingError::Io(_))
+    ));
+}
+
+/// A transport that rewrites the messages it sends.
+struct Tampering<F> {
+    inner: FramedTransport<DuplexStream>,
+    tamper: F,
+}
+
+#[async_trait]
+impl<F: FnMut(Message) -> Message + Send> Transport for Tampering<F> {
+    async fn send(&mut self, message: Message) -> streaming::Result<()> {
+        self.inner.send((self.tamper)(message)).await
+    }
+
+    async fn recv(&mut self) -> streaming::Result<Option<Message>> {
+        self.inner.recv().await
+    }
+
+    fn set_version(&mut self, version: u16) -> streaming::Result<()> {
+        self.inner.set_version(version)
+    }
+
+    fn set_compression(&mut self, compression: Compression) -> streaming::Result<()> {
+        self.inner.set_compression(compression)
+    }
+}
+
+#[tokio::test]
+async fn test_chunk_offset_overflow_is_rejected() {
+    let (source, ranges) = source();
+    let sink = Arc::new(MemoryRangeStore::new());
+    let (a, mut b) = duplex(64 * 1024);
+    let mut a = Tampering {
+        inner: a,
+        tamper: |mut message: Message| {
+            if let Payload::FileChunk { offset, .. } = &mut message.payload {
+                *offset = u64::MAX;
+            }
+            message
+        },
+    };
+
+    let mut sender = StreamSender::new(SessionId(1), NodeId(1), source);
+    let mut receiver = StreamReceiver::new(NodeId(2), sink.clone());
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Bootstrap, ranges),
+        receiver.run(&mut b),
+    );
+    assert!(matches!(received, Err(StreamingError::Protocol(_))));
+    assert!(matches!(
+        sent,
+        Err(StreamingError::Rejected {
+            retryable: false,
+            ..
+        })
+    ));
+    assert_eq!(sink.total_bytes(), 0);
+}
+
+// ============================================================================
+// Resume Tests
+// ============================================================================
+
+/// A sink that fails once, when a file is written at or past an offset.
+struct FlakySink {
+    store: Arc<MemoryRangeStore>,
+    fail_at: Mutex<Option<(&'static str, u64)>>,
+}
+
+impl FlakySink {
+    fn new(name: &'static str, offset: u64) -> Arc<Self> {
+        Arc::new(Self {
+            store: Arc::new(MemoryRangeStore::new()),
+            fail_at: Mutex::new(Some((name, offset))),
+        })
+    }
+}
+
+#[async_trait]
+impl RangeSink for FlakySink {
+    async fn write(
+        &self,
+        range: TokenRange,
+        name: &str,
+        offset: u64,
+        data: &[u8],
+    ) -> streaming::Result<()> {
+        let failing = {
+            let mut fail_at = self.fail_at.lock();
+            let failing = matches!(*fail_at, Some((n, at)) if n == name && offset >= at);
+            if failing {
+                *fail_at = None;
+            }
+            failing
+        };
+        if failing {
+            return Err(StreamingError::Storage("transient failure".to_string()));
+        }
+        self.store.write(range, name, offset, data).await
+    }
+
+    async fn finish_file(&self, range: TokenRange, name: &str, size: u64) -> streaming::Result<()> {
+        self.store.finish_file(range, name, size).await
+    }
+}
+
+#[tokio::test]
+async fn test_failed_session_resumes_mid_file() {
+    let (source, ranges) = source();
+    let sink = FlakySink::new("a", 5_000);
+    let checkpoints = Arc::new(MemoryCheckpointStore::new());
+    let mut receiver = StreamReceiver::new(NodeId(2), sink.clone())
+        .with_checkpoint_store(checkpoints.clone())
+        .with_checkpoint_interval(1_000);
+
+    let (mut a, mut b) = duplex(64 * 1024);
+    let mut sender =
+        StreamSender::new(SessionId(5), NodeId(1), source.clone()).with_chunk_size(100);
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Bootstrap, ranges.clone()),
+        receiver.run(&mut b),
+    );
+    assert!(matches!(
+        sent,
+        Err(StreamingError::Rejected {
+            retryable: true,
+            ..
+        })
+    ));
+    assert!(received.is_err());
+    assert_eq!(checkpoints.len(), 1);
+
+    // Retrying the session continues file "a" at byte 5000
+    let (mut a, mut b) = duplex(64 * 1024);
+    let mut sender =
+        StreamSender::new(SessionId(5), NodeId(1), source.clone()).with_chunk_size(100);
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Bootstrap, ranges.clone()),
+        receiver.run(&mut b),
+    );
+    let (sent, received) = (sent.unwrap(), received.unwrap());
+    assert_eq!((sent.resumed, sent.bytes), (5_000, 5_003));
+    assert_eq!((received.resumed, received.bytes), (5_000, 5_003));
+    assert_copied(&source, &sink.store, &ranges);
+    assert!(checkpoints.is_empty());
+}
+
+#[tokio::test]
+async fn test_resume_skips_complete_ranges_after_restart() {
+    let (source, ranges) = source();
+    let dir = tempfile::tempdir().unwrap();
+    let sink = FlakySink::new("c", 0);
+    let receiver = || {
+        let store = FileCheckpointStore::new(dir.path()).unwrap();
+        StreamReceiver::new(NodeId(2), sink.clone()).with_checkpoint_store(Arc::new(store))
+    };
+
+    let (mut a, mut b) = duplex(64 * 1024);
+    let mut sender = StreamSender::new(SessionId(6), NodeId(1), source.clone());
+    let mut first = receiver();
+    let (sent, _) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Rebalance, ranges.clone()),
+        first.run(&mut b),
+    );
+    assert!(sent.is_err());
+
+    // A new receiver (e.g. after a restart) reads the checkpoint from disk:
+    // the first range is skipped entirely
+    let (mut a, mut b) = duplex(64 * 1024);
+    let mut sender = StreamSender::new(SessionId(6), NodeId(1), source.clone());
+    let mut second = receiver();
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Rebalance, ranges.clone()),
+        second.run(&mut b),
+    );
+    let sent = sent.unwrap();
+    assert_eq!((sent.files, sent.bytes, sent.resumed), (1, 3, 10_000));
+    assert_eq!(received.unwrap().files, 1);
+    assert_copied(&source, &sink.store, &ranges);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+// ============================================================================
+// Throttling Tests
+// ============================================================================
+
+#[tokio::test(start_paused = true)]
+async fn test_session_bandwidth_limit() {
+    let (source, ranges) = source();
+    let (mut a, mut b) = duplex(64 * 1024);
+    let mut sender = StreamSender::new(SessionId(1), NodeId(1), source)
+        .with_chunk_size(1_000)
+        .with_bandwidth_limit(20_000);
+    let mut receiver = StreamReceiver::new(NodeId(2), MemoryRangeStore::new());
+
+    let start = Instant::now();
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Rebalance, ranges),
+        receiver.run(&mut b),
+    );
+    let elapsed = start.elapsed();
+    assert_eq!(sent.unwrap().bytes, received.unwrap().bytes);
+
+    // 10003 bytes at 20000 B/s, less the 2000-byte burst
+    assert!(elapsed >= Duration::from_millis(390), "{:?}", elapsed);
+    assert!(elapsed <= Duration::from_millis(500), "{:?}", elapsed);
+    let metrics = sender.metrics();
+    assert_eq!(metrics.limit, Some(20_000));
+    assert_eq!(metrics.total_bytes, 10_003);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_global_limit_is_shared_between_sessions() {
+    let global = BandwidthLimiter::new(Some(20_000));
+    let session = |id| {
+        let (source, ranges) = source();
+        let global = global.clone();
+        async move {
+            let (mut a, mut b) = duplex(64 * 1024);
+            let mut sender = StreamSender::new(SessionId(id), NodeId(1), source)
+                .with_chunk_size(1_000)
+                .with_global_limiter(global);
+            let mut receiver = StreamReceiver::new(NodeId(2), MemoryRangeStore::new());
+            let (sent, received) = tokio::join!(
+                sender.run(&mut a, StreamPurpose::Rebalance, ranges),
+                receiver.run(&mut b),
+            );
+            received.unwrap();
+            sent.unwrap()
+        }
+    };
+
+    let start = Instant::now();
+    let (first, second) = tokio::join!(session(1), session(2));
+    let elapsed = start.elapsed();
+    assert_eq!(first.bytes + second.bytes, 20_006);
+
+    // Both sessions together run at the global limit
+    assert!(elapsed >= Duration::from_millis(890), "{:?}", elapsed);
+    assert!(elapsed <= Duration::from_millis(1_000), "{:?}", elapsed);
+    let metrics = global.metrics();
+    assert_eq!(metrics.total_bytes, 20_006);
+    assert!(metrics.throughput > 0 && metrics.throughput <= 25_000);
+}
+
+// ============================================================================
+// Compression Tests
+// ============================================================================
+
+/// One range of JSON documents, like most range data.
+fn json_source() -> (Arc<MemoryRangeStore>, Vec<TokenRange>) {
+    let ranges = vec![range(0, 100)];
+    let store = Arc::new(MemoryRangeStore::new());
+    let json: String = (0..2_000)
+        .map(|i| {
+            format!(
+                r#"{{"key":"user:{}","value":{{"name":"user {}","active":true}}}}"#,
+                i, i
+            )
+        })
+        .collect();
+    store.insert(ranges[0], "docs", json.into_bytes());
+    (store, ranges)
+}
+
+#[tokio::test]
+async fn test_compression_is_negotiated() {
+    use Compression::{Lz4, Zstd};
+    let cases: [(&[Compression], &[Compression], Compression); 4] = [
+        (&[Lz4, Zstd], &[Lz4, Zstd], Lz4),
+        // The sender's preference wins among what both accept
+        (&[Zstd, Lz4], &[Lz4, Zstd], Zstd),
+        (&[Zstd, Lz4], &[Lz4], Lz4),
+        // Nothing in common: uncompressed
+        (&[Lz4, Zstd], &[], Compression::None),
+    ];
+    for (offered, accepted, expected) in cases {
+        let (source, ranges) = json_source();
+        let sink = Arc::new(MemoryRangeStore::new());
+        let (mut a, mut b) = duplex(64 * 1024);
+        let mut sender = StreamSender::new(SessionId(1), NodeId(1), source.clone())
+            .with_compression(offered.iter().copied());
+        let mut receiver =
+            StreamReceiver::new(NodeId(2), sink.clone()).with_compression(accepted.iter().copied());
+
+        let (sent, received) = tokio::join!(
+            sender.run(&mut a, StreamPurpose::Bootstrap, ranges.clone()),
+            receiver.run(&mut b),
+        );
+        let (sent, received) = (sent.unwrap(), received.unwrap());
+        assert_eq!(sent.compression, expected, "{:?} / {:?}", offered, accepted);
+        assert_eq!(received.compression, expected);
+        assert_copied(&source, &sink, &ranges);
+    }
+}
+
+/// A byte stream that counts the bytes written to it.
+struct CountingStream {
+    inner: DuplexStream,
+    written: Arc<AtomicUsize>,
+}
+
+impl AsyncRead for CountingStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for CountingStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            self.written.fetch_add(n, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// Stream `json_source()` with `compression` offered.
+///
+/// # Returns
+/// Bytes the sender wrote to the wire
+async fn wire_bytes(compression: Option<Compression>) -> usize {
+    let (source, ranges) = json_source();
+    let sink = Arc::new(MemoryRangeStore::new());
+    let (a, b) = tokio::io::duplex(64 * 1024);
+    let written = Arc::new(AtomicUsize::new(0));
+    let mut a = FramedTransport::new(CountingStream {
+        inner: a,
+        written: written.clone(),
+    });
+    let mut b = FramedTransport::new(b);
+
+    let mut sender = StreamSender::new(SessionId(1), NodeId(1), source.clone())
+        .with_chunk_size(16 * 1024)
+        .with_compression(compression);
+    let mut receiver = StreamReceiver::new(NodeId(2), sink.clone());
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Rebalance, ranges.clone()),
+        receiver.run(&mut b),
+    );
+    assert_eq!(sent.unwrap().bytes, received.unwrap().bytes);
+    assert_copied(&source, &sink, &ranges);
+    written.load(Ordering::Relaxed)
+}
+
+#[tokio::test]
+async fn test_compression_shrinks_wire_traffic() {
+    let plain = wire_bytes(None).await;
+    let lz4 = wire_bytes(Some(Compression::Lz4)).await;
+    let zstd = wire_bytes(Some(Compression::Zstd)).await;
+
+    assert!(plain > 100_000, "{}", plain);
+    assert!(lz4 * 3 < plain, "lz4 {} vs {}", lz4, plain);
+    assert!(zstd * 3 < plain, "zstd {} vs {}", zstd, plain);
+}
+
+// ============================================================================
+// Compatibility Tests
+// ============================================================================
+
+/// A peer from a release that only speaks `MIN_PROTOCOL_VERSION`.
+///
+/// Runs the current handshake, but narrows every `Hello` it sends or
+/// receives to `MIN_PROTOCOL_VERSION` so it negotiates what such a release
+/// would, and like one rejects any frame whose header carries a newer
+/// version.
+struct OldPeer {
+    io: DuplexStream,
+    codec: MessageCodec,
+    buf: BytesMut,
+}
+
+impl OldPeer {
+    fn new(io: DuplexStream) -> Self {
+        Self {
+            io,
+            codec: MessageCodec::new(),
+            buf: BytesMut::new(),
+        }
+    }
+}
+
+fn narrow_hello(message: &mut Message) {
+    if let Payload::Hello {
+        min_version,
+        max_version,
+        ..
+    } = &mut message.payload
+    {
+        (*min_version, *max_version) = (MIN_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION);
+    }
+}
+
+#[async_trait]
+impl Transport for OldPeer {
+    async fn send(&mut self, mut message: Message) -> streaming::Result<()> {
+        narrow_hello(&mut message);
+        let mut frame = BytesMut::new();
+        self.codec.encode(message, &mut frame)?;
+        self.io.write_all(&frame).await?;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> streaming::Result<Option<Message>> {
+        loop {
+            if self.buf.len() >= HEADER_LEN {
+                let version = u16::from_le_bytes([self.buf[2], self.buf[3]]);
+                if version > MIN_PROTOCOL_VERSION {
+                    return Err(StreamingError::UnsupportedVersion {
+                        version,
+                        min: MIN_PROTOCOL_VERSION,
+                        max: MIN_PROTOCOL_VERSION,
+                    });
+                }
+            }
+            if let Some(mut message) = self.codec.decode(&mut self.buf)? {
+                narrow_hello(&mut message);
+                return Ok(Some(message));
+            }
+            if self.io.read_buf(&mut self.buf).await? == 0 {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn set_version(&mut self, version: u16) -> streaming::Result<()> {
+        assert_eq!(version, MIN_PROTOCOL_VERSION);
+        self.codec.set_version(version)
+    }
+
+    fn set_compression(&mut self, compression: Compression) -> streaming::Result<()> {
+        assert_eq!(compression, Compression::None);
+        self.codec.set_compression(compression)
+    }
+}
+
+#[tokio::test]
+async fn test_current_sender_streams_to_old_receiver() {
+    let (source, ranges) = source();
+    let sink = Arc::new(MemoryRangeStore::new());
+    let (a, b) = tokio::io::duplex(1024);
+    let mut a = FramedTransport::new(a);
+    let mut b = OldPeer::new(b);
+
+    let mut sender = StreamSender::new(SessionId(1), NodeId(1), source.clone());
+    let mut receiver = StreamReceiver::new(NodeId(2), sink.clone());
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Bootstrap, ranges.clone()),
+        receiver.run(&mut b),
+    );
+    let (sent, received) = (sent.unwrap(), received.unwrap());
+
+    assert_eq!((sent.version, received.version), (1, 1));
+    assert_eq!(sent.compression, Compression::None);
+    assert_eq!(sent.bytes, received.bytes);
+    assert_copied(&source, &sink, &ranges);
+}
+
+#[tokio::test]
+async fn test_old_sender_streams_to_current_receiver() {
+    let (source, ranges) = source();
+    let sink = Arc::new(MemoryRangeStore::new());
+    let (a, b) = tokio::io::duplex(1024);
+    let mut a = OldPeer::new(a);
+    let mut b = FramedTransport::new(b);
+
+    let mut sender = StreamSender::new(SessionId(1), NodeId(1), source.clone());
+    let mut receiver = StreamReceiver::new(NodeId(2), sink.clone());
+    let (sent, received) = tokio::join!(
+        sender.run(&mut a, StreamPurpose::Bootstrap, ranges.clone()),
+        receiver.run(&mut b),
+    );
+    let (sent, received) = (sent.unwrap(), received.unwrap());
+
+    assert_eq!((sent.version, received.version), (1, 1));
+    assert_eq!(sent.bytes, received.bytes);
+    assert_copied(&source, &sink, &ranges);
+}
+
+#[tokio::test]
+async fn test_version_mismatch_reply_is_framed_at_min_version() {
+    let (a, b) = tokio::io::duplex(1024);
+    let mut a = OldPeer::new(a);
+
+    // A transport left at the newest version by an earlier session
+    let mut codec = MessageCodec::new();
+    codec.set_version(PROTOCOL_VERSION).unwrap();
+    let mut b = FramedTransport::with_codec(b, codec);
+
+    // Sent at MIN_PROTOCOL_VERSION by a release that also stopped speaking it
+    let hello = Message::new(
+        SessionId(1),
+        0,
+        Payload::Hello {
+            node_id: NodeId(1),
+            min_version: 0,
+            max_version: 0,
+        },
+    );
+    let mut frame = BytesMut::new();
+    MessageCodec::new().encode(hello, &mut frame).unwrap();
+    a.io.write_all(&frame).await.unwrap();
+
+    let result = StreamReceiver::new(NodeId(2), MemoryRangeStore::new())
+        .run(&mut b)
+        .await;
+    assert!(matches!(
+        result,
+        Err(StreamingError::UnsupportedVersion { version: 0, .. })
+    ));
+
+    // OldPeer fails on any frame newer than MIN_PROTOCOL_VERSION
+    let reply = a.recv().await.unwrap().unwrap();
+    assert!(matches!(
+        reply.payload,
+        Payload::Error {
+            retryable: false,
+            ..
+        }
+    ));
+}